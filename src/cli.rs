@@ -1,25 +1,431 @@
 use dashmap::DashMap;
 use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 use std::env;
+use std::ffi::OsString;
+use std::path::PathBuf;
 
-#[derive(Default, Clone, Debug)]
+use crate::events::EventsTarget;
+use crate::filter::WindowPattern;
+pub use crate::full_policy::FullPolicy;
+use crate::grid::MAX_GRID_CELLS;
+pub use crate::grid::{CellRegion, ExpiryPolicy, GridPlacementMode};
+use crate::resolve::ResolveFormat;
+use crate::rules::WindowRule;
+
+pub const USAGE: &str = "Usage: startt [options] [--] <executable|document|URL> [args...]\n       startt --profile <file|name> [options] [args...]\n       startt --resolve [--format json|tsv] [--wait SECONDS] <target> [args...]\n       startt --gui\n       startt replay <session.jsonl> -g GRID [grid options]";
+
+/// Grid requested with `-g ROWSxCOLS[mMONITOR][:cols=W,..][:rows=W,..]` or
+/// `-g auto[mMONITOR]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GridSpec {
+    pub rows: u32,
+    pub cols: u32,
+    pub monitor: i32,
+    /// Relative row heights; empty for uniform rows.
+    pub row_weights: Vec<u32>,
+    /// Relative column widths; empty for uniform columns.
+    pub col_weights: Vec<u32>,
+    /// Rows and columns follow the number of windows (`auto`); `rows` x `cols` is the
+    /// starting grid.
+    pub auto: bool,
+}
+
+fn write_weights(f: &mut std::fmt::Formatter<'_>, name: &str, weights: &[u32]) -> std::fmt::Result {
+    if weights.is_empty() {
+        return Ok(());
+    }
+    let weights: Vec<String> = weights.iter().map(u32::to_string).collect();
+    write!(f, ":{}={}", name, weights.join(","))
+}
+
+impl std::fmt::Display for GridSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.auto {
+            return write!(f, "autom{}", self.monitor);
+        }
+        write!(f, "{}x{}m{}", self.rows, self.cols, self.monitor)?;
+        write_weights(f, "cols", &self.col_weights)?;
+        write_weights(f, "rows", &self.row_weights)
+    }
+}
+
+impl std::str::FromStr for GridSpec {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_grid_spec(s)
+    }
+}
+
+/// Monitors requested with `--grid-monitors`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GridMonitors {
+    /// Repeat the grid on every attached monitor.
+    All,
+    /// Repeat the grid on these monitors, in this order.
+    List(Vec<i32>),
+}
+
+impl std::str::FromStr for GridMonitors {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("all") {
+            return Ok(GridMonitors::All);
+        }
+        let monitors = s
+            .split(',')
+            .map(|m| m.trim().parse::<i32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| GRID_MONITORS_EXPECTED)?;
+        Ok(GridMonitors::List(monitors))
+    }
+}
+
+impl std::fmt::Display for GridMonitors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GridMonitors::All => write!(f, "all"),
+            GridMonitors::List(monitors) => {
+                let monitors: Vec<String> = monitors.iter().map(i32::to_string).collect();
+                write!(f, "{}", monitors.join(","))
+            }
+        }
+    }
+}
+
+/// Parent cell requested with `-apc ROWxCOL[+ROWSxCOLS][mMONITOR]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CellSpec {
+    pub region: CellRegion,
+    pub monitor: Option<i32>,
+}
+
+impl std::str::FromStr for CellSpec {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_cell_spec(s)
+    }
+}
+
+impl std::fmt::Display for CellSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let CellRegion {
+            row,
+            col,
+            rows,
+            cols,
+        } = self.region;
+        write!(f, "{}x{}", row, col)?;
+        if (rows, cols) != (1, 1) {
+            write!(f, "+{}x{}", rows, cols)?;
+        }
+        if let Some(monitor) = self.monitor {
+            write!(f, "m{}", monitor)?;
+        }
+        Ok(())
+    }
+}
+
+/// How the launched application's windows are discovered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FindMode {
+    /// Match processes carrying `STARTT=<our pid>` in their environment (default).
+    #[default]
+    EnvTag,
+    /// Most recently created windows whose executable matches the target (`--find-recent`).
+    Recent,
+    /// Oldest matching windows created after launch (`--find-oldest`).
+    Oldest,
+}
+
+/// Serde adapters that keep grid and cell values in their command line spelling
+/// (`"2x4m0,2x4m1"`, `"0x0+2x1"`, `"all"`) so profiles read like the flags they replace.
+mod spelled {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::ffi::OsString;
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    pub fn serialize_grids<S: Serializer>(
+        grids: &[super::GridSpec],
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        let grids: Vec<String> = grids.iter().map(ToString::to_string).collect();
+        s.serialize_str(&grids.join(","))
+    }
+
+    pub fn deserialize_grids<'de, D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<Vec<super::GridSpec>, D::Error> {
+        super::parse_grid_specs(&String::deserialize(d)?).map_err(D::Error::custom)
+    }
+
+    pub fn serialize_value<T: Display, S: Serializer>(value: &T, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(value)
+    }
+
+    pub fn deserialize_value<'de, T, D>(d: D) -> Result<T, D::Error>
+    where
+        T: FromStr<Err = &'static str>,
+        D: Deserializer<'de>,
+    {
+        String::deserialize(d)?.parse().map_err(D::Error::custom)
+    }
+
+    pub fn serialize_option<T: Display, S: Serializer>(
+        value: &Option<T>,
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => s.collect_str(value),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize_option<'de, T, D>(d: D) -> Result<Option<T>, D::Error>
+    where
+        T: FromStr<Err = &'static str>,
+        D: Deserializer<'de>,
+    {
+        Option::<String>::deserialize(d)?
+            .map(|s| s.parse().map_err(D::Error::custom))
+            .transpose()
+    }
+
+    pub fn serialize_list<T: Display, S: Serializer>(
+        values: &[T],
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        s.collect_seq(values.iter().map(ToString::to_string))
+    }
+
+    pub fn deserialize_list<'de, T, D>(d: D) -> Result<Vec<T>, D::Error>
+    where
+        T: FromStr<Err = &'static str>,
+        D: Deserializer<'de>,
+    {
+        Vec::<String>::deserialize(d)?
+            .iter()
+            .map(|s| s.parse().map_err(D::Error::custom))
+            .collect()
+    }
+
+    pub fn serialize_command<S: Serializer>(command: &[OsString], s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(command.iter().map(|arg| arg.to_string_lossy()))
+    }
+
+    pub fn deserialize_command<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<OsString>, D::Error> {
+        Ok(Vec::<String>::deserialize(d)?
+            .into_iter()
+            .map(OsString::from)
+            .collect())
+    }
+}
+
+/// Every option startt accepts. Also the schema of a `--profile` file: each field
+/// may be set under its own name, anything left out keeps its default, and a name
+/// that isn't a field is an error.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct CommandLineOptions {
     pub follow_children: bool,
     pub follow_forever: bool,
     pub timeout_secs: Option<u64>,
+    /// What happens to a window when its timeout runs out (`--timeout-action`).
+    #[serde(
+        serialize_with = "spelled::serialize_value",
+        deserialize_with = "spelled::deserialize_value"
+    )]
+    pub timeout_action: ExpiryPolicy,
     pub flash_topmost_ms: u64,
     pub should_hide_title_bar: bool,
     pub should_hide_border: bool,
     pub shake_duration: u64,
+    pub sleep_duration_ms: u64,
+    /// One grid per monitor, in cell order; empty when `-g` was not given.
+    #[serde(
+        rename = "grid",
+        serialize_with = "spelled::serialize_grids",
+        deserialize_with = "spelled::deserialize_grids",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub grids: Vec<GridSpec>,
+    #[serde(
+        serialize_with = "spelled::serialize_option",
+        deserialize_with = "spelled::deserialize_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub grid_monitors: Option<GridMonitors>,
     pub fit_grid: bool,
+    /// Pixels between neighbouring grid cells.
+    pub grid_gutter: i32,
+    /// Pixels kept free between the grid and the edges of the monitor.
+    pub grid_margin: i32,
     pub reserve_parent_cell: bool,
-    pub assign_parent_cell: Option<(u32, u32, Option<i32>)>,
+    #[serde(
+        serialize_with = "spelled::serialize_option",
+        deserialize_with = "spelled::deserialize_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub assign_parent_cell: Option<CellSpec>,
     pub hide_taskbar: bool,
     pub show_taskbar: bool,
     pub grid_placement_mode: GridPlacementMode,
+    /// Move a staged window into a cell as soon as its occupant closes (`--backfill`).
+    pub backfill: bool,
+    /// Shift windows into lower cells to close the gap a closed window leaves (`--compact`).
+    pub compact: bool,
+    /// What a new window does when every cell is taken (`--on-full`).
+    #[serde(
+        serialize_with = "spelled::serialize_value",
+        deserialize_with = "spelled::deserialize_value"
+    )]
+    pub on_full: FullPolicy,
+    /// `--rule` window-matching rules, tried in order before the free-cell search.
+    #[serde(
+        serialize_with = "spelled::serialize_list",
+        deserialize_with = "spelled::deserialize_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub rules: Vec<WindowRule>,
+    /// Window classes to skip on top of the built-in list (`--exclude-class`).
+    #[serde(
+        serialize_with = "spelled::serialize_list",
+        deserialize_with = "spelled::deserialize_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub exclude_classes: Vec<WindowPattern>,
+    /// If any are given, only windows whose class matches one are considered.
+    #[serde(
+        serialize_with = "spelled::serialize_list",
+        deserialize_with = "spelled::deserialize_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub include_classes: Vec<WindowPattern>,
+    /// Window titles to skip (`--exclude-title`).
+    #[serde(
+        serialize_with = "spelled::serialize_list",
+        deserialize_with = "spelled::deserialize_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub exclude_titles: Vec<WindowPattern>,
+    /// If any are given, only windows whose title matches one are considered.
+    #[serde(
+        serialize_with = "spelled::serialize_list",
+        deserialize_with = "spelled::deserialize_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub include_titles: Vec<WindowPattern>,
+    /// `--events jsonl[=PATH]`: machine-readable event stream.
+    #[serde(
+        serialize_with = "spelled::serialize_option",
+        deserialize_with = "spelled::deserialize_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub events: Option<EventsTarget>,
+    /// `--record PATH`: session file for `startt replay`.
+    #[serde(skip)]
+    pub record: Option<PathBuf>,
+    /// Output format of `--resolve`.
+    pub resolve_format: ResolveFormat,
+    /// Seconds `--resolve` waits for a window before giving up.
+    pub wait_secs: u64,
+    /// Seconds shutdown waits for closed processes to exit before terminating them.
+    pub grace_secs: u64,
+    /// Keep the launched tree in a Job Object (`--contain`).
+    pub contain: bool,
+    /// CPU cap for the contained tree, in percent of all cores.
+    pub cpu_limit_percent: Option<u32>,
+    /// Memory cap for the contained tree, in MiB.
+    pub memory_limit_mb: Option<u64>,
     pub retain_parent_focus: bool,
     pub retain_launcher_focus: bool,
     pub keep_open: bool,
+    pub debug_chrome: bool,
+    pub find_mode: FindMode,
+    pub num_recent: usize,
+    #[serde(skip)]
+    pub gui: bool,
+    #[serde(skip)]
+    pub show_version: bool,
+    /// Profile file or `[profiles]` entry the options were layered on (`--profile`).
+    #[serde(skip)]
+    pub profile: Option<String>,
+    /// Print the effective options as a profile and exit (`--print-profile`).
+    #[serde(skip)]
+    pub print_profile: bool,
+    /// Print the launched PIDs and windows and exit, leaving them alone (`--resolve`).
+    #[serde(skip)]
+    pub resolve: bool,
+    /// The target followed by its arguments, exactly as given after the last option.
+    #[serde(
+        serialize_with = "spelled::serialize_command",
+        deserialize_with = "spelled::deserialize_command"
+    )]
+    pub command: Vec<OsString>,
+}
+
+impl Default for CommandLineOptions {
+    fn default() -> Self {
+        Self {
+            follow_children: false,
+            follow_forever: false,
+            timeout_secs: None,
+            timeout_action: ExpiryPolicy::default(),
+            flash_topmost_ms: 0,
+            should_hide_title_bar: false,
+            should_hide_border: false,
+            shake_duration: 500,
+            sleep_duration_ms: 0,
+            grids: Vec::new(),
+            grid_monitors: None,
+            fit_grid: false,
+            grid_gutter: 0,
+            grid_margin: 0,
+            reserve_parent_cell: false,
+            assign_parent_cell: None,
+            hide_taskbar: false,
+            show_taskbar: false,
+            grid_placement_mode: GridPlacementMode::default(),
+            backfill: false,
+            compact: false,
+            on_full: FullPolicy::default(),
+            rules: Vec::new(),
+            exclude_classes: Vec::new(),
+            include_classes: Vec::new(),
+            exclude_titles: Vec::new(),
+            include_titles: Vec::new(),
+            events: None,
+            record: None,
+            resolve_format: ResolveFormat::default(),
+            wait_secs: 10,
+            grace_secs: 5,
+            contain: false,
+            cpu_limit_percent: None,
+            memory_limit_mb: None,
+            retain_parent_focus: false,
+            retain_launcher_focus: false,
+            keep_open: false,
+            debug_chrome: false,
+            find_mode: FindMode::default(),
+            num_recent: 1,
+            gui: false,
+            show_version: false,
+            profile: None,
+            print_profile: false,
+            resolve: false,
+            command: Vec::new(),
+        }
+    }
+}
+
+fn patterns(patterns: &[WindowPattern]) -> Vec<String> {
+    patterns.iter().map(ToString::to_string).collect()
 }
 
 impl std::fmt::Display for CommandLineOptions {
@@ -30,52 +436,864 @@ impl std::fmt::Display for CommandLineOptions {
     follow_children: {},
     follow_forever: {},
     timeout_secs: {:?},
+    timeout_action: {},
     flash_topmost_ms: {},
     should_hide_title_bar: {},
     should_hide_border: {},
     shake_duration: {},
+    sleep_duration_ms: {},
+    grids: {:?},
+    grid_monitors: {:?},
     fit_grid: {},
+    grid_gutter: {},
+    grid_margin: {},
     reserve_parent_cell: {},
     assign_parent_cell: {:?},
     hide_taskbar: {},
     show_taskbar: {},
     grid_placement_mode: {:?},
+    backfill: {},
+    compact: {},
+    on_full: {},
+    rules: {:?},
+    exclude_classes: {:?},
+    include_classes: {:?},
+    exclude_titles: {:?},
+    include_titles: {:?},
+    events: {:?},
+    record: {:?},
+    resolve_format: {:?},
+    wait_secs: {},
+    grace_secs: {},
+    contain: {},
+    cpu_limit_percent: {:?},
+    memory_limit_mb: {:?},
     retain_parent_focus: {},
     retain_launcher_focus: {},
-    keep_open: {}
+    keep_open: {},
+    debug_chrome: {},
+    find_mode: {:?},
+    num_recent: {},
+    profile: {:?},
+    resolve: {},
+    command: {:?}
 }}",
             self.follow_children,
             self.follow_forever,
             self.timeout_secs,
+            self.timeout_action,
             self.flash_topmost_ms,
             self.should_hide_title_bar,
             self.should_hide_border,
             self.shake_duration,
+            self.sleep_duration_ms,
+            self.grids,
+            self.grid_monitors,
             self.fit_grid,
+            self.grid_gutter,
+            self.grid_margin,
             self.reserve_parent_cell,
             self.assign_parent_cell,
             self.hide_taskbar,
             self.show_taskbar,
             self.grid_placement_mode,
+            self.backfill,
+            self.compact,
+            self.on_full,
+            self.rules
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            patterns(&self.exclude_classes),
+            patterns(&self.include_classes),
+            patterns(&self.exclude_titles),
+            patterns(&self.include_titles),
+            self.events,
+            self.record,
+            self.resolve_format,
+            self.wait_secs,
+            self.grace_secs,
+            self.contain,
+            self.cpu_limit_percent,
+            self.memory_limit_mb,
             self.retain_parent_focus,
             self.retain_launcher_focus,
-            self.keep_open
+            self.keep_open,
+            self.debug_chrome,
+            self.find_mode,
+            self.num_recent,
+            self.profile,
+            self.resolve,
+            self.command
         )
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum GridPlacementMode {
-    FirstFree,
-    Sequential,
+impl CommandLineOptions {
+    /// The grids to lay out, one per monitor. With `--grid-monitors` the first `-g`
+    /// grid is repeated on each requested monitor (`all` meaning monitors
+    /// `0..monitor_count`).
+    pub fn grid_specs(&self, monitor_count: i32) -> Vec<GridSpec> {
+        let Some(template) = self.grids.first() else {
+            return Vec::new();
+        };
+        let monitors: Vec<i32> = match &self.grid_monitors {
+            None => return self.grids.clone(),
+            Some(GridMonitors::All) => (0..monitor_count.max(1)).collect(),
+            Some(GridMonitors::List(monitors)) => monitors.clone(),
+        };
+        monitors
+            .into_iter()
+            .map(|monitor| GridSpec {
+                monitor,
+                ..template.clone()
+            })
+            .collect()
+    }
+
+    /// Rewrites http(s) URLs in `command` to `debugchrome://` when `--debug-chrome` is set.
+    /// Returns true if any argument was changed.
+    pub fn rewrite_debug_chrome_urls(&mut self) -> bool {
+        if !self.debug_chrome {
+            return false;
+        }
+        let mut did_mutate = false;
+        for arg in self.command.iter_mut() {
+            let s = arg.to_string_lossy();
+            if s.starts_with("http://") || s.starts_with("https://") {
+                *arg = OsString::from(format!("debugchrome://{}", s));
+                did_mutate = true;
+            }
+        }
+        did_mutate
+    }
 }
 
-impl Default for GridPlacementMode {
-    fn default() -> Self {
-        GridPlacementMode::FirstFree
+/// Errors produced while parsing the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliError {
+    MissingValue {
+        flag: String,
+        expected: &'static str,
+    },
+    InvalidValue {
+        flag: String,
+        value: String,
+        expected: &'static str,
+    },
+    /// The `--profile` could not be found, read or parsed.
+    Profile(String),
+    /// An argument before the command that looks like an option but isn't one.
+    UnknownOption(String),
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::MissingValue { flag, expected } => {
+                write!(f, "expected {} after {}", expected, flag)
+            }
+            CliError::InvalidValue {
+                flag,
+                value,
+                expected,
+            } => write!(
+                f,
+                "invalid value '{}' for {}: expected {}",
+                value, flag, expected
+            ),
+            CliError::Profile(message) => write!(f, "{}", message),
+            CliError::UnknownOption(flag) => {
+                write!(
+                    f,
+                    "unknown option {} (use -- before a command that starts with -)",
+                    flag
+                )
+            }
+        }
     }
 }
 
+impl std::error::Error for CliError {}
+
+/// How many values an option consumes.
+enum Arity {
+    /// Takes no value, or an inline `=true`/`=false` (see [`switch`]).
+    Flag,
+    /// Always takes the next argument; the string describes it for error messages.
+    Required(&'static str),
+    /// Takes the next argument only if the predicate accepts it.
+    Optional(fn(&str) -> bool),
+}
+
+/// One entry in the option table: every spelling, its arity and how it updates the options.
+/// `apply` returns a description of the expected value on failure.
+struct OptionSpec {
+    names: &'static [&'static str],
+    arity: Arity,
+    apply: fn(&mut CommandLineOptions, Option<&str>) -> Result<(), &'static str>,
+}
+
+fn parse_value<T: std::str::FromStr>(
+    value: Option<&str>,
+    expected: &'static str,
+) -> Result<T, &'static str> {
+    value.and_then(|v| v.parse().ok()).ok_or(expected)
+}
+
+/// The value of a flag: on when given bare, otherwise `--flag=true|false` (or `on|off`,
+/// `yes|no`, `1|0`), so a flag a profile turns on can be turned off again.
+fn switch(value: Option<&str>) -> Result<bool, &'static str> {
+    const EXPECTED: &str = "true or false";
+    match value.map(str::to_ascii_lowercase).as_deref() {
+        None | Some("true" | "on" | "yes" | "1") => Ok(true),
+        Some("false" | "off" | "no" | "0") => Ok(false),
+        Some(_) => Err(EXPECTED),
+    }
+}
+
+fn starts_with_digit(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_digit())
+}
+
+static OPTIONS: &[OptionSpec] = &[
+    OptionSpec {
+        names: &["--profile"],
+        arity: Arity::Required("a profile file or name"),
+        apply: |o, v| {
+            o.profile = v.map(str::to_string);
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--print-profile"],
+        arity: Arity::Flag,
+        apply: |o, v| {
+            o.print_profile = switch(v)?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--resolve"],
+        arity: Arity::Flag,
+        apply: |o, v| {
+            o.resolve = switch(v)?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--format"],
+        arity: Arity::Required("json or tsv"),
+        apply: |o, v| {
+            o.resolve_format = parse_value(v, "json or tsv")?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--wait"],
+        arity: Arity::Required("number of seconds"),
+        apply: |o, v| {
+            o.wait_secs = parse_value(v, "number of seconds")?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--grace"],
+        arity: Arity::Required("number of seconds"),
+        apply: |o, v| {
+            o.grace_secs = parse_value(v, "number of seconds")?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--contain"],
+        arity: Arity::Flag,
+        apply: |o, v| {
+            o.contain = switch(v)?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--cpu-limit"],
+        arity: Arity::Required("percent"),
+        apply: |o, v| {
+            o.cpu_limit_percent = Some(parse_value(v, "percent")?);
+            o.contain = true;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--memory-limit"],
+        arity: Arity::Required("MiB"),
+        apply: |o, v| {
+            o.memory_limit_mb = Some(parse_value(v, "MiB")?);
+            o.contain = true;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--version"],
+        arity: Arity::Flag,
+        apply: |o, v| {
+            o.show_version = switch(v)?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--gui"],
+        arity: Arity::Flag,
+        apply: |o, v| {
+            o.gui = switch(v)?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["-f", "--follow"],
+        arity: Arity::Flag,
+        apply: |o, v| {
+            o.follow_children = switch(v)?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        // `--follow-forver` is the historical spelling and is still accepted.
+        names: &["-F", "--follow-forever", "--follow-forver"],
+        arity: Arity::Flag,
+        apply: |o, v| {
+            o.follow_forever = switch(v)?;
+            o.follow_children |= o.follow_forever;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["-fo", "--find-oldest"],
+        arity: Arity::Flag,
+        apply: |o, v| {
+            o.find_mode = match switch(v)? {
+                true => FindMode::Oldest,
+                false => FindMode::default(),
+            };
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["-fr", "--find-recent"],
+        arity: Arity::Flag,
+        apply: |o, v| {
+            o.find_mode = match switch(v)? {
+                true => FindMode::Recent,
+                false => FindMode::default(),
+            };
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["-nr", "--num-recent"],
+        arity: Arity::Required("a number"),
+        apply: |o, v| {
+            o.num_recent = parse_value(v, "a number")?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["-sl", "--sleep-duration"],
+        arity: Arity::Required("milliseconds"),
+        apply: |o, v| {
+            o.sleep_duration_ms = parse_value(v, "milliseconds")?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["-sd", "--shake-duration"],
+        arity: Arity::Required("milliseconds"),
+        apply: |o, v| {
+            o.shake_duration = parse_value(v, "milliseconds")?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["-t", "--timeout"],
+        arity: Arity::Required("number of seconds"),
+        apply: |o, v| {
+            o.timeout_secs = Some(parse_value(v, "number of seconds")?);
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--timeout-action"],
+        arity: Arity::Required("close, minimize or overflow=MONITOR"),
+        apply: |o, v| {
+            o.timeout_action = v.unwrap_or_default().parse()?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["-T", "--flash-topmost"],
+        arity: Arity::Optional(|s| s.parse::<u64>().is_ok()),
+        apply: |o, v| {
+            o.flash_topmost_ms = match v {
+                Some(_) => parse_value(v, "milliseconds")?,
+                None => 10,
+            };
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["-hT", "--hide-title-bar"],
+        arity: Arity::Flag,
+        apply: |o, v| {
+            o.should_hide_title_bar = switch(v)?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["-hB", "--hide-border"],
+        arity: Arity::Flag,
+        apply: |o, v| {
+            o.should_hide_border = switch(v)?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["-htb", "--hide-taskbar"],
+        arity: Arity::Flag,
+        apply: |o, v| {
+            o.hide_taskbar = switch(v)?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["-stb", "--show-taskbar"],
+        arity: Arity::Flag,
+        apply: |o, v| {
+            o.show_taskbar = switch(v)?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["-dbg", "--debug-chrome"],
+        arity: Arity::Flag,
+        apply: |o, v| {
+            o.debug_chrome = switch(v)?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["-g", "--grid"],
+        arity: Arity::Required(GRID_SPEC_EXPECTED),
+        apply: |o, v| {
+            o.grids = parse_grid_specs(v.unwrap_or_default())?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--grid-monitors"],
+        arity: Arity::Required(GRID_MONITORS_EXPECTED),
+        apply: |o, v| {
+            o.grid_monitors = Some(parse_value(v, GRID_MONITORS_EXPECTED)?);
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["-fg", "--fit-grid"],
+        arity: Arity::Flag,
+        apply: |o, v| {
+            o.fit_grid = switch(v)?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--grid-gutter"],
+        arity: Arity::Required("pixels"),
+        apply: |o, v| {
+            o.grid_gutter = parse_value::<u16>(v, "pixels")?.into();
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--grid-margin"],
+        arity: Arity::Required("pixels"),
+        apply: |o, v| {
+            o.grid_margin = parse_value::<u16>(v, "pixels")?.into();
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--grid-placement"],
+        arity: Arity::Required("firstfree or sequential"),
+        apply: |o, v| {
+            o.grid_placement_mode = parse_value(v, "firstfree or sequential")?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--backfill"],
+        arity: Arity::Flag,
+        apply: |o, v| {
+            o.backfill = switch(v)?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--compact"],
+        arity: Arity::Flag,
+        apply: |o, v| {
+            o.compact = switch(v)?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--on-full"],
+        arity: Arity::Required(
+            "close-oldest, queue, minimize-oldest, overflow-monitor=MONITOR, stack or grow",
+        ),
+        apply: |o, v| {
+            o.on_full = v.unwrap_or_default().parse()?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--rule"],
+        arity: Arity::Required("a rule such as 'title~=panic => 0x0'"),
+        apply: |o, v| {
+            o.rules.push(v.unwrap_or_default().parse()?);
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--exclude-class"],
+        arity: Arity::Required("a glob or re:REGEX"),
+        apply: |o, v| {
+            o.exclude_classes.push(v.unwrap_or_default().parse()?);
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--include-class"],
+        arity: Arity::Required("a glob or re:REGEX"),
+        apply: |o, v| {
+            o.include_classes.push(v.unwrap_or_default().parse()?);
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--exclude-title"],
+        arity: Arity::Required("a glob or re:REGEX"),
+        apply: |o, v| {
+            o.exclude_titles.push(v.unwrap_or_default().parse()?);
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--include-title"],
+        arity: Arity::Required("a glob or re:REGEX"),
+        apply: |o, v| {
+            o.include_titles.push(v.unwrap_or_default().parse()?);
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--events"],
+        arity: Arity::Required("jsonl or jsonl=PATH"),
+        apply: |o, v| {
+            o.events = Some(v.unwrap_or_default().parse()?);
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["--record"],
+        arity: Arity::Required("a session file"),
+        apply: |o, v| {
+            o.record = v.map(PathBuf::from);
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["-rpc", "--reserve-parent-cell"],
+        arity: Arity::Flag,
+        apply: |o, v| {
+            o.reserve_parent_cell = switch(v)?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["-apc", "--assign-parent-cell"],
+        arity: Arity::Optional(starts_with_digit),
+        apply: |o, v| {
+            o.assign_parent_cell = Some(match v {
+                Some(cell) => parse_cell_spec(cell)?,
+                None => CellSpec::default(),
+            });
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["-rpf", "--retain-parent-focus"],
+        arity: Arity::Flag,
+        apply: |o, v| {
+            o.retain_parent_focus = switch(v)?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["-rlf", "--retain-launcher-focus"],
+        arity: Arity::Flag,
+        apply: |o, v| {
+            o.retain_launcher_focus = switch(v)?;
+            Ok(())
+        },
+    },
+    OptionSpec {
+        names: &["-ko", "--keep-open"],
+        arity: Arity::Flag,
+        apply: |o, v| {
+            o.keep_open = switch(v)?;
+            Ok(())
+        },
+    },
+];
+
+fn find_option(name: &str) -> Option<&'static OptionSpec> {
+    OPTIONS.iter().find(|spec| spec.names.contains(&name))
+}
+
+/// Splits `ROWSxCOLS[mMONITOR]` style strings into the `ROWSxCOLS` part and the monitor index.
+fn split_monitor(s: &str) -> Result<(&str, Option<i32>), ()> {
+    match s.find('m') {
+        Some(idx) => {
+            let monitor = s[idx + 1..].parse::<i32>().map_err(|_| ())?;
+            Ok((&s[..idx], Some(monitor)))
+        }
+        None => Ok((s, None)),
+    }
+}
+
+fn split_pair(s: &str) -> Result<(u32, u32), ()> {
+    let (a, b) = s.split_once('x').ok_or(())?;
+    Ok((a.parse().map_err(|_| ())?, b.parse().map_err(|_| ())?))
+}
+
+const GRID_MONITORS_EXPECTED: &str = "all or a list of monitors such as 0,1";
+
+const GRID_SPEC_EXPECTED: &str =
+    "ROWSxCOLS[mDISPLAY#][:cols=W,..][:rows=W,..] or auto[mDISPLAY#][,...]";
+
+/// Parses a comma separated weight list with exactly `count` positive entries.
+fn parse_weights(s: &str, count: u32) -> Result<Vec<u32>, &'static str> {
+    const EXPECTED: &str = "one positive weight per row/column, e.g. cols=2,1,1";
+    let weights = s
+        .split(',')
+        .map(|w| w.trim().parse::<u32>().ok().filter(|&w| w > 0))
+        .collect::<Option<Vec<_>>>()
+        .ok_or(EXPECTED)?;
+    if weights.len() != count as usize {
+        return Err(EXPECTED);
+    }
+    Ok(weights)
+}
+
+/// Parses a grid argument such as `2x2`, `2x2m1` (monitor is zero-based),
+/// `2x3:cols=2,1,1:rows=3,1` (relative column widths and row heights) or `autom1` (a
+/// grid that starts at 1x1 and follows the number of windows).
+pub fn parse_grid_spec(s: &str) -> Result<GridSpec, &'static str> {
+    let mut parts = s.split(':');
+    let head = parts.next().unwrap_or_default();
+    if let Some(monitor) = head.strip_prefix("auto") {
+        if parts.next().is_some() {
+            return Err("auto grids take no row or column weights");
+        }
+        let (rest, monitor) = split_monitor(monitor).map_err(|_| GRID_SPEC_EXPECTED)?;
+        if !rest.is_empty() {
+            return Err(GRID_SPEC_EXPECTED);
+        }
+        return Ok(GridSpec {
+            rows: 1,
+            cols: 1,
+            monitor: monitor.unwrap_or(0),
+            row_weights: Vec::new(),
+            col_weights: Vec::new(),
+            auto: true,
+        });
+    }
+    let (rc, monitor) = split_monitor(head).map_err(|_| GRID_SPEC_EXPECTED)?;
+    let (rows, cols) = split_pair(rc).map_err(|_| GRID_SPEC_EXPECTED)?;
+    if rows == 0 || cols == 0 {
+        return Err("at least one row and one column");
+    }
+    if rows
+        .checked_mul(cols)
+        .is_none_or(|cells| cells > MAX_GRID_CELLS)
+    {
+        return Err("at most 4096 cells");
+    }
+    let mut spec = GridSpec {
+        rows,
+        cols,
+        monitor: monitor.unwrap_or(0),
+        row_weights: Vec::new(),
+        col_weights: Vec::new(),
+        auto: false,
+    };
+    for part in parts {
+        match part.split_once('=') {
+            Some(("cols", weights)) => spec.col_weights = parse_weights(weights, cols)?,
+            Some(("rows", weights)) => spec.row_weights = parse_weights(weights, rows)?,
+            _ => return Err(GRID_SPEC_EXPECTED),
+        }
+    }
+    Ok(spec)
+}
+
+/// Parses a comma separated list of grids, one per monitor, such as `2x4m0,2x4m1`.
+///
+/// Weight lists also use commas, so a new grid only starts at an element that begins
+/// with `ROWSxCOLS`.
+pub fn parse_grid_specs(s: &str) -> Result<Vec<GridSpec>, &'static str> {
+    let starts_grid = |part: &str| {
+        let head = part.split([':', 'm']).next().unwrap_or_default();
+        head == "auto" || split_pair(head).is_ok()
+    };
+    let mut specs: Vec<String> = Vec::new();
+    for part in s.split(',') {
+        match specs.last_mut() {
+            Some(spec) if !starts_grid(part) => {
+                spec.push(',');
+                spec.push_str(part);
+            }
+            _ => specs.push(part.to_string()),
+        }
+    }
+    specs.iter().map(|spec| parse_grid_spec(spec)).collect()
+}
+
+/// Parses a parent cell such as `1x1`, `1x1m1` or `0x0+2x1` (spanning two rows and
+/// one column from cell 0x0).
+pub fn parse_cell_spec(s: &str) -> Result<CellSpec, &'static str> {
+    const EXPECTED: &str = "ROWxCOL[+ROWSxCOLS][mDISPLAY#]";
+    let (rc, monitor) = split_monitor(s).map_err(|_| EXPECTED)?;
+    let (cell, span) = match rc.split_once('+') {
+        Some((cell, span)) => (cell, Some(span)),
+        None => (rc, None),
+    };
+    let (row, col) = split_pair(cell).map_err(|_| EXPECTED)?;
+    let (rows, cols) = match span {
+        Some(span) => split_pair(span).map_err(|_| EXPECTED)?,
+        None => (1, 1),
+    };
+    if rows == 0 || cols == 0 {
+        return Err("a span of at least one row and one column");
+    }
+    Ok(CellSpec {
+        region: CellRegion {
+            row,
+            col,
+            rows,
+            cols,
+        },
+        monitor,
+    })
+}
+
+/// Parses startt's arguments (without the program name) into `CommandLineOptions`.
+///
+/// Options are read until the first argument that is not an option, or up to `--`; it and
+/// everything after it become `command`. An unknown option before the command is an
+/// error. Values may be given as a separate argument, as `--name=value`,
+/// or attached to `-g` (`-g2x2m1`). Flags take `--name=false` to turn off what a
+/// profile turned on.
+///
+/// With `--profile`, the arguments are applied on top of the profile: flags override the
+/// profile's values and a command on the line replaces the profile's command.
+pub fn parse_args<I, S>(args: I) -> Result<CommandLineOptions, CliError>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<OsString>,
+{
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    let options = parse_args_onto(CommandLineOptions::default(), args.clone())?;
+    let Some(profile) = options.profile.as_deref() else {
+        return Ok(options);
+    };
+    let base =
+        crate::profile::load_profile(profile).map_err(|e| CliError::Profile(e.to_string()))?;
+    parse_args_onto(base, args)
+}
+
+/// Applies `args` to `options`; see [`parse_args`].
+fn parse_args_onto(
+    mut options: CommandLineOptions,
+    args: Vec<OsString>,
+) -> Result<CommandLineOptions, CliError> {
+    let mut args = args.into_iter().peekable();
+
+    while let Some(arg) = args.next() {
+        let arg_str = arg.to_string_lossy().into_owned();
+
+        let (name, inline_value) = match arg_str.split_once('=') {
+            Some((name, value)) if arg_str.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg_str.as_str(), None),
+        };
+        let (spec, inline_value) = match find_option(name) {
+            Some(spec) => (spec, inline_value),
+            None if arg_str.starts_with("-g")
+                && arg_str.len() > 2
+                && !arg_str.starts_with("--") =>
+            {
+                (
+                    find_option("-g").expect("-g is in the option table"),
+                    Some(arg_str[2..].to_string()),
+                )
+            }
+            None if arg_str == "--" => {
+                options.command = args.collect();
+                break;
+            }
+            None if arg_str.starts_with('-') && arg_str.len() > 1 => {
+                return Err(CliError::UnknownOption(name.to_string()));
+            }
+            None => {
+                options.command = std::iter::once(arg).chain(args).collect();
+                break;
+            }
+        };
+
+        let value = match spec.arity {
+            Arity::Flag => inline_value,
+            Arity::Required(expected) => match inline_value {
+                Some(value) => Some(value),
+                None => Some(
+                    args.next()
+                        .ok_or_else(|| CliError::MissingValue {
+                            flag: name.to_string(),
+                            expected,
+                        })?
+                        .to_string_lossy()
+                        .into_owned(),
+                ),
+            },
+            Arity::Optional(accepts) => match inline_value {
+                Some(value) => Some(value),
+                None => match args.peek().map(|next| next.to_string_lossy().into_owned()) {
+                    Some(next) if accepts(&next) => {
+                        args.next();
+                        Some(next)
+                    }
+                    _ => None,
+                },
+            },
+        };
+
+        (spec.apply)(&mut options, value.as_deref()).map_err(|expected| {
+            CliError::InvalidValue {
+                flag: name.to_string(),
+                value: value.clone().unwrap_or_default(),
+                expected,
+            }
+        })?;
+    }
+    Ok(options)
+}
+
 pub static CMD_OPTIONS: Lazy<DashMap<&'static str, CommandLineOptions>> = Lazy::new(DashMap::new);
 
 /// Access the parsed command-line options.
@@ -83,7 +1301,7 @@ pub fn get_command_line_options() -> CommandLineOptions {
     CMD_OPTIONS
         .get("options")
         .map(|entry| entry.value().clone())
-        .unwrap_or_else(CommandLineOptions::default)
+        .unwrap_or_default()
 }
 
 /// Update the command-line options.
@@ -92,7 +1310,7 @@ pub fn update_command_line_options(new_options: CommandLineOptions) {
 }
 
 /// Prints the program name and version, then exits.
-fn print_version_and_exit() -> ! {
+pub fn print_version_and_exit() -> ! {
     let exe = env::args().next().unwrap_or_else(|| "startt".to_string());
     let exe = exe
         .rsplit_once(std::path::MAIN_SEPARATOR)
@@ -104,71 +1322,188 @@ fn print_version_and_exit() -> ! {
     println!("{} {}", exe, env!("CARGO_PKG_VERSION"));
     std::process::exit(0);
 }
-pub fn parse_command_line() {
-    let mut args = env::args_os().skip(1).peekable();
-    // Get or insert default options for mutation
-    let mut options = CMD_OPTIONS
-        .entry("options")
-        .or_insert_with(CommandLineOptions::default);
 
-    while let Some(arg) = args.next() {
-        let arg_str = arg.to_string_lossy();
-
-        match arg_str.as_ref() {
-            "--version" => print_version_and_exit(),
-            "-f" | "--follow" => options.follow_children = true,
-            "-F" | "--follow-forever" => {
-                options.follow_children = true;
-                options.follow_forever = true;
-            }
-            "-t" | "--timeout" => {
-                let t_arg = args
-                    .next()
-                    .expect("Expected number of seconds after -t/--timeout");
-                options.timeout_secs = Some(
-                    t_arg
-                        .to_string_lossy()
-                        .parse()
-                        .expect("Invalid timeout value"),
-                );
-            }
-            "-T" | "--flash-topmost" => {
-                // Accept an optional value (milliseconds)
-                if let Some(val) = args.peek() {
-                    if let Ok(ms) = val.to_string_lossy().parse::<u64>() {
-                        args.next(); // consume
-                        options.flash_topmost_ms = ms;
-                    } else {
-                        options.flash_topmost_ms = 10; // default
-                    }
-                } else {
-                    options.flash_topmost_ms = 10; // default
-                }
-            }
-            "-hT" | "--hide-title-bar" => options.should_hide_title_bar = true,
-            "-hB" | "--hide-border" => options.should_hide_border = true,
-            "--shake-duration" | "-sd" => {
-                if let Some(duration_arg) = args.peek() {
-                    if let Ok(duration) = duration_arg.to_string_lossy().parse::<u64>() {
-                        options.shake_duration = duration;
-                        args.next(); // Consume the value
-                        println!("Shake duration set to {} ms", options.shake_duration);
-                    } else {
-                        panic!("Invalid shake duration value");
-                    }
-                } else {
-                    panic!("Expected milliseconds after --shake-duration/-sd");
-                }
-            }
-            "--fit-grid" | "-fg" => options.fit_grid = true,
-            "--reserve-parent-cell" | "-rpc" => options.reserve_parent_cell = true,
-            "--assign-parent-cell" | "-apc" => {
-                options.assign_parent_cell = Some((0, 0, None)); // Example default value
-            }
-            "--retain-parent-focus" | "-rpf" => options.retain_parent_focus = true,
-            "--retain-launcher-focus" | "-rlf" => options.retain_launcher_focus = true,
-            "-ko" | "--keep-open" => options.keep_open = true,
-            _ => {}
+/// Parses the process arguments and stores the result for `get_command_line_options`.
+pub fn parse_command_line() -> Result<CommandLineOptions, CliError> {
+    let options = parse_args(env::args_os().skip(1))?;
+    update_command_line_options(options.clone());
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grids_with_too_many_cells_are_rejected() {
+        for grid in ["65536x65536", "65535x65537", "4097x1", "65x64"] {
+            assert_eq!(parse_grid_spec(grid), Err("at most 4096 cells"), "{}", grid);
+            assert!(parse_args(["-g", grid, "app"]).is_err(), "{}", grid);
+        }
+        let spec = parse_grid_spec("64x64").unwrap();
+        assert_eq!(spec.rows * spec.cols, MAX_GRID_CELLS);
+    }
+
+    #[test]
+    fn values_are_taken_according_to_arity() {
+        // (arguments, what is left as the command)
+        let cases: &[(&[&str], &[&str])] = &[
+            // Flags only take an inline value
+            (&["-f", "x"], &["x"]),
+            (&["--follow=false", "x"], &["x"]),
+            // Required values are the next argument, even one that looks like an option
+            (&["-t", "5", "x"], &["x"]),
+            (&["--timeout=5", "x", "-f"], &["x", "-f"]),
+            (&["--exclude-title", "-f", "x"], &["x"]),
+            // Optional values only when the next argument looks like one
+            (&["-T", "200", "x"], &["x"]),
+            (&["-T", "x", "200"], &["x", "200"]),
+            (&["-apc", "1x1", "x"], &["x"]),
+            (&["-apc", "x"], &["x"]),
+            (&["-g2x2m1", "x"], &["x"]),
+            // Everything from the command on, or after `--`, is the command's
+            (
+                &["x", "-g", "2x2", "--bogus"],
+                &["x", "-g", "2x2", "--bogus"],
+            ),
+            (&["-f", "--", "-x", "-f"], &["-x", "-f"]),
+            (&["-", "-f"], &["-", "-f"]),
+        ];
+        for (args, command) in cases {
+            let options = parse_args(*args).unwrap();
+            assert_eq!(options.command, *command, "{:?}", args);
+        }
+        assert_eq!(
+            parse_args(["-T", "200", "x"]).unwrap().flash_topmost_ms,
+            200
+        );
+        assert_eq!(parse_args(["-T", "x"]).unwrap().flash_topmost_ms, 10);
+        assert_eq!(
+            parse_args(["-apc", "x"]).unwrap().assign_parent_cell,
+            Some(CellSpec::default())
+        );
+        let options = parse_args(["--exclude-title", "-f", "x"]).unwrap();
+        assert!(!options.follow_children);
+        assert_eq!(options.exclude_titles[0].to_string(), "-f");
+    }
+
+    #[test]
+    fn bad_arguments_before_the_command_are_errors() {
+        let cases: &[(&[&str], CliError)] = &[
+            (
+                &["-t"],
+                CliError::MissingValue {
+                    flag: "-t".into(),
+                    expected: "number of seconds",
+                },
+            ),
+            (
+                &["--timeout=soon", "x"],
+                CliError::InvalidValue {
+                    flag: "--timeout".into(),
+                    value: "soon".into(),
+                    expected: "number of seconds",
+                },
+            ),
+            (&["--bogus", "x"], CliError::UnknownOption("--bogus".into())),
+            (
+                &["--bogus=1", "x"],
+                CliError::UnknownOption("--bogus".into()),
+            ),
+            (&["-f", "-x", "app"], CliError::UnknownOption("-x".into())),
+        ];
+        for (args, error) in cases {
+            assert_eq!(parse_args(*args).unwrap_err(), *error, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn aliases_set_the_same_option() {
+        let cases: &[(&[&str], Option<&str>)] = &[
+            (&["-f", "--follow"], None),
+            (&["-fo", "--find-oldest"], None),
+            (&["-nr", "--num-recent"], Some("3")),
+            (&["-sl", "--sleep-duration"], Some("250")),
+            (&["-sd", "--shake-duration"], Some("700")),
+            (&["-t", "--timeout"], Some("3")),
+            (&["-T", "--flash-topmost"], Some("50")),
+            (&["-g", "--grid"], Some("2x3m1")),
+            (&["-apc", "--assign-parent-cell"], Some("1x0")),
+        ];
+        let default = format!("{:?}", parse_args(["x"]).unwrap());
+        for (names, value) in cases {
+            let parsed: Vec<String> = names
+                .iter()
+                .map(|name| {
+                    let args = std::iter::once(*name).chain(*value).chain(["x"]);
+                    format!("{:?}", parse_args(args).unwrap())
+                })
+                .collect();
+            assert_ne!(parsed[0], default, "{:?}", names);
+            assert!(parsed.iter().all(|p| *p == parsed[0]), "{:?}", names);
+        }
+        // `-sd` is the shake duration, as documented; the sleep moved to `-sl`
+        let options = parse_args(["-sd", "700", "-sl", "250", "x"]).unwrap();
+        assert_eq!(options.shake_duration, 700);
+        assert_eq!(options.sleep_duration_ms, 250);
+    }
+
+    #[test]
+    fn no_name_is_used_by_two_options() {
+        let mut names: Vec<&str> = OPTIONS
+            .iter()
+            .flat_map(|spec| spec.names)
+            .copied()
+            .collect();
+        let count = names.len();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), count);
+    }
+
+    #[test]
+    fn command_line_options_layer_on_top_of_a_profile() {
+        let base = parse_args([
+            "-g",
+            "2x2",
+            "-t",
+            "10",
+            "--grace",
+            "3",
+            "from-profile",
+            "arg",
+        ])
+        .unwrap();
+        let path = std::env::temp_dir().join(format!("startt-cli-{}.toml", std::process::id()));
+        std::fs::write(&path, crate::profile::to_toml(&base)).unwrap();
+        let profile = path.to_string_lossy().into_owned();
+        // (arguments after `--profile PATH`, timeout, grid, command)
+        let cases: &[(&[&str], u64, &str, &[&str])] = &[
+            (&[], 10, "2x2", &["from-profile", "arg"]),
+            (&["-t", "5"], 5, "2x2", &["from-profile", "arg"]),
+            (&["-g", "1x3", "other"], 10, "1x3", &["other"]),
+            (&["--timeout=1", "--", "-other"], 1, "2x2", &["-other"]),
+        ];
+        let parsed: Vec<_> = cases
+            .iter()
+            .map(|(args, ..)| parse_args(["--profile", profile.as_str()].iter().chain(*args)))
+            .collect();
+        std::fs::remove_file(&path).unwrap();
+        for ((args, timeout, grid, command), options) in cases.iter().zip(parsed) {
+            let options = options.unwrap();
+            assert_eq!(options.timeout_secs, Some(*timeout), "{:?}", args);
+            assert_eq!(
+                options.grids,
+                [parse_grid_spec(grid).unwrap()],
+                "{:?}",
+                args
+            );
+            assert_eq!(options.command, *command, "{:?}", args);
+            assert_eq!(options.grace_secs, 3, "{:?}", args);
         }
+        assert!(matches!(
+            parse_args(["--profile", "no-such-profile-anywhere", "x"]),
+            Err(CliError::Profile(_))
+        ));
     }
 }
@@ -0,0 +1,368 @@
+// src/containment.rs
+//! `--contain`: keep a launched tree inside one kernel object, so membership doesn't
+//! depend on parent PIDs, the `STARTT` variable or ETW.
+//!
+//! On Windows that is a Job Object with kill-on-close: every process the launched one
+//! starts afterwards joins the job (unless it asks to break away), the job's process
+//! list is the membership, and closing the last handle — including startt exiting —
+//! terminates them all. On Linux a cgroup v2 child of startt's own cgroup plays the
+//! same role; its members are killed when the [`Containment`] is dropped.
+//!
+//! Assignment fails when the process sits in a job that can't be nested (or the cgroup
+//! hierarchy isn't writable); callers then fall back to the usual discovery.
+use crate::cli::CommandLineOptions;
+use once_cell::sync::OnceCell;
+use std::io;
+
+/// Resource limits for the whole contained tree.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContainLimits {
+    /// Hard cap on CPU use across all cores, 1-100.
+    pub cpu_percent: Option<u32>,
+    /// Cap on the committed memory of all members together.
+    pub memory_bytes: Option<u64>,
+}
+
+impl ContainLimits {
+    /// The `--cpu-limit` / `--memory-limit` options.
+    pub fn from_options(options: &CommandLineOptions) -> Self {
+        ContainLimits {
+            cpu_percent: options.cpu_limit_percent,
+            memory_bytes: options.memory_limit_mb.map(|mb| mb * 1024 * 1024),
+        }
+    }
+}
+
+pub use platform::Containment;
+
+static CONTAINMENT: OnceCell<Containment> = OnceCell::new();
+
+/// Makes `containment` the one the rest of startt consults. Only the first call has an
+/// effect; the containment then lives until startt exits.
+pub fn install(containment: Containment) {
+    let _ = CONTAINMENT.set(containment);
+}
+
+/// The installed containment, if `--contain` succeeded.
+pub fn current() -> Option<&'static Containment> {
+    CONTAINMENT.get()
+}
+
+/// Creates a containment with `limits` and puts `pid` in it.
+pub fn contain(pid: u32, limits: &ContainLimits) -> io::Result<Containment> {
+    let containment = Containment::new(limits)?;
+    containment.assign(pid)?;
+    Ok(containment)
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::ContainLimits;
+    use std::io;
+    use std::ptr;
+    use winapi::shared::basetsd::ULONG_PTR;
+    use winapi::shared::minwindef::{DWORD, LPVOID};
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::jobapi::IsProcessInJob;
+    use winapi::um::jobapi2::{
+        AssignProcessToJobObject, CreateJobObjectW, QueryInformationJobObject,
+        SetInformationJobObject, TerminateJobObject,
+    };
+    use winapi::um::processthreadsapi::OpenProcess;
+    use winapi::um::winnt::{
+        HANDLE, JOB_OBJECT_CPU_RATE_CONTROL_ENABLE, JOB_OBJECT_CPU_RATE_CONTROL_HARD_CAP,
+        JOB_OBJECT_LIMIT_JOB_MEMORY, JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+        JOBOBJECT_BASIC_PROCESS_ID_LIST, JOBOBJECT_CPU_RATE_CONTROL_INFORMATION,
+        JOBOBJECT_EXTENDED_LIMIT_INFORMATION, JobObjectBasicProcessIdList,
+        JobObjectCpuRateControlInformation, JobObjectExtendedLimitInformation,
+        PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_SET_QUOTA, PROCESS_TERMINATE,
+    };
+
+    /// A kill-on-close Job Object.
+    pub struct Containment {
+        /// The job `HANDLE`, kept as an integer so the containment can be shared.
+        job: isize,
+    }
+
+    fn set_information<T>(job: HANDLE, class: u32, info: &mut T) -> io::Result<()> {
+        let ok = unsafe {
+            SetInformationJobObject(
+                job,
+                class,
+                info as *mut T as LPVOID,
+                std::mem::size_of::<T>() as DWORD,
+            )
+        };
+        if ok == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    impl Containment {
+        pub fn new(limits: &ContainLimits) -> io::Result<Self> {
+            let job = unsafe { CreateJobObjectW(ptr::null_mut(), ptr::null()) };
+            if job.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            // Closes the job again if a limit can't be set
+            let containment = Containment { job: job as isize };
+
+            let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = unsafe { std::mem::zeroed() };
+            info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+            if let Some(bytes) = limits.memory_bytes {
+                info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_JOB_MEMORY;
+                info.JobMemoryLimit = bytes as usize;
+            }
+            set_information(job, JobObjectExtendedLimitInformation, &mut info)?;
+
+            if let Some(percent) = limits.cpu_percent {
+                let mut cpu: JOBOBJECT_CPU_RATE_CONTROL_INFORMATION = unsafe { std::mem::zeroed() };
+                cpu.ControlFlags =
+                    JOB_OBJECT_CPU_RATE_CONTROL_ENABLE | JOB_OBJECT_CPU_RATE_CONTROL_HARD_CAP;
+                // In hundredths of a percent
+                unsafe { *cpu.u.CpuRate_mut() = percent.clamp(1, 100) * 100 };
+                set_information(job, JobObjectCpuRateControlInformation, &mut cpu)?;
+            }
+            Ok(containment)
+        }
+
+        /// Puts `pid` into the job; its future children follow it automatically.
+        pub fn assign(&self, pid: u32) -> io::Result<()> {
+            unsafe {
+                let process = OpenProcess(
+                    PROCESS_SET_QUOTA | PROCESS_TERMINATE | PROCESS_QUERY_LIMITED_INFORMATION,
+                    0,
+                    pid,
+                );
+                if process.is_null() {
+                    return Err(io::Error::last_os_error());
+                }
+                let result = if AssignProcessToJobObject(self.job as HANDLE, process) != 0 {
+                    Ok(())
+                } else {
+                    let error = io::Error::last_os_error();
+                    let mut in_job = 0;
+                    IsProcessInJob(process, ptr::null_mut(), &mut in_job);
+                    if in_job != 0 {
+                        Err(io::Error::other(format!(
+                            "PID {} is already in a job that can't be nested: {}",
+                            pid, error
+                        )))
+                    } else {
+                        Err(error)
+                    }
+                };
+                CloseHandle(process);
+                result
+            }
+        }
+
+        /// PIDs of every process currently in the job.
+        pub fn members(&self) -> Vec<u32> {
+            let header = std::mem::size_of::<JOBOBJECT_BASIC_PROCESS_ID_LIST>()
+                / std::mem::size_of::<ULONG_PTR>();
+            let mut capacity = 256;
+            loop {
+                // ULONG_PTR-sized slots keep the id array aligned
+                let mut buffer: Vec<ULONG_PTR> = vec![0; header + capacity];
+                let list = buffer.as_mut_ptr() as *mut JOBOBJECT_BASIC_PROCESS_ID_LIST;
+                let ok = unsafe {
+                    QueryInformationJobObject(
+                        self.job as HANDLE,
+                        JobObjectBasicProcessIdList,
+                        list as LPVOID,
+                        (buffer.len() * std::mem::size_of::<ULONG_PTR>()) as DWORD,
+                        ptr::null_mut(),
+                    )
+                };
+                let (assigned, listed) = unsafe {
+                    (
+                        (*list).NumberOfAssignedProcesses as usize,
+                        (*list).NumberOfProcessIdsInList as usize,
+                    )
+                };
+                if ok == 0 && assigned <= capacity {
+                    return Vec::new();
+                }
+                if assigned > listed && assigned > capacity {
+                    capacity = assigned + 64;
+                    continue;
+                }
+                let ids =
+                    unsafe { std::slice::from_raw_parts((*list).ProcessIdList.as_ptr(), listed) };
+                return ids.iter().map(|&id| id as u32).collect();
+            }
+        }
+
+        /// Terminates every member now.
+        pub fn terminate(&self) -> io::Result<()> {
+            if unsafe { TerminateJobObject(self.job as HANDLE, 1) } == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+    }
+
+    impl Drop for Containment {
+        /// Closing the last handle kills every member (kill-on-close).
+        fn drop(&mut self) {
+            unsafe { CloseHandle(self.job as HANDLE) };
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::ContainLimits;
+    use std::io;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// A cgroup v2 directory below startt's own cgroup.
+    pub struct Containment {
+        dir: PathBuf,
+    }
+
+    /// Mount point of the cgroup v2 hierarchy, from `/proc/self/mountinfo`.
+    fn cgroup2_mount() -> io::Result<PathBuf> {
+        let mountinfo = std::fs::read_to_string("/proc/self/mountinfo")?;
+        mountinfo
+            .lines()
+            .find_map(|line| {
+                let (mount, fs) = line.split_once(" - ")?;
+                if fs.split_whitespace().next()? != "cgroup2" {
+                    return None;
+                }
+                // Field 5 of the mount half is the mount point
+                mount.split_whitespace().nth(4).map(PathBuf::from)
+            })
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no cgroup2 mount"))
+    }
+
+    /// startt's own cgroup v2 path, relative to the mount point.
+    fn own_cgroup() -> io::Result<String> {
+        let cgroup = std::fs::read_to_string("/proc/self/cgroup")?;
+        cgroup
+            .lines()
+            .find_map(|line| line.strip_prefix("0::"))
+            .map(|path| path.trim_start_matches('/').to_string())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not in a cgroup v2"))
+    }
+
+    impl Containment {
+        pub fn new(limits: &ContainLimits) -> io::Result<Self> {
+            static COUNT: AtomicUsize = AtomicUsize::new(0);
+            let dir = cgroup2_mount()?.join(own_cgroup()?).join(format!(
+                "startt-{}-{}",
+                std::process::id(),
+                COUNT.fetch_add(1, Ordering::Relaxed)
+            ));
+            std::fs::create_dir(&dir)?;
+            // Removes the directory again if a limit can't be set
+            let containment = Containment { dir };
+            if let Some(percent) = limits.cpu_percent {
+                let cpus = std::thread::available_parallelism().map_or(1, |n| n.get() as u64);
+                let quota = percent.clamp(1, 100) as u64 * 1000 * cpus;
+                containment.write("cpu.max", &format!("{} 100000", quota))?;
+            }
+            if let Some(bytes) = limits.memory_bytes {
+                containment.write("memory.max", &bytes.to_string())?;
+            }
+            Ok(containment)
+        }
+
+        fn write(&self, file: &str, value: &str) -> io::Result<()> {
+            std::fs::write(self.dir.join(file), value)
+        }
+
+        /// Moves `pid` into the cgroup; its future children follow it automatically.
+        pub fn assign(&self, pid: u32) -> io::Result<()> {
+            self.write("cgroup.procs", &pid.to_string())
+        }
+
+        /// PIDs of every process currently in the cgroup.
+        pub fn members(&self) -> Vec<u32> {
+            std::fs::read_to_string(self.dir.join("cgroup.procs"))
+                .map(|procs| procs.lines().filter_map(|l| l.parse().ok()).collect())
+                .unwrap_or_default()
+        }
+
+        /// Kills every member now (`cgroup.kill`, Linux 5.14+).
+        pub fn terminate(&self) -> io::Result<()> {
+            self.write("cgroup.kill", "1")
+        }
+    }
+
+    impl Drop for Containment {
+        /// Kills the members and removes the cgroup, like closing a kill-on-close job.
+        fn drop(&mut self) {
+            let _ = self.terminate();
+            // The directory can only go once the killed members are gone
+            for _ in 0..50 {
+                if std::fs::remove_dir(&self.dir).is_ok() || !self.dir.exists() {
+                    return;
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::process_tree::ProcessTree;
+    use std::io::Write;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{Command, Stdio};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn a_contained_tree_is_listed_and_killed_on_drop() {
+        let containment = match Containment::new(&ContainLimits::default()) {
+            Ok(containment) => containment,
+            Err(e) => {
+                eprintln!("skipped: cgroup v2 is not writable here: {}", e);
+                return;
+            }
+        };
+        // The shell starts its children only once it is in the cgroup
+        let mut shell = Command::new("sh")
+            .args(["-c", "read go; sleep 5 & sleep 5"])
+            .stdin(Stdio::piped())
+            .spawn()
+            .unwrap();
+        if let Err(e) = containment.assign(shell.id()) {
+            eprintln!("skipped: cannot move a process into the cgroup: {}", e);
+            let _ = shell.kill();
+            let _ = shell.wait();
+            return;
+        }
+        writeln!(shell.stdin.take().unwrap(), "go").unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while containment.members().len() < 3 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        let mut members = containment.members();
+        members.sort_unstable();
+        let mut tree = vec![shell.id()];
+        tree.extend(ProcessTree::snapshot().descendants(shell.id()));
+        tree.sort_unstable();
+        assert_eq!(members, tree);
+        assert_eq!(members.len(), 3);
+
+        drop(containment);
+        assert_eq!(shell.wait().unwrap().signal(), Some(9));
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let mut survivors = members.clone();
+        while !survivors.is_empty() && Instant::now() < deadline {
+            let snapshot = ProcessTree::snapshot();
+            survivors.retain(|&pid| snapshot.contains(pid));
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(survivors.is_empty(), "{:?}", survivors);
+    }
+}
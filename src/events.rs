@@ -0,0 +1,348 @@
+// src/events.rs
+//! Machine-readable event stream for automation (`--events jsonl[=PATH]`).
+//!
+//! Every [`Event`] is written as one JSON object per line, tagged with `event` and
+//! stamped with `ts_ms` (milliseconds since the Unix epoch) and `elapsed_ms` (since
+//! the stream was opened):
+//!
+//! ```text
+//! {"event":"launched","pid":4242,"file":"cargo-e","params":"--run-all","ts_ms":...,"elapsed_ms":0}
+//! {"event":"cell_assigned","hwnd":263462,"pid":5120,"cell":3,"monitor":0,"bounds":{...},...}
+//! ```
+//!
+//! `jsonl` writes to stdout alongside the usual log lines (events are the lines starting
+//! with `{`); `jsonl=PATH` writes to a file instead. Nothing is emitted unless a stream
+//! was opened with [`install`].
+use crate::grid::{Rect, WindowId};
+use crate::window_system::WindowInfo;
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Where `--events` sends the stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventsTarget {
+    Stdout,
+    File(PathBuf),
+}
+
+impl std::str::FromStr for EventsTarget {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const EXPECTED: &str = "jsonl or jsonl=PATH";
+        match s.split_once('=') {
+            None if s.eq_ignore_ascii_case("jsonl") => Ok(EventsTarget::Stdout),
+            Some((format, path)) if format.eq_ignore_ascii_case("jsonl") && !path.is_empty() => {
+                Ok(EventsTarget::File(PathBuf::from(path)))
+            }
+            _ => Err(EXPECTED),
+        }
+    }
+}
+
+impl std::fmt::Display for EventsTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EventsTarget::Stdout => write!(f, "jsonl"),
+            EventsTarget::File(path) => write!(f, "jsonl={}", path.display()),
+        }
+    }
+}
+
+/// Why a window was closed to free its cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvictReason {
+    /// Every cell was taken and a new window needed one.
+    Full,
+    /// The window outlived `--timeout`.
+    Timeout,
+    /// A `--rule` pinned a new window to the cell.
+    Pinned,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// The target was started.
+    Launched {
+        pid: u32,
+        file: String,
+        params: String,
+    },
+    /// A process belonging to the launch was seen for the first time.
+    ProcessDiscovered { pid: u32, parent_pid: Option<u32> },
+    /// A window belonging to the launch was seen for the first time.
+    WindowDiscovered {
+        #[serde(flatten)]
+        window: WindowInfo,
+    },
+    /// A window was moved into a grid cell.
+    CellAssigned {
+        hwnd: WindowId,
+        pid: u32,
+        cell: usize,
+        monitor: Option<i32>,
+        bounds: Rect,
+    },
+    /// A window did not end up where it was moved to.
+    MoveFailed {
+        hwnd: WindowId,
+        cell: usize,
+        wanted: Rect,
+        attempts: u32,
+    },
+    /// A window was asked to close to free its cell.
+    Evicted {
+        hwnd: WindowId,
+        cell: usize,
+        reason: EvictReason,
+    },
+    /// A window in the grid was destroyed and its cell is free again.
+    CellFreed {
+        hwnd: WindowId,
+        cell: usize,
+        /// How long the window held the cell.
+        lifetime_ms: u64,
+    },
+    WindowDestroyed {
+        hwnd: WindowId,
+        pid: u32,
+        class: String,
+        title: String,
+    },
+    /// A staged shutdown finished; PIDs by how they ended.
+    Shutdown {
+        closed: Vec<u32>,
+        force_killed: Vec<u32>,
+        failed: Vec<u32>,
+    },
+    /// startt is about to exit.
+    Exit { code: i32, reason: String },
+}
+
+/// An event as written: its fields, then the timestamps.
+#[derive(Serialize)]
+struct Record<'a> {
+    #[serde(flatten)]
+    event: &'a Event,
+    ts_ms: u64,
+    elapsed_ms: u64,
+}
+
+struct Stream {
+    out: Mutex<Box<dyn Write + Send>>,
+    opened_at: Instant,
+}
+
+static STREAM: OnceCell<Stream> = OnceCell::new();
+
+/// Opens the event stream. Only the first call has an effect.
+pub fn install(target: &EventsTarget) -> std::io::Result<()> {
+    let out: Box<dyn Write + Send> = match target {
+        EventsTarget::Stdout => Box::new(std::io::stdout()),
+        EventsTarget::File(path) => Box::new(std::io::BufWriter::new(std::fs::File::create(path)?)),
+    };
+    let _ = STREAM.set(Stream {
+        out: Mutex::new(out),
+        opened_at: Instant::now(),
+    });
+    Ok(())
+}
+
+/// Whether a stream is open or a session is being recorded; lets callers skip building
+/// expensive events.
+pub fn enabled() -> bool {
+    STREAM.get().is_some() || crate::record::enabled()
+}
+
+/// Writes one event line, if a stream is open, and hands the event to the session
+/// recorder. Write errors are reported on stderr and otherwise ignored.
+pub fn emit(event: Event) {
+    crate::record::observe(&event);
+    let Some(stream) = STREAM.get() else {
+        return;
+    };
+    let record = Record {
+        event: &event,
+        ts_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64),
+        elapsed_ms: stream.opened_at.elapsed().as_millis() as u64,
+    };
+    // One write per line, so log lines printed from other threads can't split an event
+    let Ok(mut line) = serde_json::to_vec(&record) else {
+        return;
+    };
+    line.push(b'\n');
+    let mut out = stream.out.lock().unwrap_or_else(|e| e.into_inner());
+    let written = out.write_all(&line).and_then(|()| out.flush());
+    if let Err(e) = written {
+        eprintln!("startt: cannot write event: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The keys of `event` as it is written, in order.
+    fn keys_of(event: &Event) -> Vec<String> {
+        let record = Record {
+            event,
+            ts_ms: 1_700_000_000_000,
+            elapsed_ms: 5,
+        };
+        let line = serde_json::to_string(&record).unwrap();
+        let serde_json::Value::Object(object) = serde_json::from_str(&line).unwrap() else {
+            panic!("not an object: {}", line);
+        };
+        object.keys().cloned().collect()
+    }
+
+    #[test]
+    fn every_event_is_written_with_its_field_names() {
+        let bounds = Rect {
+            left: 0,
+            top: 0,
+            right: 800,
+            bottom: 600,
+        };
+        let window = WindowInfo {
+            handle: 263462,
+            pid: 5120,
+            class: "ConsoleWindowClass".into(),
+            title: "cargo-e".into(),
+            exe: Some("cargo-e.exe".into()),
+            bounds,
+            created_at: Some(1_700_000_000_000),
+            is_console: true,
+            monitor: Some(0),
+        };
+        let cases: Vec<(Event, &[&str])> = vec![
+            (
+                Event::Launched {
+                    pid: 4242,
+                    file: "cargo-e".into(),
+                    params: "--run-all".into(),
+                },
+                &["event", "pid", "file", "params"],
+            ),
+            (
+                Event::ProcessDiscovered {
+                    pid: 5120,
+                    parent_pid: Some(4242),
+                },
+                &["event", "pid", "parent_pid"],
+            ),
+            (
+                Event::WindowDiscovered { window },
+                &[
+                    "event",
+                    "hwnd",
+                    "pid",
+                    "class",
+                    "title",
+                    "exe",
+                    "bounds",
+                    "created_at",
+                    "is_console",
+                    "monitor",
+                ],
+            ),
+            (
+                Event::CellAssigned {
+                    hwnd: 263462,
+                    pid: 5120,
+                    cell: 3,
+                    monitor: Some(0),
+                    bounds,
+                },
+                &["event", "hwnd", "pid", "cell", "monitor", "bounds"],
+            ),
+            (
+                Event::MoveFailed {
+                    hwnd: 263462,
+                    cell: 3,
+                    wanted: bounds,
+                    attempts: 2,
+                },
+                &["event", "hwnd", "cell", "wanted", "attempts"],
+            ),
+            (
+                Event::Evicted {
+                    hwnd: 263462,
+                    cell: 3,
+                    reason: EvictReason::Timeout,
+                },
+                &["event", "hwnd", "cell", "reason"],
+            ),
+            (
+                Event::CellFreed {
+                    hwnd: 263462,
+                    cell: 3,
+                    lifetime_ms: 1500,
+                },
+                &["event", "hwnd", "cell", "lifetime_ms"],
+            ),
+            (
+                Event::WindowDestroyed {
+                    hwnd: 263462,
+                    pid: 5120,
+                    class: "ConsoleWindowClass".into(),
+                    title: "cargo-e".into(),
+                },
+                &["event", "hwnd", "pid", "class", "title"],
+            ),
+            (
+                Event::Shutdown {
+                    closed: vec![5120],
+                    force_killed: vec![],
+                    failed: vec![],
+                },
+                &["event", "closed", "force_killed", "failed"],
+            ),
+            (
+                Event::Exit {
+                    code: 0,
+                    reason: "interrupted".into(),
+                },
+                &["event", "code", "reason"],
+            ),
+        ];
+        for (event, fields) in cases {
+            let mut expected: Vec<&str> = fields.to_vec();
+            expected.extend(["ts_ms", "elapsed_ms"]);
+            let mut keys = keys_of(&event);
+            keys.sort();
+            expected.sort();
+            assert_eq!(keys, expected, "{:?}", event);
+        }
+    }
+
+    #[test]
+    fn events_are_tagged_in_snake_case() {
+        let line = serde_json::to_value(Event::CellAssigned {
+            hwnd: 263462,
+            pid: 5120,
+            cell: 3,
+            monitor: None,
+            bounds: Rect::default(),
+        })
+        .unwrap();
+        assert_eq!(line["event"], "cell_assigned");
+        assert_eq!(line["hwnd"], 263462);
+        assert_eq!(line["bounds"]["right"], 0);
+        let evicted = serde_json::to_value(Event::Evicted {
+            hwnd: 1,
+            cell: 0,
+            reason: EvictReason::Pinned,
+        })
+        .unwrap();
+        assert_eq!(evicted["reason"], "pinned");
+    }
+}
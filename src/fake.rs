@@ -0,0 +1,497 @@
+// src/fake.rs
+//! An in-memory window system and process table for driving the follow loop without
+//! a display or real processes.
+//!
+//! [`FakeWindowSystem`] and [`FakeProcessTree`] implement [`WindowSystem`] and
+//! [`ProcessInspector`] over a script of what happens when: processes start and exit,
+//! windows are created, minimized, retitled and destroyed. Times are offsets on a
+//! shared [`FakeClock`], which only moves when the follow loop sleeps on it (or it is
+//! advanced by hand), so a scenario runs instantly and the same way every time:
+//!
+//! ```text
+//! t=0      process 100 starts; follower.track(100)
+//! t=300ms  window A of 100 is created, refusing its first 2 moves
+//! t=1s     process 101 (child of 100) starts and creates window B
+//! t=4s     window A is destroyed; process 100 exits
+//! ```
+//!
+//! A [`Follower`](crate::follow::Follower) built on the pair is polled with
+//! [`follow::follow`](crate::follow::follow) and the fake clock. Afterwards the fakes
+//! tell where each window was put ([`FakeWindow::moves`]) and which windows startt
+//! closed ([`FakeWindowSystem::closed`]).
+use crate::follow::Clock;
+use crate::grid::{Rect, WindowId};
+use crate::process_tree::{ProcessInfo, ProcessTree};
+use crate::ps::ProcessInspector;
+use crate::window_system::WindowSystem;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicIsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Simulated time. It starts at zero when created and moves only when slept on or
+/// advanced.
+#[derive(Debug)]
+pub struct FakeClock {
+    start: Instant,
+    elapsed: Mutex<Duration>,
+}
+
+impl FakeClock {
+    pub fn new() -> Arc<Self> {
+        Arc::new(FakeClock {
+            start: Instant::now(),
+            elapsed: Mutex::new(Duration::ZERO),
+        })
+    }
+
+    /// Simulated time since the clock was created.
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.elapsed.lock().unwrap() += by;
+    }
+
+    /// The instant `elapsed` after the clock started.
+    pub fn at(&self, elapsed: Duration) -> Instant {
+        self.start + elapsed
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Instant {
+        self.at(self.elapsed())
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+/// Steps of a script, applied once the clock reaches them, in time order (steps at
+/// the same time in the order they were added).
+#[derive(Debug)]
+struct Script<S> {
+    steps: Vec<(Duration, S)>,
+}
+
+impl<S> Script<S> {
+    fn add(&mut self, at: Duration, step: S) {
+        let idx = self.steps.partition_point(|&(t, _)| t <= at);
+        self.steps.insert(idx, (at, step));
+    }
+
+    /// Removes and returns the steps due at `elapsed`, with their times.
+    fn due(&mut self, elapsed: Duration) -> Vec<(Duration, S)> {
+        let count = self.steps.partition_point(|&(t, _)| t <= elapsed);
+        self.steps.drain(..count).collect()
+    }
+}
+
+/// A window of a [`FakeWindowSystem`], as it is now.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FakeWindow {
+    pub pid: u32,
+    pub class: String,
+    pub title: String,
+    pub bounds: Rect,
+    pub visible: bool,
+    pub minimized: bool,
+    pub topmost: bool,
+    pub has_title_bar: bool,
+    pub has_border: bool,
+    /// How many more moves the window ignores, staying where it is.
+    pub refused_moves: u32,
+    /// Whether it stays open when asked to close.
+    pub ignores_close: bool,
+    /// Every rectangle the window was asked to move to, including refused moves.
+    pub moves: Vec<Rect>,
+    /// How many times it was shaken.
+    pub shakes: u32,
+}
+
+impl FakeWindow {
+    /// A visible, decorated window that moves and closes when asked.
+    pub fn new(pid: u32, class: &str, title: &str, bounds: Rect) -> Self {
+        FakeWindow {
+            pid,
+            class: class.to_string(),
+            title: title.to_string(),
+            bounds,
+            visible: true,
+            minimized: false,
+            topmost: false,
+            has_title_bar: true,
+            has_border: true,
+            refused_moves: 0,
+            ignores_close: false,
+            moves: Vec::new(),
+            shakes: 0,
+        }
+    }
+
+    /// Ignores its first `count` moves (`u32::MAX` for every move).
+    pub fn refusing_moves(mut self, count: u32) -> Self {
+        self.refused_moves = count;
+        self
+    }
+
+    /// Stays open when asked to close.
+    pub fn ignoring_close(mut self) -> Self {
+        self.ignores_close = true;
+        self
+    }
+
+    /// Created hidden, as windows that are never shown.
+    pub fn hidden(mut self) -> Self {
+        self.visible = false;
+        self
+    }
+}
+
+#[derive(Debug)]
+enum WindowStep {
+    Create(WindowId, FakeWindow),
+    Show(WindowId),
+    Minimize(WindowId),
+    Retitle(WindowId, String),
+    Destroy(WindowId),
+}
+
+#[derive(Debug, Default)]
+struct WindowState {
+    /// Open windows, in creation order.
+    windows: BTreeMap<WindowId, FakeWindow>,
+    /// Windows closed through [`WindowSystem::close`].
+    closed: Vec<WindowId>,
+    /// The window last given the focus, while it is open.
+    focused: Option<WindowId>,
+}
+
+/// A [`WindowSystem`] whose windows come and go on a script.
+pub struct FakeWindowSystem {
+    clock: Arc<FakeClock>,
+    monitors: Vec<Rect>,
+    next_id: AtomicIsize,
+    script: Mutex<Script<WindowStep>>,
+    state: Mutex<WindowState>,
+}
+
+impl FakeWindowSystem {
+    /// No windows yet, on `monitors` (work areas, primary first; one 1920x1080 monitor
+    /// if empty).
+    pub fn new(clock: Arc<FakeClock>, monitors: Vec<Rect>) -> Self {
+        let monitors = if monitors.is_empty() {
+            vec![Rect::new(0, 0, 1920, 1080)]
+        } else {
+            monitors
+        };
+        FakeWindowSystem {
+            clock,
+            monitors,
+            next_id: AtomicIsize::new(0x10000),
+            script: Mutex::new(Script { steps: Vec::new() }),
+            state: Mutex::new(WindowState::default()),
+        }
+    }
+
+    /// Creates `window` at `at` and returns the ID it will have.
+    pub fn create_at(&self, at: Duration, window: FakeWindow) -> WindowId {
+        let id = self.next_id.fetch_add(0x10, Ordering::Relaxed);
+        self.add(at, WindowStep::Create(id, window));
+        id
+    }
+
+    /// Shows a hidden window at `at`.
+    pub fn show_at(&self, at: Duration, window: WindowId) {
+        self.add(at, WindowStep::Show(window));
+    }
+
+    /// Minimizes `window` at `at`, as its user would.
+    pub fn minimize_at(&self, at: Duration, window: WindowId) {
+        self.add(at, WindowStep::Minimize(window));
+    }
+
+    pub fn retitle_at(&self, at: Duration, window: WindowId, title: &str) {
+        self.add(at, WindowStep::Retitle(window, title.to_string()));
+    }
+
+    /// Destroys `window` at `at`, as when its process closes it or exits.
+    pub fn destroy_at(&self, at: Duration, window: WindowId) {
+        self.add(at, WindowStep::Destroy(window));
+    }
+
+    /// The window as it is now, or None if it is not open.
+    pub fn window(&self, window: WindowId) -> Option<FakeWindow> {
+        self.state().windows.get(&window).cloned()
+    }
+
+    /// Windows asked to close through [`WindowSystem::close`], in order.
+    pub fn closed(&self) -> Vec<WindowId> {
+        self.state().closed.clone()
+    }
+
+    fn add(&self, at: Duration, step: WindowStep) {
+        self.script.lock().unwrap().add(at, step);
+    }
+
+    /// The windows, with the script played up to now.
+    fn state(&self) -> MutexGuard<'_, WindowState> {
+        let due = self.script.lock().unwrap().due(self.clock.elapsed());
+        let mut state = self.state.lock().unwrap();
+        for (_, step) in due {
+            match step {
+                WindowStep::Create(id, window) => {
+                    state.windows.insert(id, window);
+                }
+                WindowStep::Destroy(id) => {
+                    state.windows.remove(&id);
+                }
+                WindowStep::Show(id) => {
+                    if let Some(window) = state.windows.get_mut(&id) {
+                        window.visible = true;
+                    }
+                }
+                WindowStep::Minimize(id) => {
+                    if let Some(window) = state.windows.get_mut(&id) {
+                        window.minimized = true;
+                    }
+                }
+                WindowStep::Retitle(id, title) => {
+                    if let Some(window) = state.windows.get_mut(&id) {
+                        window.title = title;
+                    }
+                }
+            }
+        }
+        state
+    }
+
+    /// Calls `f` on the window if it is open.
+    fn with_window<R>(&self, window: WindowId, f: impl FnOnce(&mut FakeWindow) -> R) -> Option<R> {
+        self.state().windows.get_mut(&window).map(f)
+    }
+}
+
+impl WindowSystem for FakeWindowSystem {
+    fn windows(&self) -> Vec<WindowId> {
+        self.state().windows.keys().copied().collect()
+    }
+
+    fn is_window(&self, window: WindowId) -> bool {
+        self.state().windows.contains_key(&window)
+    }
+
+    fn is_visible(&self, window: WindowId) -> bool {
+        self.with_window(window, |w| w.visible).unwrap_or(false)
+    }
+
+    fn pid(&self, window: WindowId) -> Option<u32> {
+        self.with_window(window, |w| w.pid)
+    }
+
+    fn class(&self, window: WindowId) -> String {
+        self.with_window(window, |w| w.class.clone())
+            .unwrap_or_default()
+    }
+
+    fn title(&self, window: WindowId) -> String {
+        self.with_window(window, |w| w.title.clone())
+            .unwrap_or_default()
+    }
+
+    fn bounds(&self, window: WindowId) -> Option<Rect> {
+        self.with_window(window, |w| w.bounds)
+    }
+
+    fn set_bounds(&self, window: WindowId, bounds: Rect, resize: bool) -> bool {
+        self.with_window(window, |w| {
+            w.moves.push(bounds);
+            if w.refused_moves > 0 {
+                w.refused_moves -= 1;
+                return;
+            }
+            w.bounds = if resize {
+                bounds
+            } else {
+                let (width, height) = (w.bounds.width(), w.bounds.height());
+                Rect::new(
+                    bounds.left,
+                    bounds.top,
+                    bounds.left + width,
+                    bounds.top + height,
+                )
+            };
+        })
+        .is_some()
+    }
+
+    fn restore(&self, window: WindowId) {
+        self.with_window(window, |w| {
+            w.visible = true;
+            w.minimized = false;
+        });
+    }
+
+    fn minimize(&self, window: WindowId) {
+        self.with_window(window, |w| w.minimized = true);
+    }
+
+    fn set_topmost(&self, window: WindowId, topmost: bool) {
+        self.with_window(window, |w| w.topmost = topmost);
+    }
+
+    fn hide_decorations(&self, window: WindowId, title_bar: bool, border: bool) {
+        self.with_window(window, |w| {
+            w.has_title_bar &= !title_bar;
+            w.has_border &= !border;
+        });
+    }
+
+    fn close(&self, window: WindowId) {
+        let mut state = self.state();
+        let Some(ignores_close) = state.windows.get(&window).map(|w| w.ignores_close) else {
+            return;
+        };
+        state.closed.push(window);
+        if !ignores_close {
+            state.windows.remove(&window);
+        }
+    }
+
+    fn shake(&self, window: WindowId, _duration: Duration) {
+        self.with_window(window, |w| w.shakes += 1);
+    }
+
+    fn focus(&self, window: WindowId) {
+        let mut state = self.state();
+        if state.windows.contains_key(&window) {
+            state.focused = Some(window);
+        }
+    }
+
+    fn foreground(&self) -> Option<WindowId> {
+        let state = self.state();
+        state.focused.filter(|w| state.windows.contains_key(w))
+    }
+
+    fn monitors(&self) -> Vec<Rect> {
+        self.monitors.clone()
+    }
+}
+
+/// A process of a [`FakeProcessTree`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FakeProcess {
+    pub pid: u32,
+    pub ppid: u32,
+    pub image: String,
+    pub cmdline: Option<String>,
+    pub environ: Vec<(String, String)>,
+}
+
+impl FakeProcess {
+    pub fn new(pid: u32, ppid: u32, image: &str) -> Self {
+        FakeProcess {
+            pid,
+            ppid,
+            image: image.to_string(),
+            cmdline: None,
+            environ: Vec::new(),
+        }
+    }
+
+    pub fn cmdline(mut self, cmdline: &str) -> Self {
+        self.cmdline = Some(cmdline.to_string());
+        self
+    }
+
+    /// Sets an environment variable, e.g. `STARTT` to a follower's
+    /// [`tag`](crate::follow::Follower::tag).
+    pub fn env(mut self, name: &str, value: &str) -> Self {
+        self.environ.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+#[derive(Debug)]
+enum ProcessStep {
+    Start(FakeProcess),
+    Exit(u32),
+}
+
+/// A [`ProcessInspector`] whose processes start and exit on a script.
+pub struct FakeProcessTree {
+    clock: Arc<FakeClock>,
+    script: Mutex<Script<ProcessStep>>,
+    /// Running processes and the simulated time they started at.
+    running: Mutex<BTreeMap<u32, (FakeProcess, Duration)>>,
+}
+
+impl FakeProcessTree {
+    pub fn new(clock: Arc<FakeClock>) -> Self {
+        FakeProcessTree {
+            clock,
+            script: Mutex::new(Script { steps: Vec::new() }),
+            running: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Starts `process` at `at`. A process started again under a running PID replaces it.
+    pub fn start_at(&self, at: Duration, process: FakeProcess) {
+        self.script
+            .lock()
+            .unwrap()
+            .add(at, ProcessStep::Start(process));
+    }
+
+    pub fn exit_at(&self, at: Duration, pid: u32) {
+        self.script.lock().unwrap().add(at, ProcessStep::Exit(pid));
+    }
+
+    /// The running processes, with the script played up to now.
+    fn running(&self) -> MutexGuard<'_, BTreeMap<u32, (FakeProcess, Duration)>> {
+        let due = self.script.lock().unwrap().due(self.clock.elapsed());
+        let mut running = self.running.lock().unwrap();
+        for (at, step) in due {
+            match step {
+                ProcessStep::Start(process) => {
+                    running.insert(process.pid, (process, at));
+                }
+                ProcessStep::Exit(pid) => {
+                    running.remove(&pid);
+                }
+            }
+        }
+        running
+    }
+}
+
+impl ProcessInspector for FakeProcessTree {
+    fn pids(&self) -> Vec<u32> {
+        self.running().keys().copied().collect()
+    }
+
+    fn tree(&self) -> ProcessTree {
+        self.running()
+            .values()
+            .map(|(process, started)| ProcessInfo {
+                pid: process.pid,
+                ppid: process.ppid,
+                image: process.image.clone(),
+                start_time: Some(started.as_millis() as u64),
+                cmdline: process.cmdline.clone(),
+            })
+            .collect()
+    }
+
+    fn cmdline(&self, pid: u32) -> Option<String> {
+        self.running().get(&pid)?.0.cmdline.clone()
+    }
+
+    fn environ(&self, pid: u32) -> Option<Vec<(String, String)>> {
+        Some(self.running().get(&pid)?.0.environ.clone())
+    }
+}
@@ -0,0 +1,223 @@
+// src/filter.rs
+//! Which windows startt looks at in the first place.
+//!
+//! Every discovery path (the follow loop, grid eligibility and `find_hwnd_by_pid`)
+//! asks the same [`WindowFilter`]. It always drops the helper windows in
+//! [`DEFAULT_EXCLUDED_CLASSES`], plus anything matching `--exclude-class` /
+//! `--exclude-title`. `--include-class` / `--include-title` turn into allow-lists:
+//! once given, a window has to match at least one of them.
+//!
+//! Patterns are globs (`*` and `?`) over the whole value, or regexes searched in the
+//! value when prefixed with `re:`. Both ignore case.
+use crate::cli::CommandLineOptions;
+use once_cell::sync::OnceCell;
+use regex::{Regex, RegexBuilder};
+
+/// Window classes that are never real application windows: GPU and IME helpers,
+/// and winit's message-only event target.
+pub const DEFAULT_EXCLUDED_CLASSES: &[&str] = &[
+    "NVOpenGLPbuffer",
+    "wgpu Device Class*",
+    "MSCTFIME UI",
+    "Default IME",
+    "IME",
+    "temp_d3d_window_*",
+    "Winit Thread Event Target",
+];
+
+/// A glob or `re:` regex matched against a window class or title.
+#[derive(Debug, Clone)]
+pub struct WindowPattern {
+    regex: Regex,
+    /// The pattern as written, for display and `--print-profile`.
+    source: String,
+}
+
+impl WindowPattern {
+    pub fn is_match(&self, value: &str) -> bool {
+        self.regex.is_match(value)
+    }
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+impl std::str::FromStr for WindowPattern {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let regex = match s.strip_prefix("re:") {
+            Some(regex) => regex.to_string(),
+            None => glob_to_regex(s),
+        };
+        let regex = RegexBuilder::new(&regex)
+            .case_insensitive(true)
+            .build()
+            .map_err(|_| "a glob or a valid re:REGEX")?;
+        Ok(WindowPattern {
+            regex,
+            source: s.to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for WindowPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WindowFilter {
+    pub exclude_classes: Vec<WindowPattern>,
+    pub include_classes: Vec<WindowPattern>,
+    pub exclude_titles: Vec<WindowPattern>,
+    pub include_titles: Vec<WindowPattern>,
+}
+
+impl Default for WindowFilter {
+    /// Only the built-in class exclusions.
+    fn default() -> Self {
+        Self {
+            exclude_classes: DEFAULT_EXCLUDED_CLASSES
+                .iter()
+                .map(|class| class.parse().expect("built-in class pattern"))
+                .collect(),
+            include_classes: Vec::new(),
+            exclude_titles: Vec::new(),
+            include_titles: Vec::new(),
+        }
+    }
+}
+
+fn any_match(patterns: &[WindowPattern], value: &str) -> bool {
+    patterns.iter().any(|pattern| pattern.is_match(value))
+}
+
+impl WindowFilter {
+    /// The built-in exclusions plus the `--exclude-*` / `--include-*` options.
+    pub fn from_options(options: &CommandLineOptions) -> Self {
+        let mut filter = Self::default();
+        filter
+            .exclude_classes
+            .extend(options.exclude_classes.iter().cloned());
+        filter.include_classes = options.include_classes.clone();
+        filter.exclude_titles = options.exclude_titles.clone();
+        filter.include_titles = options.include_titles.clone();
+        filter
+    }
+
+    pub fn allows_class(&self, class: &str) -> bool {
+        !any_match(&self.exclude_classes, class)
+            && (self.include_classes.is_empty() || any_match(&self.include_classes, class))
+    }
+
+    pub fn allows_title(&self, title: &str) -> bool {
+        !any_match(&self.exclude_titles, title)
+            && (self.include_titles.is_empty() || any_match(&self.include_titles, title))
+    }
+
+    /// Whether a window with this class and title should be considered at all.
+    pub fn allows(&self, class: &str, title: &str) -> bool {
+        self.allows_class(class) && self.allows_title(title)
+    }
+
+    /// Whether the title needs looking up at all; saves a `GetWindowTextW` per window.
+    pub fn filters_titles(&self) -> bool {
+        !self.exclude_titles.is_empty() || !self.include_titles.is_empty()
+    }
+}
+
+static WINDOW_FILTER: OnceCell<WindowFilter> = OnceCell::new();
+
+/// Sets the filter used by every discovery path. Only the first call has an effect.
+pub fn install(filter: WindowFilter) {
+    let _ = WINDOW_FILTER.set(filter);
+}
+
+/// The installed filter, or the built-in defaults if none was installed.
+pub fn current() -> &'static WindowFilter {
+    WINDOW_FILTER.get_or_init(WindowFilter::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter_for(args: &[&str]) -> WindowFilter {
+        let options = crate::cli::parse_args(args.iter().chain(&["app"])).unwrap();
+        WindowFilter::from_options(&options)
+    }
+
+    #[test]
+    fn helper_windows_are_dropped_by_default() {
+        let filter = WindowFilter::default();
+        for class in [
+            "IME",
+            "Default IME",
+            "wgpu Device Class 1",
+            "temp_d3d_window_7",
+        ] {
+            assert!(!filter.allows(class, "anything"), "{}", class);
+        }
+        // Globs cover the whole class
+        for class in ["IME Helper", "Notepad", "my wgpu Device Class"] {
+            assert!(filter.allows(class, "anything"), "{}", class);
+        }
+        assert!(!filter.filters_titles());
+    }
+
+    #[test]
+    fn globs_match_the_whole_value_and_regexes_search_in_it() {
+        let glob: WindowPattern = "Chrome_*Win?".parse().unwrap();
+        assert!(glob.is_match("Chrome_WidgetWin1"));
+        assert!(!glob.is_match("Chrome_WidgetWin10"));
+        assert!(!glob.is_match("xChrome_WidgetWin1"));
+        // Regex characters in a glob are literal
+        let glob: WindowPattern = "a.b(c)".parse().unwrap();
+        assert!(glob.is_match("a.b(c)"));
+        assert!(!glob.is_match("axb(c)"));
+
+        let regex: WindowPattern = "re:win\\d+$".parse().unwrap();
+        assert!(regex.is_match("Chrome_WidgetWin1"));
+        assert!(!regex.is_match("Chrome_WidgetWin1 x"));
+        assert_eq!(regex.to_string(), "re:win\\d+$");
+        assert!("re:(".parse::<WindowPattern>().is_err());
+    }
+
+    #[test]
+    fn patterns_ignore_case() {
+        let filter = filter_for(&["--exclude-title", "SPLASH*", "--exclude-class", "re:^tool"]);
+        assert!(!filter.allows("App", "splash screen"));
+        assert!(!filter.allows("ToolWindow", "main"));
+        assert!(!filter.allows("ime", "main"));
+        assert!(filter.allows("App", "main"));
+        assert!(filter.filters_titles());
+    }
+
+    #[test]
+    fn includes_turn_matching_into_an_allow_list() {
+        let filter = filter_for(&["--include-class", "App*", "--include-class", "re:editor"]);
+        assert!(filter.allows("AppMain", "x"));
+        assert!(filter.allows("TextEditorWindow", "x"));
+        assert!(!filter.allows("Other", "x"));
+        // Titles are still unrestricted
+        assert!(!filter.filters_titles());
+
+        // Exclusions still apply to what the allow-list lets through
+        let filter = filter_for(&["--include-title", "build*", "--exclude-title", "re:debug"]);
+        assert!(filter.allows("Any", "Build output"));
+        assert!(!filter.allows("Any", "Settings"));
+        assert!(!filter.allows("Any", "Build output (debug)"));
+    }
+}
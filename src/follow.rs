@@ -0,0 +1,1303 @@
+// src/follow.rs
+//! Launch-and-grid through a [`WindowSystem`]: user32 on Windows, X11 on Linux.
+//!
+//! A [`Launcher`](crate::Launcher) starts the target with `STARTT=<our pid>` in its
+//! environment, and a [`Follower`] polls for the windows of every process that carries
+//! the tag or descends from the launch. Each new window goes through the `--rule`s and is placed
+//! by the [`GridEngine`], so `--on-full`, `--backfill`, `--compact`, `-t` and
+//! `-g auto` behave the same on every platform, as do the window effects (`-hT`, `-hB`,
+//! `-T`, shaking after a move, `-rpf`, `-rlf`) where the platform has them. A watched
+//! [`WindowEventSource`] has new windows placed, and closed ones leave their cells,
+//! between polls.
+//!
+//! The loop itself is [`follow`]: it only sees the [`WindowSystem`] and
+//! [`ProcessInspector`] the follower was built on and the [`Clock`] it is given, so
+//! it can be driven against [`crate::fake`] in simulated time.
+use crate::cli::{CellSpec, CommandLineOptions, FindMode, FullPolicy};
+use crate::events::{self, Event, EvictReason};
+use crate::filter::WindowFilter;
+use crate::grid::{ExpiryPolicy, GridEngine, Placement, Rect, WindowId, stacked_rect};
+use crate::process_tree::{ProcessInfo, ProcessTree};
+use crate::ps::ProcessInspector;
+use crate::record::WorkArea;
+use crate::rules::{RuleAction, RuleTimer, WindowFacts, WindowRule};
+use crate::window_events::{WindowEvent, WindowEventKind, WindowEventSource};
+use crate::window_system::{WindowInfo, WindowSystem};
+use crossbeam_channel::Sender;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+/// How often the follow loop looks for new and closed windows.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How many times a window that did not end up in its cell is moved again.
+const MAX_MOVE_ATTEMPTS: u32 = 3;
+
+/// A window of the launch, as it was when first seen.
+#[derive(Debug, Clone)]
+struct KnownWindow {
+    pid: u32,
+    found_at: Instant,
+    class: String,
+    title: String,
+    /// Image name and start time of the owning process.
+    exe: Option<String>,
+    created_at: Option<u64>,
+}
+
+/// Where a follower's events go: the `--events` stream, if one is installed, and the
+/// channel of a [`LaunchedApp`](crate::LaunchedApp).
+#[derive(Debug, Default)]
+struct EventSink {
+    tx: Option<Sender<Event>>,
+}
+
+impl EventSink {
+    fn emit(&self, event: Event) {
+        if let Some(tx) = &self.tx {
+            let _ = tx.send(event.clone());
+        }
+        events::emit(event);
+    }
+}
+
+/// Finds the launch's windows and keeps them in the grid.
+pub struct Follower<'w> {
+    windows: &'w dyn WindowSystem,
+    processes: &'w dyn ProcessInspector,
+    options: CommandLineOptions,
+    /// Which windows are considered at all, from the `--exclude-*` / `--include-*` options.
+    filter: WindowFilter,
+    /// None without `-g`: windows are only reported.
+    engine: Option<GridEngine>,
+    /// The `STARTT` value the launch's processes carry.
+    tag: String,
+    /// Windows and processes that existed before the launch.
+    initial_windows: HashSet<WindowId>,
+    initial_pids: HashSet<u32>,
+    /// Processes of the launch seen so far.
+    pids: HashSet<u32>,
+    /// What the snapshots said about them while they were running.
+    process_infos: HashMap<u32, ProcessInfo>,
+    known: HashMap<WindowId, KnownWindow>,
+    /// Windows waiting for a cell to free.
+    staged: VecDeque<WindowId>,
+    /// Windows a rule keeps out of the grid, or that timed out and were set aside.
+    unmanaged: HashSet<WindowId>,
+    /// Windows moved since the last poll, with the rectangle they were moved to.
+    moved: HashMap<WindowId, (usize, Rect)>,
+    failed: HashMap<WindowId, u32>,
+    /// The `--rule` whose timer was last applied to each window.
+    timer_rules: HashMap<WindowId, usize>,
+    /// Windows flashed topmost (`-T`) and when they drop back.
+    topmost_until: Vec<(WindowId, Instant)>,
+    /// The window that had the focus before the launch, which `-rlf` gives it back to.
+    launcher_window: Option<WindowId>,
+    /// Whether a process of the launch was running at the last poll.
+    alive: bool,
+    events: EventSink,
+    /// Reports windows between polls (see [`Follower::watch`]).
+    window_events: Option<Box<dyn WindowEventSource + 'w>>,
+}
+
+impl<'w> Follower<'w> {
+    /// Remembers the windows and processes that already exist, so only the launch's are
+    /// picked up, and lays out the `-g` grids on the monitors of `windows`.
+    pub fn new(
+        windows: &'w dyn WindowSystem,
+        processes: &'w dyn ProcessInspector,
+        options: CommandLineOptions,
+    ) -> Self {
+        let work_areas: Vec<WorkArea> = windows
+            .monitors()
+            .into_iter()
+            .enumerate()
+            .map(|(monitor, bounds)| WorkArea {
+                monitor: monitor as i32,
+                bounds,
+            })
+            .collect();
+        let engine = crate::replay::grid_pool(&options, &work_areas).map(|pool| {
+            let mut engine = GridEngine::new(pool, options.grid_placement_mode);
+            engine.set_timeout(options.timeout_secs.map(Duration::from_secs));
+            engine.set_on_full(options.on_full.strategy());
+            engine
+        });
+        Follower {
+            windows,
+            processes,
+            filter: WindowFilter::from_options(&options),
+            engine,
+            tag: std::process::id().to_string(),
+            initial_windows: windows.windows().into_iter().collect(),
+            initial_pids: processes.tree().pids().collect(),
+            pids: HashSet::new(),
+            process_infos: HashMap::new(),
+            known: HashMap::new(),
+            staged: VecDeque::new(),
+            unmanaged: HashSet::new(),
+            moved: HashMap::new(),
+            failed: HashMap::new(),
+            timer_rules: HashMap::new(),
+            topmost_until: Vec::new(),
+            launcher_window: windows.foreground(),
+            alive: true,
+            events: EventSink::default(),
+            window_events: None,
+            options,
+        }
+    }
+
+    /// The `STARTT` environment value that marks the launch's processes.
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Also sends every event to `tx`.
+    pub fn send_events(&mut self, tx: Sender<Event>) {
+        self.events.tx = Some(tx);
+    }
+
+    /// Emits `event` wherever the follower's own events go.
+    pub fn emit(&self, event: Event) {
+        self.events.emit(event);
+    }
+
+    /// Follows `pid` and everything it starts.
+    pub fn track(&mut self, pid: u32) {
+        self.pids.insert(pid);
+    }
+
+    pub fn engine(&self) -> Option<&GridEngine> {
+        self.engine.as_ref()
+    }
+
+    /// Processes of the launch seen so far, including ones that have exited.
+    pub fn pids(&self) -> &HashSet<u32> {
+        &self.pids
+    }
+
+    /// [`Follower::pids`] that were running at a poll, by PID, as last seen.
+    pub fn processes(&self) -> Vec<ProcessInfo> {
+        let mut processes: Vec<ProcessInfo> = self.process_infos.values().cloned().collect();
+        processes.sort_by_key(|process| process.pid);
+        processes
+    }
+
+    /// Open windows of the launch, in the order they were found.
+    pub fn windows(&self) -> Vec<WindowId> {
+        let mut windows: Vec<(Instant, WindowId)> = self
+            .known
+            .iter()
+            .map(|(&window, known)| (known.found_at, window))
+            .collect();
+        windows.sort();
+        windows.into_iter().map(|(_, window)| window).collect()
+    }
+
+    /// [`Follower::windows`] as they are now, skipping any that closed since the last
+    /// poll.
+    pub fn window_infos(&self) -> Vec<WindowInfo> {
+        self.windows()
+            .into_iter()
+            .filter_map(|window| {
+                let mut info = self.windows.info(window)?;
+                if let Some(known) = self.known.get(&window) {
+                    info.exe = known.exe.clone();
+                    info.created_at = known.created_at;
+                }
+                Some(info)
+            })
+            .collect()
+    }
+
+    /// Whether every window moved has been checked to be in place.
+    pub fn is_settled(&self) -> bool {
+        self.moved.is_empty()
+    }
+
+    /// Whether any process of the launch was still running at the last poll.
+    pub fn is_alive(&self) -> bool {
+        self.alive
+    }
+
+    /// Whether the follow loop is done after the last poll: without `-f` once the first
+    /// windows are in place, without `-F` once every process of the launch has exited.
+    pub fn end(&self) -> Option<FollowEnd> {
+        if !self.options.follow_children && !self.known.is_empty() && self.is_settled() {
+            return Some(FollowEnd::Finished);
+        }
+        if !self.options.follow_forever && !self.alive {
+            return Some(FollowEnd::ProcessesExited);
+        }
+        None
+    }
+
+    /// One pass of the follow loop at `now`.
+    pub fn poll(&mut self, now: Instant) {
+        let tree = &self.processes.tree();
+        self.track_processes(tree);
+        self.alive = self.pids.iter().any(|&pid| tree.contains(pid));
+        self.check_moves();
+        self.forget_closed(now);
+        self.expire_timers(now);
+        if self.engine.as_mut().is_some_and(|e| e.settle(now)) {
+            self.relayout_windows();
+        }
+        self.topmost_until.retain(|&(window, until)| {
+            if until > now {
+                return true;
+            }
+            self.windows.set_topmost(window, false);
+            false
+        });
+        self.discover_windows(tree, now);
+        if self.rules().iter().any(|r| r.timer.is_some()) {
+            let placed: Vec<WindowId> = self.known.keys().copied().collect();
+            for window in placed {
+                self.apply_rule_timer(window, tree, now);
+            }
+        }
+    }
+
+    fn rules(&self) -> &[WindowRule] {
+        &self.options.rules
+    }
+
+    /// Adds descendants of the launch, members of the `--contain` job or cgroup, and
+    /// processes carrying its tag or, with `--find-recent`/`--find-oldest`, running the
+    /// target's executable. The latter may predate the launch, which can hand its work
+    /// to an instance already running.
+    fn track_processes(&mut self, tree: &ProcessTree) {
+        let found: Vec<u32> = match self.options.find_mode {
+            FindMode::EnvTag => self
+                .processes
+                .env_pids("STARTT", Some(&self.tag))
+                .into_iter()
+                .filter(|pid| !self.initial_pids.contains(pid))
+                .collect(),
+            FindMode::Recent | FindMode::Oldest => match self.options.command.first() {
+                Some(program) => {
+                    let mut matching: Vec<u32> = tree
+                        .most_recent_matching(&program.to_string_lossy())
+                        .into_iter()
+                        .map(|entry| entry.pid)
+                        .filter(|&pid| pid != std::process::id())
+                        .collect();
+                    if self.options.find_mode == FindMode::Oldest {
+                        matching.reverse();
+                    }
+                    matching.truncate(self.options.num_recent);
+                    matching
+                }
+                None => Vec::new(),
+            },
+        };
+        let roots: Vec<u32> = self.pids.iter().copied().collect();
+        let descendants = roots.into_iter().flat_map(|pid| tree.descendants(pid));
+        // `--contain` also catches processes that re-parented or were handed off
+        let contained = crate::containment::current().map_or_else(Vec::new, |c| c.members());
+        let new: Vec<u32> = found
+            .into_iter()
+            .chain(descendants)
+            .chain(contained)
+            .filter(|pid| !self.pids.contains(pid))
+            .collect();
+        for pid in new {
+            if self.pids.insert(pid) {
+                self.events.emit(Event::ProcessDiscovered {
+                    pid,
+                    parent_pid: tree.parent_of(pid),
+                });
+            }
+        }
+        for &pid in &self.pids {
+            if let Some(process) = tree.get(pid) {
+                self.process_infos.insert(pid, process.clone());
+            }
+        }
+    }
+
+    /// New visible windows of the launch's processes that the filter lets through.
+    fn discover_windows(&mut self, tree: &ProcessTree, now: Instant) {
+        for window in self.windows.windows() {
+            self.discover(window, tree, now);
+        }
+    }
+
+    /// Picks up and places `window` if it is a new visible window of the launch that the
+    /// filter lets through.
+    fn discover(&mut self, window: WindowId, tree: &ProcessTree, now: Instant) {
+        if !self.is_unseen(window) || !self.windows.is_visible(window) {
+            return;
+        }
+        let Some(pid) = self.windows.pid(window) else {
+            return;
+        };
+        if !self.pids.contains(&pid) {
+            return;
+        }
+        let Some(info) = self.windows.info(window) else {
+            return;
+        };
+        if !self.filter.allows(&info.class, &info.title) {
+            return;
+        }
+        let info = info.with_process(tree);
+        println!(
+            "Found window {:#x} of PID {} ('{}', {})",
+            window, pid, info.title, info.class
+        );
+        self.known.insert(
+            window,
+            KnownWindow {
+                pid,
+                found_at: now,
+                class: info.class.clone(),
+                title: info.title.clone(),
+                exe: info.exe.clone(),
+                created_at: info.created_at,
+            },
+        );
+        self.events.emit(Event::WindowDiscovered { window: info });
+        self.place(window, tree, now);
+    }
+
+    /// Whether `window` is neither one of the launch's nor was open before it.
+    fn is_unseen(&self, window: WindowId) -> bool {
+        !self.known.contains_key(&window) && !self.initial_windows.contains(&window)
+    }
+
+    /// Places new windows and frees the cells of closed ones as `source` reports them,
+    /// between polls (see [`Follower::wait`]).
+    pub fn watch(&mut self, source: impl WindowEventSource + 'w) {
+        self.window_events = Some(Box::new(source));
+    }
+
+    /// Sleeps `timeout` on `clock`, handling the window events that come in meanwhile.
+    pub fn wait(&mut self, clock: &dyn Clock, timeout: Duration) {
+        let until = clock.now() + timeout;
+        while let Some(source) = self.window_events.as_mut() {
+            let left = until.saturating_duration_since(clock.now());
+            let Some(event) = source.next_event(left) else {
+                break;
+            };
+            self.window_event(event, clock.now());
+        }
+        clock.sleep(until.saturating_duration_since(clock.now()));
+    }
+
+    /// Handles the window events already queued by the watched source.
+    pub fn handle_window_events(&mut self, now: Instant) {
+        while let Some(event) = self
+            .window_events
+            .as_mut()
+            .and_then(|source| source.try_next_event())
+        {
+            self.window_event(event, now);
+        }
+    }
+
+    fn window_event(&mut self, event: WindowEvent, now: Instant) {
+        match event.kind {
+            // Windows of processes the last poll did not know yet wait for the next one;
+            // only a window that may need placing is worth a process snapshot
+            WindowEventKind::Created | WindowEventKind::Shown | WindowEventKind::NameChanged => {
+                if self.pids.contains(&event.pid) && self.is_unseen(event.hwnd) {
+                    let tree = self.processes.tree();
+                    self.discover(event.hwnd, &tree, now);
+                }
+            }
+            WindowEventKind::Destroyed => {
+                if self.known.contains_key(&event.hwnd) {
+                    self.forget_closed(now);
+                }
+            }
+            WindowEventKind::LocationChanged => {}
+        }
+    }
+
+    /// Calls `f` with the properties `--rule`s match `window` on.
+    fn with_window_facts<R>(
+        &self,
+        window: WindowId,
+        tree: &ProcessTree,
+        f: impl FnOnce(&WindowFacts) -> R,
+    ) -> R {
+        let pid = self.known.get(&window).map_or(0, |known| known.pid);
+        let class = self.windows.class(window);
+        let title = self.windows.title(window);
+        let image = tree.get(pid).map_or("", |entry| entry.image.as_str());
+        let cmdline = if self.rules().iter().any(WindowRule::needs_cmdline) {
+            self.processes.cmdline(pid)
+        } else {
+            None
+        };
+        f(&WindowFacts {
+            class: &class,
+            title: &title,
+            image,
+            cmdline: cmdline.as_deref(),
+        })
+    }
+
+    /// Puts a newly found window where the rules and the engine say.
+    fn place(&mut self, window: WindowId, tree: &ProcessTree, now: Instant) {
+        let action = if self.rules().is_empty() {
+            None
+        } else {
+            self.with_window_facts(window, tree, |facts| {
+                let rule = crate::rules::first_match(self.rules(), facts)?;
+                println!("Rule '{}' matched window {:#x}", rule, window);
+                rule.action
+            })
+        };
+        match action {
+            Some(RuleAction::Ignore) => {
+                self.unmanaged.insert(window);
+                return;
+            }
+            Some(RuleAction::Float) => {
+                self.unmanaged.insert(window);
+                self.apply_effects(window, now);
+                return;
+            }
+            _ => {}
+        }
+        let Some(engine) = self.engine.as_mut() else {
+            self.apply_effects(window, now);
+            return;
+        };
+        // The launch's first window goes to the parent cell when one is asked for
+        let options = &self.options;
+        let parent_cell = if self.known.len() == 1
+            && (options.reserve_parent_cell || options.assign_parent_cell.is_some())
+        {
+            let CellSpec { region, monitor } = options.assign_parent_cell.unwrap_or_default();
+            engine
+                .pool
+                .index(monitor, region.row, region.col)
+                .map(|idx| (idx, engine.pool.region_cells(monitor, &region)))
+        } else {
+            None
+        };
+        if let Some((idx, reserved)) = parent_cell {
+            engine.reserve(reserved);
+            engine.protect(Some(window));
+            engine.assign(idx, window, now);
+            self.move_to_cell(window, idx);
+            self.apply_effects(window, now);
+            return;
+        }
+        // A pinned window takes the first free cell of its range, or makes room there
+        if let Some(RuleAction::Cell(spec)) = action
+            && let Some(idx) = self.make_room_in(window, spec, now)
+        {
+            self.settle_window(window, idx, tree, now);
+            return;
+        }
+        let Some(engine) = self.engine.as_mut() else {
+            return;
+        };
+        let cell = match engine.next_placement() {
+            Placement::Cell(idx) => idx,
+            Placement::Evict { cell, window: old } => {
+                println!(
+                    "All grid cells are full, closing window {:#x} in cell {}",
+                    old, cell
+                );
+                self.events.emit(Event::Evicted {
+                    hwnd: old,
+                    cell,
+                    reason: EvictReason::Full,
+                });
+                self.windows.close(old);
+                cell
+            }
+            Placement::Minimize { cell, window: old } => {
+                println!(
+                    "All grid cells are full, minimizing window {:#x} from cell {}",
+                    old, cell
+                );
+                self.events.emit(Event::Evicted {
+                    hwnd: old,
+                    cell,
+                    reason: EvictReason::Full,
+                });
+                self.set_aside(old, None);
+                cell
+            }
+            Placement::Overflow {
+                cell,
+                window: old,
+                monitor,
+            } => {
+                println!(
+                    "All grid cells are full, moving window {:#x} from cell {} to monitor {}",
+                    old, cell, monitor
+                );
+                self.events.emit(Event::Evicted {
+                    hwnd: old,
+                    cell,
+                    reason: EvictReason::Full,
+                });
+                self.set_aside(old, Some(monitor));
+                cell
+            }
+            Placement::Stack { cell, depth } => {
+                println!(
+                    "All grid cells are full, stacking window {:#x} over cell {} at depth {}",
+                    window, cell, depth
+                );
+                engine.stack(cell, window, now);
+                self.move_to_cell(window, cell);
+                self.apply_effects(window, now);
+                return;
+            }
+            Placement::Grown { cell } => {
+                println!("All grid cells are full, growing the grid");
+                self.relayout_windows();
+                cell
+            }
+            Placement::Full => {
+                if self.options.on_full == FullPolicy::Queue {
+                    println!(
+                        "All grid cells are full, window {:#x} waits for a cell",
+                        window
+                    );
+                } else {
+                    println!("No filled cells to evict (parent/reserved protected).");
+                }
+                self.staged.push_back(window);
+                return;
+            }
+        };
+        // Sequential placement wraps around onto taken cells; their windows stay put
+        if let Some(engine) = self.engine.as_mut() {
+            engine.assign(cell, window, now);
+        }
+        self.settle_window(window, cell, tree, now);
+    }
+
+    /// The first free cell of a rule's range for `window`, closing the range's oldest
+    /// window if every cell is taken. Records `window` in the cell at `now`.
+    fn make_room_in(&mut self, window: WindowId, spec: CellSpec, now: Instant) -> Option<usize> {
+        let engine = self.engine.as_mut()?;
+        let cells = engine.pool.region_cells(spec.monitor, &spec.region);
+        let idx = match cells.iter().copied().find(|&idx| engine.is_free(idx)) {
+            Some(idx) => idx,
+            None => {
+                let (idx, evicted) = engine.evict_oldest_in(&cells)?;
+                println!(
+                    "Evicting window {:#x} from cell {} for pinned window {:#x}",
+                    evicted, idx, window
+                );
+                self.events.emit(Event::Evicted {
+                    hwnd: evicted,
+                    cell: idx,
+                    reason: EvictReason::Pinned,
+                });
+                self.windows.close(evicted);
+                idx
+            }
+        };
+        // Windows stacked over the evicted one keep the cell; this one goes on top
+        match engine.is_free(idx) {
+            true => {
+                engine.assign(idx, window, now);
+            }
+            false => {
+                engine.stack(idx, window, now);
+            }
+        }
+        Some(idx)
+    }
+
+    /// Moves a window the engine just put in cell `idx` there, and starts its rule
+    /// timer and effects.
+    fn settle_window(&mut self, window: WindowId, idx: usize, tree: &ProcessTree, now: Instant) {
+        self.move_to_cell(window, idx);
+        self.apply_rule_timer(window, tree, now);
+        self.apply_effects(window, now);
+    }
+
+    /// Moves `window` onto cell `idx`, which the engine already records it in, cascaded
+    /// if it is stacked there. Whether it got there is checked on the next poll.
+    fn move_to_cell(&mut self, window: WindowId, idx: usize) {
+        let Some(engine) = &self.engine else {
+            return;
+        };
+        let depth = engine.stack_position(window).unwrap_or(0);
+        let cell = stacked_rect(engine.pool.cell_rect(idx), depth);
+        let target = match (self.options.fit_grid, self.windows.bounds(window)) {
+            (false, Some(bounds)) => engine
+                .pool
+                .place_in_cell(idx, bounds.width(), bounds.height(), false)
+                .map_or(cell, |placed| {
+                    Rect::new(
+                        cell.left,
+                        cell.top,
+                        cell.left + placed.width(),
+                        cell.top + placed.height(),
+                    )
+                }),
+            _ => cell,
+        };
+        self.windows.restore(window);
+        self.windows
+            .set_bounds(window, target, self.options.fit_grid);
+        self.moved.insert(window, (idx, target));
+        self.events.emit(Event::CellAssigned {
+            hwnd: window,
+            pid: self.known.get(&window).map_or(0, |known| known.pid),
+            cell: idx,
+            monitor: engine.pool.monitor_of(idx),
+            bounds: target,
+        });
+        // Moving may have brought the window to the front
+        if self.options.retain_parent_focus
+            && let Some(&parent) = self.windows().first()
+        {
+            self.windows.focus(parent);
+        }
+        if self.options.retain_launcher_focus
+            && let Some(launcher) = self.launcher_window
+        {
+            self.windows.focus(launcher);
+        }
+    }
+
+    /// Checks that the windows moved since the last poll are where they were put, shaking
+    /// them (`-sd`), and moves the others again, up to [`MAX_MOVE_ATTEMPTS`] times.
+    fn check_moves(&mut self) {
+        let moved: Vec<(WindowId, (usize, Rect))> = self.moved.drain().collect();
+        for (window, (idx, wanted)) in moved {
+            let Some(actual) = self.windows.bounds(window) else {
+                continue;
+            };
+            if (actual.left, actual.top) == (wanted.left, wanted.top) {
+                self.failed.remove(&window);
+                if self.options.shake_duration > 0 {
+                    let duration = Duration::from_millis(self.options.shake_duration);
+                    self.windows.shake(window, duration);
+                }
+                continue;
+            }
+            // The window may have left the cell since
+            if self.engine.as_ref().and_then(|e| e.cell_of(window)) != Some(idx) {
+                continue;
+            }
+            let attempts = *self
+                .failed
+                .entry(window)
+                .and_modify(|count| *count += 1)
+                .or_insert(1);
+            println!(
+                "Warning: window {:#x} did not move to expected position (wanted: {},{} got: {},{})",
+                window, wanted.left, wanted.top, actual.left, actual.top
+            );
+            self.events.emit(Event::MoveFailed {
+                hwnd: window,
+                cell: idx,
+                wanted,
+                attempts,
+            });
+            if attempts < MAX_MOVE_ATTEMPTS {
+                self.move_to_cell(window, idx);
+            }
+        }
+    }
+
+    /// Frees the cells of windows that are gone and refills them.
+    fn forget_closed(&mut self, now: Instant) {
+        let closed: Vec<WindowId> = self
+            .known
+            .keys()
+            .copied()
+            .filter(|&window| !self.windows.is_window(window))
+            .collect();
+        for window in closed {
+            let Some(known) = self.known.remove(&window) else {
+                continue;
+            };
+            self.staged.retain(|&w| w != window);
+            self.unmanaged.remove(&window);
+            self.moved.remove(&window);
+            self.failed.remove(&window);
+            self.timer_rules.remove(&window);
+            self.events.emit(Event::WindowDestroyed {
+                hwnd: window,
+                pid: known.pid,
+                class: known.class,
+                title: known.title,
+            });
+            let Some(engine) = self.engine.as_mut() else {
+                continue;
+            };
+            let Some((idx, lifetime)) = engine.window_closed(window, now) else {
+                continue;
+            };
+            if !engine.is_free(idx) {
+                println!(
+                    "Window {:#x} closed after {:?}; cell {} keeps its stacked windows",
+                    window, lifetime, idx
+                );
+                continue;
+            }
+            println!(
+                "Window {:#x} closed after {:?}; cell {} is free",
+                window, lifetime, idx
+            );
+            self.events.emit(Event::CellFreed {
+                hwnd: window,
+                cell: idx,
+                lifetime_ms: lifetime.as_millis() as u64,
+            });
+            self.refill(idx, now);
+        }
+    }
+
+    /// Refills cell `idx` after its window left the grid: `--backfill` (or
+    /// `--on-full queue`) and `--compact`.
+    fn refill(&mut self, idx: usize, now: Instant) {
+        let Some(engine) = self.engine.as_mut() else {
+            return;
+        };
+        // A window stacked in the cell took it over
+        if !engine.is_free(idx) {
+            return;
+        }
+        if self.options.backfill || self.options.on_full == FullPolicy::Queue {
+            while let Some(window) = self.staged.pop_front() {
+                if !self.windows.is_window(window) {
+                    continue;
+                }
+                println!("Back-filling cell {} with staged window {:#x}", idx, window);
+                engine.assign(idx, window, now);
+                self.move_to_cell(window, idx);
+                break;
+            }
+        }
+        if self.options.compact {
+            let Some(engine) = self.engine.as_mut() else {
+                return;
+            };
+            for (window, from, to) in engine.compact() {
+                println!(
+                    "Compacting window {:#x} from cell {} to cell {}",
+                    window, from, to
+                );
+                self.move_to_cell(window, to);
+            }
+        }
+    }
+
+    /// Moves every window in the grid to its cell after the engine laid the grid out
+    /// again (`--on-full grow`, `-g auto`).
+    fn relayout_windows(&mut self) {
+        let Some(engine) = &self.engine else {
+            return;
+        };
+        for grid in &engine.pool.monitors {
+            println!(
+                "Grid is now {}x{} on monitor {}",
+                grid.layout.rows, grid.layout.cols, grid.monitor
+            );
+        }
+        let windows: Vec<(usize, WindowId)> = engine
+            .cells()
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, c)| {
+                let stacked = c.stacked.iter().map(|&(w, _)| w);
+                c.window.into_iter().chain(stacked).map(move |w| (idx, w))
+            })
+            .collect();
+        for (idx, window) in windows {
+            self.move_to_cell(window, idx);
+        }
+    }
+
+    /// Minimizes `window`, or moves it to `monitor`, and leaves it alone from then on.
+    fn set_aside(&mut self, window: WindowId, monitor: Option<i32>) {
+        self.unmanaged.insert(window);
+        match monitor {
+            None => self.windows.minimize(window),
+            Some(monitor) => {
+                let area = self.windows.monitor_rect(monitor);
+                self.windows.set_bounds(window, area, false);
+            }
+        }
+    }
+
+    /// Applies `--timeout-action` to every window whose deadline has passed.
+    fn expire_timers(&mut self, now: Instant) {
+        let Some(engine) = self.engine.as_mut() else {
+            return;
+        };
+        for (idx, window) in engine.expire(now) {
+            self.timer_rules.remove(&window);
+            if !self.windows.is_window(window) {
+                continue;
+            }
+            println!(
+                "Window {:#x} in cell {} timed out ({})",
+                window, idx, self.options.timeout_action
+            );
+            self.events.emit(Event::Evicted {
+                hwnd: window,
+                cell: idx,
+                reason: EvictReason::Timeout,
+            });
+            match self.options.timeout_action {
+                ExpiryPolicy::Close => self.windows.close(window),
+                ExpiryPolicy::Minimize => self.set_aside(window, None),
+                ExpiryPolicy::Overflow(monitor) => self.set_aside(window, Some(monitor)),
+            }
+            self.refill(idx, now);
+        }
+    }
+
+    /// Applies the timer of the first `--rule` matching a window in the grid. A rule is
+    /// applied once until a different one (or none) matches; a window that stops
+    /// matching a `timeout=off` rule gets its remaining time back.
+    fn apply_rule_timer(&mut self, window: WindowId, tree: &ProcessTree, now: Instant) {
+        let in_grid = self
+            .engine
+            .as_ref()
+            .is_some_and(|e| e.cell_of(window).is_some());
+        if !in_grid || self.rules().iter().all(|r| r.timer.is_none()) {
+            return;
+        }
+        let matched = self.with_window_facts(window, tree, |facts| {
+            crate::rules::first_timer_match(self.rules(), facts)
+                .map(|(idx, rule)| (idx, rule.timer))
+        });
+        if matched.map(|(idx, _)| idx) == self.timer_rules.get(&window).copied() {
+            return;
+        }
+        let Some(engine) = self.engine.as_mut() else {
+            return;
+        };
+        // A timer paused by the previous rule runs again unless the new one pauses it too
+        engine.timers_mut().resume(window, now);
+        let Some((idx, Some(timer))) = matched else {
+            self.timer_rules.remove(&window);
+            return;
+        };
+        self.timer_rules.insert(window, idx);
+        println!(
+            "Rule '{}' sets the timer of window {:#x}: {:?}",
+            self.options.rules[idx], window, timer
+        );
+        let timers = engine.timers_mut();
+        match timer {
+            RuleTimer::After(after) => timers.schedule(window, now + after),
+            RuleTimer::Extend(by) => {
+                if !timers.extend(window, by) {
+                    timers.schedule(window, now + by);
+                }
+            }
+            RuleTimer::Off => {
+                timers.pause(window, now);
+            }
+        }
+    }
+
+    /// `-hT`, `-hB` and `-T`.
+    fn apply_effects(&mut self, window: WindowId, now: Instant) {
+        let options = &self.options;
+        self.windows.hide_decorations(
+            window,
+            options.should_hide_title_bar,
+            options.should_hide_border,
+        );
+        if options.flash_topmost_ms > 0 {
+            self.windows.set_topmost(window, true);
+            let until = now + Duration::from_millis(options.flash_topmost_ms);
+            self.topmost_until.push((window, until));
+        }
+    }
+}
+
+/// Where the follow loop gets the time from and how it waits between polls.
+pub trait Clock {
+    fn now(&self) -> Instant;
+    fn sleep(&self, duration: Duration);
+}
+
+/// The wall clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// Why [`follow`] returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FollowEnd {
+    /// Not following (`-f`), and the first windows are in place.
+    Finished,
+    /// Every process of the launch has exited (not with `-F`).
+    ProcessesExited,
+    /// The deadline passed.
+    Deadline,
+}
+
+impl FollowEnd {
+    /// The `reason` of the `exit` event.
+    pub fn reason(self) -> &'static str {
+        match self {
+            FollowEnd::Finished => "finished",
+            FollowEnd::ProcessesExited => "all processes exited",
+            FollowEnd::Deadline => "deadline",
+        }
+    }
+}
+
+/// Polls `follower` every [`POLL_INTERVAL`] of `clock` until its first windows are
+/// placed (without `-f`), its processes are gone (without `-F`), or `deadline`.
+pub fn follow(follower: &mut Follower, clock: &dyn Clock, deadline: Option<Instant>) -> FollowEnd {
+    loop {
+        let now = clock.now();
+        follower.poll(now);
+        if let Some(end) = follower.end() {
+            return end;
+        }
+        if deadline.is_some_and(|deadline| now >= deadline) {
+            return FollowEnd::Deadline;
+        }
+        follower.wait(clock, POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::{FakeClock, FakeProcess, FakeProcessTree, FakeWindow, FakeWindowSystem};
+    use std::sync::Arc;
+
+    /// Where windows open, away from every cell's corner.
+    const WINDOW: Rect = Rect {
+        left: 40,
+        top: 30,
+        right: 340,
+        bottom: 230,
+    };
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    /// One 1000x500 monitor, and the launched process 100 running from the start.
+    fn scene() -> (Arc<FakeClock>, FakeWindowSystem, FakeProcessTree) {
+        let clock = FakeClock::new();
+        let windows = FakeWindowSystem::new(clock.clone(), vec![Rect::new(0, 0, 1000, 500)]);
+        let processes = FakeProcessTree::new(clock.clone());
+        processes.start_at(Duration::ZERO, FakeProcess::new(100, 1, "app"));
+        (clock, windows, processes)
+    }
+
+    /// A follower of process 100 with the command line `args`.
+    fn follower<'w>(
+        windows: &'w FakeWindowSystem,
+        processes: &'w FakeProcessTree,
+        args: &[&str],
+    ) -> Follower<'w> {
+        let options = crate::cli::parse_args(args.iter().chain(&["app"])).unwrap();
+        let mut follower = Follower::new(windows, processes, options);
+        follower.track(100);
+        follower
+    }
+
+    fn window(pid: u32, title: &str) -> FakeWindow {
+        FakeWindow::new(pid, "App", title, WINDOW)
+    }
+
+    /// Where the top-left corner of cell `idx` is.
+    fn cell_origin(follower: &Follower, idx: usize) -> (i32, i32) {
+        let cell = follower.engine().unwrap().pool.cell_rect(idx);
+        (cell.left, cell.top)
+    }
+
+    fn origin(rect: Rect) -> (i32, i32) {
+        (rect.left, rect.top)
+    }
+
+    #[test]
+    fn the_first_window_goes_to_the_parent_cell_and_the_rest_around_it() {
+        let (clock, windows, processes) = scene();
+        let parent = windows.create_at(ms(100), window(100, "parent"));
+        let second = windows.create_at(ms(200), window(100, "second"));
+        let third = windows.create_at(ms(200), window(100, "third"));
+        // The parent spans both rows of the first column, which stay reserved
+        let mut follower = follower(
+            &windows,
+            &processes,
+            &["-f", "-g", "2x2", "-apc", "0x0+2x1", "-rpc"],
+        );
+        clock.advance(ms(100));
+        follower.poll(clock.now());
+        let engine = follower.engine().unwrap();
+        assert_eq!(engine.cell_of(parent), Some(0));
+        assert_eq!(engine.protected(), Some(parent));
+        assert!(engine.is_reserved(2));
+
+        clock.advance(ms(100));
+        follower.poll(clock.now());
+        let engine = follower.engine().unwrap();
+        assert_eq!(engine.cell_of(second), Some(1));
+        assert_eq!(engine.cell_of(third), Some(3));
+        assert!(engine.is_free(2));
+        for (window, idx) in [(parent, 0), (second, 1), (third, 3)] {
+            let moved = windows.window(window).unwrap().bounds;
+            assert_eq!(origin(moved), cell_origin(&follower, idx));
+        }
+    }
+
+    #[test]
+    fn only_new_visible_windows_of_the_launch_that_pass_the_filter_are_placed() {
+        let (clock, windows, processes) = scene();
+        let existing = windows.create_at(Duration::ZERO, window(100, "existing"));
+        let mut follower = follower(
+            &windows,
+            &processes,
+            &["-f", "-g", "2x2", "--exclude-title", "splash*"],
+        );
+        let hidden = windows.create_at(ms(100), window(100, "hidden").hidden());
+        let foreign = windows.create_at(ms(100), window(200, "foreign"));
+        let splash = windows.create_at(ms(100), window(100, "splash screen"));
+        let main = windows.create_at(ms(100), window(100, "main"));
+        clock.advance(ms(100));
+        follower.poll(clock.now());
+        assert_eq!(follower.windows(), [main]);
+        for ignored in [existing, hidden, foreign, splash] {
+            assert_eq!(follower.engine().unwrap().cell_of(ignored), None);
+            assert!(windows.window(ignored).unwrap().moves.is_empty());
+        }
+
+        // A child's windows are the launch's too, and hidden ones count once shown
+        processes.start_at(ms(200), FakeProcess::new(101, 100, "child"));
+        let child = windows.create_at(ms(200), window(101, "child"));
+        windows.show_at(ms(200), hidden);
+        clock.advance(ms(100));
+        follower.poll(clock.now());
+        let engine = follower.engine().unwrap();
+        assert_eq!(engine.cell_of(main), Some(0));
+        assert!(engine.cell_of(hidden).is_some());
+        assert!(engine.cell_of(child).is_some());
+        assert!(follower.pids().contains(&101));
+    }
+
+    #[test]
+    fn windows_fill_the_cells_in_order() {
+        let (clock, windows, processes) = scene();
+        let mut follower = follower(&windows, &processes, &["-f", "-g", "2x2"]);
+        let found: Vec<WindowId> = (0..4)
+            .map(|i| windows.create_at(ms(100 * (i + 1)), window(100, "app")))
+            .collect();
+        for _ in 0..4 {
+            clock.advance(ms(100));
+            follower.poll(clock.now());
+        }
+        for (idx, &window) in found.iter().enumerate() {
+            assert_eq!(follower.engine().unwrap().cell_of(window), Some(idx));
+            let moves = windows.window(window).unwrap().moves;
+            assert_eq!(moves.len(), 1);
+            assert_eq!(origin(moves[0]), cell_origin(&follower, idx));
+            assert_eq!(moves[0].width(), WINDOW.width());
+        }
+        assert_eq!(follower.windows(), found);
+    }
+
+    #[test]
+    fn find_oldest_and_find_recent_pick_opposite_ends_up_to_num_recent() {
+        let (clock, windows, processes) = scene();
+        for (pid, at) in [(201, 10), (202, 20), (203, 30)] {
+            processes.start_at(ms(at), FakeProcess::new(pid, 1, "app"));
+        }
+        clock.advance(ms(50));
+        let tracked = |args: &[&str]| {
+            let mut follower = follower(&windows, &processes, args);
+            follower.poll(clock.now());
+            let mut pids: Vec<u32> = follower.pids().iter().copied().collect();
+            pids.sort();
+            pids
+        };
+        // Process 100 is tracked either way; the oldest other match is 201
+        assert_eq!(tracked(&["-fo", "-nr", "2"]), [100, 201]);
+        assert_eq!(tracked(&["-fr", "-nr", "2"]), [100, 202, 203]);
+        assert_eq!(tracked(&["-fr"]), [100, 203]);
+    }
+
+    #[test]
+    fn a_window_that_refuses_to_move_is_retried_up_to_the_limit() {
+        let (clock, windows, processes) = scene();
+        let stubborn = windows.create_at(ms(100), window(100, "stubborn").refusing_moves(u32::MAX));
+        let shy = windows.create_at(ms(100), window(100, "shy").refusing_moves(1));
+        let mut follower = follower(&windows, &processes, &["-f", "-g", "1x2"]);
+        let (tx, events) = crossbeam_channel::unbounded();
+        follower.send_events(tx);
+        for _ in 0..(MAX_MOVE_ATTEMPTS + 3) {
+            clock.advance(POLL_INTERVAL);
+            follower.poll(clock.now());
+        }
+        let failed: Vec<(WindowId, u32)> = events
+            .try_iter()
+            .filter_map(|event| match event {
+                Event::MoveFailed { hwnd, attempts, .. } => Some((hwnd, attempts)),
+                _ => None,
+            })
+            .collect();
+        let attempts_of = |window| {
+            failed
+                .iter()
+                .filter(|&&(hwnd, _)| hwnd == window)
+                .map(|&(_, attempts)| attempts)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            attempts_of(stubborn),
+            (1..=MAX_MOVE_ATTEMPTS).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            windows.window(stubborn).unwrap().moves.len(),
+            MAX_MOVE_ATTEMPTS as usize
+        );
+        assert_eq!(windows.window(stubborn).unwrap().bounds, WINDOW);
+        // Still counted as in its cell, which nobody else gets
+        assert_eq!(follower.engine().unwrap().cell_of(stubborn), Some(0));
+
+        assert_eq!(attempts_of(shy), [1]);
+        let shy = windows.window(shy).unwrap();
+        assert_eq!(shy.moves.len(), 2);
+        assert_eq!(origin(shy.bounds), cell_origin(&follower, 1));
+        assert!(follower.is_settled());
+    }
+
+    #[test]
+    fn windows_are_closed_when_their_time_is_up() {
+        let (clock, windows, processes) = scene();
+        let first = windows.create_at(ms(300), window(100, "first"));
+        let second = windows.create_at(ms(1300), window(100, "second"));
+        let mut follower = follower(&windows, &processes, &["-f", "-g", "1x2", "-t", "2"]);
+        let end = follow(
+            &mut follower,
+            &*clock,
+            Some(clock.at(Duration::from_secs(5))),
+        );
+        assert_eq!(end, FollowEnd::Deadline);
+        assert_eq!(windows.closed(), [first, second]);
+        assert!(follower.windows().is_empty());
+        let engine = follower.engine().unwrap();
+        assert!(engine.is_free(0) && engine.is_free(1));
+
+        // Each went after its own two seconds, within a poll or so
+        let (tx, events) = crossbeam_channel::unbounded();
+        follower.send_events(tx);
+        let late = windows.create_at(clock.elapsed() + ms(100), window(100, "late"));
+        let placed_at = clock.elapsed() + ms(100);
+        let mut closed_at = None;
+        while closed_at.is_none() {
+            follower.poll(clock.now());
+            follower.wait(&*clock, POLL_INTERVAL);
+            if windows.closed().contains(&late) {
+                closed_at = Some(clock.elapsed());
+            }
+        }
+        let lifetime = closed_at.unwrap() - placed_at;
+        assert!(lifetime >= Duration::from_secs(2), "{:?}", lifetime);
+        assert!(
+            lifetime <= Duration::from_secs(2) + 3 * POLL_INTERVAL,
+            "{:?}",
+            lifetime
+        );
+        assert!(events.try_iter().any(|event| matches!(
+            event,
+            Event::Evicted {
+                hwnd,
+                reason: EvictReason::Timeout,
+                ..
+            } if hwnd == late
+        )));
+    }
+
+    #[test]
+    fn following_ends_once_every_process_of_the_launch_has_exited() {
+        let (clock, windows, processes) = scene();
+        processes.start_at(ms(500), FakeProcess::new(101, 100, "child"));
+        let child = windows.create_at(ms(600), window(101, "child"));
+        processes.exit_at(ms(1000), 100);
+        windows.destroy_at(ms(3000), child);
+        processes.exit_at(ms(3000), 101);
+        let mut follower = follower(&windows, &processes, &["-f", "-g", "1x2"]);
+        let end = follow(&mut follower, &*clock, None);
+        assert_eq!(end, FollowEnd::ProcessesExited);
+        assert!(!follower.is_alive());
+        // The child kept the launch alive after its parent exited
+        assert!(clock.elapsed() >= ms(3000));
+        assert!(clock.elapsed() < ms(3000) + 2 * POLL_INTERVAL);
+        assert!(follower.engine().unwrap().is_free(0));
+    }
+
+    #[test]
+    fn without_follow_the_loop_ends_once_the_first_windows_are_in_place() {
+        let (clock, windows, processes) = scene();
+        let first = windows.create_at(ms(600), window(100, "first").refusing_moves(1));
+        let mut follower = follower(&windows, &processes, &["-g", "1x2"]);
+        let end = follow(&mut follower, &*clock, None);
+        assert_eq!(end, FollowEnd::Finished);
+        assert_eq!(
+            origin(windows.window(first).unwrap().bounds),
+            cell_origin(&follower, 0)
+        );
+    }
+
+    #[test]
+    fn placed_windows_are_shaken_and_the_launcher_keeps_the_focus() {
+        let (clock, windows, processes) = scene();
+        let launcher = windows.create_at(Duration::ZERO, FakeWindow::new(1, "Console", "", WINDOW));
+        windows.focus(launcher);
+        let mut follower = follower(&windows, &processes, &["-f", "-g", "1x2", "-rlf"]);
+        let first = windows.create_at(ms(100), window(100, "first"));
+        clock.advance(POLL_INTERVAL);
+        windows.focus(first);
+        follower.poll(clock.now());
+        assert_eq!(windows.foreground(), Some(launcher));
+        assert_eq!(windows.window(first).unwrap().shakes, 0);
+        // Once it is checked to be in its cell
+        clock.advance(POLL_INTERVAL);
+        follower.poll(clock.now());
+        assert_eq!(windows.window(first).unwrap().shakes, 1);
+    }
+
+    #[test]
+    fn the_parent_keeps_the_focus_and_nothing_is_shaken_without_a_duration() {
+        let (clock, windows, processes) = scene();
+        let parent = windows.create_at(ms(100), window(100, "parent"));
+        let child = windows.create_at(ms(200), window(100, "child"));
+        let mut follower = follower(
+            &windows,
+            &processes,
+            &["-f", "-g", "1x2", "-rpf", "-sd", "0"],
+        );
+        for _ in 0..2 {
+            clock.advance(ms(100));
+            windows.focus(child);
+            follower.poll(clock.now());
+        }
+        assert_eq!(follower.engine().unwrap().cell_of(child), Some(1));
+        assert_eq!(windows.foreground(), Some(parent));
+        clock.advance(POLL_INTERVAL);
+        follower.poll(clock.now());
+        assert_eq!(windows.window(parent).unwrap().shakes, 0);
+        assert_eq!(windows.window(child).unwrap().shakes, 0);
+    }
+}
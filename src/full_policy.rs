@@ -0,0 +1,315 @@
+// src/full_policy.rs
+//! What the grid engine does when a window arrives and every cell is taken
+//! (`--on-full`).
+//!
+//! Each policy is a [`FullStrategy`] the [`GridEngine`] calls from
+//! [`GridEngine::next_placement`]; it may release a cell, stack onto one or re-lay out
+//! the grid, and returns the [`Placement`] for the caller to apply. A custom strategy
+//! can be installed with [`GridEngine::set_on_full`].
+use crate::grid::{GridEngine, Placement};
+use std::sync::Arc;
+
+/// Makes room for one more window in a full grid.
+pub trait FullStrategy: std::fmt::Debug + Send + Sync {
+    /// Called when no candidate cell of `engine` is free.
+    fn make_room(&self, engine: &mut GridEngine) -> Placement;
+}
+
+/// `--on-full` policies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FullPolicy {
+    /// Close the oldest window and take its cell.
+    #[default]
+    CloseOldest,
+    /// Hold the new window until a cell frees.
+    Queue,
+    /// Minimize the oldest window and take its cell.
+    MinimizeOldest,
+    /// Move the oldest window to this monitor, outside the grid, and take its cell.
+    OverflowMonitor(i32),
+    /// Cascade the new window over the cell holding the fewest windows.
+    Stack,
+    /// Add a row or column to the first grid and lay every window out again.
+    Grow,
+}
+
+impl FullPolicy {
+    /// The built-in strategy for this policy.
+    pub fn strategy(self) -> Arc<dyn FullStrategy> {
+        match self {
+            FullPolicy::CloseOldest => Arc::new(CloseOldest),
+            FullPolicy::Queue => Arc::new(Queue),
+            FullPolicy::MinimizeOldest => Arc::new(MinimizeOldest),
+            FullPolicy::OverflowMonitor(monitor) => Arc::new(OverflowMonitor(monitor)),
+            FullPolicy::Stack => Arc::new(Stack),
+            FullPolicy::Grow => Arc::new(Grow),
+        }
+    }
+}
+
+impl std::str::FromStr for FullPolicy {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const EXPECTED: &str =
+            "close-oldest, queue, minimize-oldest, overflow-monitor=MONITOR, stack or grow";
+        let s = s.trim().to_ascii_lowercase();
+        match s.split_once(['=', ' ']) {
+            None => match s.as_str() {
+                "close-oldest" => Ok(FullPolicy::CloseOldest),
+                "queue" => Ok(FullPolicy::Queue),
+                "minimize-oldest" => Ok(FullPolicy::MinimizeOldest),
+                "stack" => Ok(FullPolicy::Stack),
+                "grow" => Ok(FullPolicy::Grow),
+                _ => Err(EXPECTED),
+            },
+            Some(("overflow-monitor", monitor)) => monitor
+                .trim()
+                .parse()
+                .map(FullPolicy::OverflowMonitor)
+                .map_err(|_| EXPECTED),
+            _ => Err(EXPECTED),
+        }
+    }
+}
+
+impl std::fmt::Display for FullPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FullPolicy::CloseOldest => write!(f, "close-oldest"),
+            FullPolicy::Queue => write!(f, "queue"),
+            FullPolicy::MinimizeOldest => write!(f, "minimize-oldest"),
+            FullPolicy::OverflowMonitor(monitor) => write!(f, "overflow-monitor={}", monitor),
+            FullPolicy::Stack => write!(f, "stack"),
+            FullPolicy::Grow => write!(f, "grow"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CloseOldest;
+
+impl FullStrategy for CloseOldest {
+    fn make_room(&self, engine: &mut GridEngine) -> Placement {
+        match engine.evict_oldest() {
+            Some((cell, window)) => Placement::Evict { cell, window },
+            None => Placement::Full,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Queue;
+
+impl FullStrategy for Queue {
+    fn make_room(&self, _engine: &mut GridEngine) -> Placement {
+        Placement::Full
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MinimizeOldest;
+
+impl FullStrategy for MinimizeOldest {
+    fn make_room(&self, engine: &mut GridEngine) -> Placement {
+        match engine.evict_oldest() {
+            Some((cell, window)) => Placement::Minimize { cell, window },
+            None => Placement::Full,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OverflowMonitor(pub i32);
+
+impl FullStrategy for OverflowMonitor {
+    fn make_room(&self, engine: &mut GridEngine) -> Placement {
+        match engine.evict_oldest() {
+            Some((cell, window)) => Placement::Overflow {
+                cell,
+                window,
+                monitor: self.0,
+            },
+            None => Placement::Full,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Stack;
+
+impl FullStrategy for Stack {
+    fn make_room(&self, engine: &mut GridEngine) -> Placement {
+        // Shallowest stack first, lowest cell on ties, so every cell fills evenly
+        engine
+            .candidate_cells()
+            .into_iter()
+            .filter(|&idx| engine.occupant(idx) != engine.protected())
+            .min_by_key(|&idx| (engine.stack_depth(idx), idx))
+            .map_or(Placement::Full, |cell| Placement::Stack {
+                cell,
+                depth: engine.stack_depth(cell),
+            })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Grow;
+
+impl FullStrategy for Grow {
+    fn make_room(&self, engine: &mut GridEngine) -> Placement {
+        if !engine.grow() {
+            return Placement::Full;
+        }
+        engine
+            .candidate_cells()
+            .into_iter()
+            .find(|&idx| engine.is_free(idx))
+            .map_or(Placement::Full, |cell| Placement::Grown { cell })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{GridLayout, GridPlacementMode, GridPool, MIN_GROWN_CELL, Rect};
+    use std::time::{Duration, Instant};
+
+    /// A full 1x2 grid on a 1000x500 monitor: window 1 went in first, then window 2.
+    fn full_engine(policy: FullPolicy, start: Instant) -> GridEngine {
+        let pool = GridPool::single(0, GridLayout::new(1, 2, Rect::new(0, 0, 1000, 500)));
+        let mut engine = GridEngine::new(pool, GridPlacementMode::FirstFree);
+        engine.set_on_full(policy.strategy());
+        engine.assign(1, 1, start);
+        engine.assign(0, 2, start + Duration::from_secs(1));
+        engine
+    }
+
+    #[test]
+    fn close_oldest_releases_the_oldest_window() {
+        let start = Instant::now();
+        let mut engine = full_engine(FullPolicy::CloseOldest, start);
+        assert_eq!(
+            engine.next_placement(),
+            Placement::Evict { cell: 1, window: 1 }
+        );
+        assert!(engine.has_been_full());
+        assert!(engine.is_free(1));
+        assert_eq!(engine.cell_of(1), None);
+
+        // The protected window and reserved cells are left alone
+        engine.assign(1, 3, start + Duration::from_secs(2));
+        engine.protect(Some(2));
+        assert_eq!(
+            engine.next_placement(),
+            Placement::Evict { cell: 1, window: 3 }
+        );
+        engine.assign(1, 4, start + Duration::from_secs(3));
+        engine.reserve([1]);
+        assert_eq!(engine.next_placement(), Placement::Full);
+        assert_eq!(engine.occupancy(), [Some(2), Some(4)]);
+    }
+
+    #[test]
+    fn queue_leaves_the_grid_alone() {
+        let mut engine = full_engine(FullPolicy::Queue, Instant::now());
+        assert_eq!(engine.next_placement(), Placement::Full);
+        assert!(engine.has_been_full());
+        assert_eq!(engine.occupancy(), [Some(2), Some(1)]);
+    }
+
+    #[test]
+    fn minimize_and_overflow_release_the_oldest_window() {
+        let start = Instant::now();
+        let mut engine = full_engine(FullPolicy::MinimizeOldest, start);
+        assert_eq!(
+            engine.next_placement(),
+            Placement::Minimize { cell: 1, window: 1 }
+        );
+        assert_eq!(engine.occupancy(), [Some(2), None]);
+
+        let mut engine = full_engine(FullPolicy::OverflowMonitor(1), start);
+        assert_eq!(
+            engine.next_placement(),
+            Placement::Overflow {
+                cell: 1,
+                window: 1,
+                monitor: 1
+            }
+        );
+        assert_eq!(engine.occupancy(), [Some(2), None]);
+    }
+
+    #[test]
+    fn stack_fills_the_shallowest_cell_first() {
+        let start = Instant::now();
+        let mut engine = full_engine(FullPolicy::Stack, start);
+        let mut depths = Vec::new();
+        for window in 3..=6 {
+            let Placement::Stack { cell, depth } = engine.next_placement() else {
+                panic!("expected a stack placement");
+            };
+            assert_eq!(engine.stack(cell, window, start), Some(depth));
+            depths.push((cell, depth));
+        }
+        assert_eq!(depths, [(0, 1), (1, 1), (0, 2), (1, 2)]);
+        assert_eq!(engine.stack_position(5), Some(2));
+        assert_eq!(engine.occupancy(), [Some(2), Some(1)]);
+
+        // Never over the protected window
+        engine.protect(Some(2));
+        assert_eq!(
+            engine.next_placement(),
+            Placement::Stack { cell: 1, depth: 3 }
+        );
+    }
+
+    #[test]
+    fn grow_adds_cells_until_they_would_get_too_small() {
+        let start = Instant::now();
+        let mut engine = full_engine(FullPolicy::Grow, start);
+        assert_eq!(engine.next_placement(), Placement::Grown { cell: 2 });
+        let layout = &engine.pool.primary().layout;
+        assert_eq!((layout.rows, layout.cols), (1, 3));
+        // The windows keep their order in the larger grid
+        assert_eq!(engine.occupancy(), [Some(2), Some(1), None]);
+
+        let mut window = 3;
+        loop {
+            match engine.next_placement() {
+                Placement::Cell(cell) | Placement::Grown { cell } => {
+                    engine.assign(cell, window, start);
+                    window += 1;
+                }
+                Placement::Full => break,
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        let layout = engine.pool.primary().layout.clone();
+        assert!(engine.free_cells().is_empty());
+        assert_eq!(engine.cells().len(), layout.cell_count());
+        for rect in layout.cell_rects() {
+            assert!(rect.width() >= MIN_GROWN_CELL && rect.height() >= MIN_GROWN_CELL);
+        }
+        assert_eq!(layout.cell_count(), window as usize - 1);
+    }
+
+    #[test]
+    fn strategies_parse_and_print_alike() {
+        for policy in [
+            FullPolicy::CloseOldest,
+            FullPolicy::Queue,
+            FullPolicy::MinimizeOldest,
+            FullPolicy::OverflowMonitor(2),
+            FullPolicy::Stack,
+            FullPolicy::Grow,
+        ] {
+            assert_eq!(policy.to_string().parse(), Ok(policy));
+        }
+        assert_eq!(
+            "overflow-monitor 1".parse(),
+            Ok(FullPolicy::OverflowMonitor(1))
+        );
+        assert!("shrink".parse::<FullPolicy>().is_err());
+    }
+}
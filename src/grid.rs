@@ -0,0 +1,1442 @@
+// src/grid.rs
+//! Platform-neutral grid layout and cell occupancy.
+//!
+//! Nothing in here touches HWNDs or winapi: windows are identified by an opaque
+//! [`WindowId`], geometry is plain [`Rect`]s, and every decision (which cell, which
+//! window to evict) is returned to the caller to apply.
+use crate::full_policy::{FullPolicy, FullStrategy};
+use crate::timer_wheel::TimerWheel;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Opaque window identifier (an HWND cast to `isize` on Windows).
+pub type WindowId = isize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Rect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl Rect {
+    pub fn new(left: i32, top: i32, right: i32, bottom: i32) -> Self {
+        Self {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.right - self.left
+    }
+
+    pub fn height(&self) -> i32 {
+        self.bottom - self.top
+    }
+
+    pub fn center(&self) -> (i32, i32) {
+        (self.left + self.width() / 2, self.top + self.height() / 2)
+    }
+
+    /// Shrinks the rectangle by `by` pixels on every side.
+    pub fn inset(&self, by: i32) -> Self {
+        Self::new(
+            self.left + by,
+            self.top + by,
+            self.right - by,
+            self.bottom - by,
+        )
+    }
+}
+
+/// Splits `len` pixels starting at `start` into `n` spans separated by `gutter`
+/// pixels, sized in proportion to `weights` (uniform when it is empty). The spans
+/// exactly tile the range: pixels lost to integer division go one each to the
+/// leading spans. Returns (start, length) per span.
+fn split_span(start: i32, len: i32, n: u32, weights: &[u32], gutter: i32) -> Vec<(i32, i32)> {
+    let n = n.max(1) as usize;
+    let weights: Vec<i64> = if weights.len() == n && weights.iter().any(|&w| w > 0) {
+        weights.iter().map(|&w| w as i64).collect()
+    } else {
+        vec![1; n]
+    };
+    let total: i64 = weights.iter().sum();
+    let usable = (len - gutter * (n as i32 - 1)).max(0) as i64;
+    let mut sizes: Vec<i64> = weights.iter().map(|w| usable * w / total).collect();
+    let remainder = (usable - sizes.iter().sum::<i64>()) as usize;
+    for size in sizes.iter_mut().take(remainder) {
+        *size += 1;
+    }
+    let mut pos = start;
+    sizes
+        .into_iter()
+        .map(|size| {
+            let span = (pos, size as i32);
+            pos += size as i32 + gutter;
+            span
+        })
+        .collect()
+}
+
+/// A rectangular block of cells: `rows` x `cols` cells starting at (`row`, `col`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellRegion {
+    pub row: u32,
+    pub col: u32,
+    pub rows: u32,
+    pub cols: u32,
+}
+
+impl CellRegion {
+    /// A region covering the single cell (`row`, `col`).
+    pub fn cell(row: u32, col: u32) -> Self {
+        Self {
+            row,
+            col,
+            rows: 1,
+            cols: 1,
+        }
+    }
+}
+
+impl Default for CellRegion {
+    fn default() -> Self {
+        Self::cell(0, 0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GridPlacementMode {
+    /// Always use the lowest-index free cell, falling back on the `--on-full` policy
+    /// when every cell is taken.
+    #[default]
+    FirstFree,
+    /// Walk the cells round-robin, regardless of occupancy.
+    Sequential,
+}
+
+impl std::str::FromStr for GridPlacementMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "firstfree" | "first-free" => Ok(GridPlacementMode::FirstFree),
+            "sequential" => Ok(GridPlacementMode::Sequential),
+            _ => Err(()),
+        }
+    }
+}
+
+/// What happens to a window whose timeout expires (`--timeout-action`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExpiryPolicy {
+    /// Ask the window to close.
+    #[default]
+    Close,
+    /// Minimize the window and leave it alone from then on.
+    Minimize,
+    /// Move the window to this monitor, outside the grid, and leave it alone.
+    Overflow(i32),
+}
+
+impl std::str::FromStr for ExpiryPolicy {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const EXPECTED: &str = "close, minimize or overflow=MONITOR";
+        let s = s.to_ascii_lowercase();
+        match s.split_once('=') {
+            None if s == "close" => Ok(ExpiryPolicy::Close),
+            None if s == "minimize" => Ok(ExpiryPolicy::Minimize),
+            Some(("overflow", monitor)) => monitor
+                .parse()
+                .map(ExpiryPolicy::Overflow)
+                .map_err(|_| EXPECTED),
+            _ => Err(EXPECTED),
+        }
+    }
+}
+
+impl std::fmt::Display for ExpiryPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExpiryPolicy::Close => write!(f, "close"),
+            ExpiryPolicy::Minimize => write!(f, "minimize"),
+            ExpiryPolicy::Overflow(monitor) => write!(f, "overflow={}", monitor),
+        }
+    }
+}
+
+/// Geometry of a `rows` x `cols` grid laid over a work area.
+///
+/// Cells exactly tile the work area minus `margin` (pixels kept free along each
+/// edge) and `gutter` (pixels between neighbouring cells). Rows and columns are
+/// sized by their relative weights; empty weights mean uniform cells.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GridLayout {
+    pub rows: u32,
+    pub cols: u32,
+    pub work_area: Rect,
+    pub gutter: i32,
+    pub margin: i32,
+    pub row_weights: Vec<u32>,
+    pub col_weights: Vec<u32>,
+}
+
+impl GridLayout {
+    pub fn new(rows: u32, cols: u32, work_area: Rect) -> Self {
+        Self {
+            rows,
+            cols,
+            work_area,
+            gutter: 0,
+            margin: 0,
+            row_weights: Vec::new(),
+            col_weights: Vec::new(),
+        }
+    }
+
+    pub fn with_row_weights(mut self, weights: Vec<u32>) -> Self {
+        self.row_weights = weights;
+        self
+    }
+
+    pub fn with_col_weights(mut self, weights: Vec<u32>) -> Self {
+        self.col_weights = weights;
+        self
+    }
+
+    pub fn with_gutter(mut self, gutter: i32) -> Self {
+        self.gutter = gutter.max(0);
+        self
+    }
+
+    pub fn with_margin(mut self, margin: i32) -> Self {
+        self.margin = margin.max(0);
+        self
+    }
+
+    /// The work area minus the outer margin; what the cells tile.
+    pub fn grid_area(&self) -> Rect {
+        self.work_area.inset(self.margin)
+    }
+
+    /// `rows * cols`; the `-g` parser keeps it to [`MAX_GRID_CELLS`].
+    pub fn cell_count(&self) -> usize {
+        (self.rows as usize).saturating_mul(self.cols as usize)
+    }
+
+    /// Returns (row, col) for a cell index.
+    pub fn row_col(&self, idx: usize) -> (u32, u32) {
+        (idx as u32 / self.cols, idx as u32 % self.cols)
+    }
+
+    /// Returns the cell index for (row, col), or None if it is outside the grid.
+    pub fn index(&self, row: u32, col: u32) -> Option<usize> {
+        if row >= self.rows || col >= self.cols {
+            return None;
+        }
+        Some((row * self.cols + col) as usize)
+    }
+
+    /// Left edge and width of every column.
+    pub fn column_spans(&self) -> Vec<(i32, i32)> {
+        let area = self.grid_area();
+        split_span(
+            area.left,
+            area.width(),
+            self.cols,
+            &self.col_weights,
+            self.gutter,
+        )
+    }
+
+    /// Top edge and height of every row.
+    pub fn row_spans(&self) -> Vec<(i32, i32)> {
+        let area = self.grid_area();
+        split_span(
+            area.top,
+            area.height(),
+            self.rows,
+            &self.row_weights,
+            self.gutter,
+        )
+    }
+
+    /// Screen rectangle of a cell.
+    pub fn cell_rect(&self, idx: usize) -> Rect {
+        let (row, col) = self.row_col(idx);
+        let (left, width) = self.column_spans()[col as usize];
+        let (top, height) = self.row_spans()[row as usize];
+        Rect::new(left, top, left + width, top + height)
+    }
+
+    /// Rectangles of every cell, in index order.
+    pub fn cell_rects(&self) -> Vec<Rect> {
+        let (columns, rows) = (self.column_spans(), self.row_spans());
+        rows.iter()
+            .flat_map(|&(top, height)| {
+                columns
+                    .iter()
+                    .map(move |&(left, width)| Rect::new(left, top, left + width, top + height))
+            })
+            .collect()
+    }
+
+    /// Indices of the cells covered by `region`, clipped to the grid.
+    pub fn region_cells(&self, region: &CellRegion) -> Vec<usize> {
+        let rows = region.row..region.row.saturating_add(region.rows).min(self.rows);
+        rows.flat_map(|row| {
+            let cols = region.col..region.col.saturating_add(region.cols).min(self.cols);
+            cols.filter_map(move |col| self.index(row, col))
+        })
+        .collect()
+    }
+
+    /// Screen rectangle covering every cell of `region` (and the gutters between
+    /// them), or None if the region lies outside the grid.
+    pub fn region_rect(&self, region: &CellRegion) -> Option<Rect> {
+        let cells = self.region_cells(region);
+        let first = self.cell_rect(*cells.first()?);
+        let last = self.cell_rect(*cells.last()?);
+        Some(Rect::new(first.left, first.top, last.right, last.bottom))
+    }
+
+    /// Point used to probe which window visually owns a cell.
+    pub fn cell_center(&self, idx: usize) -> (i32, i32) {
+        self.cell_rect(idx).center()
+    }
+
+    /// Where a `win_width` x `win_height` window should go for cell `idx`.
+    ///
+    /// With `fit` the window takes the whole cell. Otherwise it keeps its size, is
+    /// centered in the cell and clamped to the grid area; None if it is larger than
+    /// the grid area.
+    pub fn place_in_cell(
+        &self,
+        idx: usize,
+        win_width: i32,
+        win_height: i32,
+        fit: bool,
+    ) -> Option<Rect> {
+        let cell = self.cell_rect(idx);
+        if fit {
+            return Some(cell);
+        }
+        let area = self.grid_area();
+        let (max_x, max_y) = (area.right - win_width, area.bottom - win_height);
+        if area.left > max_x || area.top > max_y {
+            return None;
+        }
+        let x = (cell.left + (cell.width() - win_width) / 2).clamp(area.left, max_x);
+        let y = (cell.top + (cell.height() - win_height) / 2).clamp(area.top, max_y);
+        Some(Rect::new(x, y, x + win_width, y + win_height))
+    }
+}
+
+/// One monitor's grid within a [`GridPool`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonitorGrid {
+    pub monitor: i32,
+    pub layout: GridLayout,
+    /// Sized by the engine to the windows it holds (`-g auto`).
+    pub auto: bool,
+}
+
+/// Grids on one or more monitors treated as a single ordered pool of cells: every
+/// cell of the first monitor's grid, then every cell of the next, and so on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GridPool {
+    pub monitors: Vec<MonitorGrid>,
+}
+
+impl GridPool {
+    /// Panics if `monitors` is empty.
+    pub fn new(monitors: Vec<MonitorGrid>) -> Self {
+        assert!(
+            !monitors.is_empty(),
+            "a grid pool needs at least one monitor"
+        );
+        Self { monitors }
+    }
+
+    /// A pool with a single monitor's grid.
+    pub fn single(monitor: i32, layout: GridLayout) -> Self {
+        Self::new(vec![MonitorGrid {
+            monitor,
+            layout,
+            auto: false,
+        }])
+    }
+
+    /// The first monitor's grid.
+    pub fn primary(&self) -> &MonitorGrid {
+        &self.monitors[0]
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.monitors.iter().map(|m| m.layout.cell_count()).sum()
+    }
+
+    /// Each monitor's grid with the pool index of its first cell.
+    pub fn sections(&self) -> impl Iterator<Item = (usize, &MonitorGrid)> {
+        self.monitors.iter().scan(0, |offset, grid| {
+            let start = *offset;
+            *offset += grid.layout.cell_count();
+            Some((start, grid))
+        })
+    }
+
+    /// Maps a pool index to its monitor grid and the index within that grid.
+    pub fn locate(&self, idx: usize) -> Option<(&MonitorGrid, usize)> {
+        self.sections()
+            .find(|(start, grid)| idx < start + grid.layout.cell_count())
+            .map(|(start, grid)| (grid, idx - start))
+    }
+
+    pub fn monitor_of(&self, idx: usize) -> Option<i32> {
+        self.locate(idx).map(|(grid, _)| grid.monitor)
+    }
+
+    /// The grid on `monitor` (the first grid when None or not in the pool) and the
+    /// pool index of its first cell.
+    fn section_for(&self, monitor: Option<i32>) -> (usize, &MonitorGrid) {
+        monitor
+            .and_then(|m| self.sections().find(|(_, grid)| grid.monitor == m))
+            .unwrap_or((0, self.primary()))
+    }
+
+    /// Pool index of (`row`, `col`) on `monitor`'s grid (see [`GridPool::region_cells`]).
+    pub fn index(&self, monitor: Option<i32>, row: u32, col: u32) -> Option<usize> {
+        let (start, grid) = self.section_for(monitor);
+        grid.layout.index(row, col).map(|idx| start + idx)
+    }
+
+    /// Screen rectangle of a cell. Panics if `idx` is outside the pool.
+    pub fn cell_rect(&self, idx: usize) -> Rect {
+        let (grid, local) = self.locate(idx).expect("cell index outside the grid pool");
+        grid.layout.cell_rect(local)
+    }
+
+    pub fn cell_center(&self, idx: usize) -> (i32, i32) {
+        self.cell_rect(idx).center()
+    }
+
+    /// Rectangles of every cell, in pool order.
+    pub fn cell_rects(&self) -> Vec<Rect> {
+        self.monitors
+            .iter()
+            .flat_map(|m| m.layout.cell_rects())
+            .collect()
+    }
+
+    /// See [`GridLayout::place_in_cell`]; the window is kept on the cell's monitor.
+    pub fn place_in_cell(
+        &self,
+        idx: usize,
+        win_width: i32,
+        win_height: i32,
+        fit: bool,
+    ) -> Option<Rect> {
+        let (grid, local) = self.locate(idx)?;
+        grid.layout.place_in_cell(local, win_width, win_height, fit)
+    }
+
+    /// Pool indices of the cells `region` covers on `monitor`'s grid. A monitor that
+    /// is not in the pool (or None) means the first grid.
+    pub fn region_cells(&self, monitor: Option<i32>, region: &CellRegion) -> Vec<usize> {
+        let (start, grid) = self.section_for(monitor);
+        grid.layout
+            .region_cells(region)
+            .into_iter()
+            .map(|idx| start + idx)
+            .collect()
+    }
+
+    pub fn region_rect(&self, monitor: Option<i32>, region: &CellRegion) -> Option<Rect> {
+        self.section_for(monitor).1.layout.region_rect(region)
+    }
+}
+
+/// Smallest width or height [`GridEngine::grow`] and auto grids shrink cells to.
+pub const MIN_GROWN_CELL: i32 = 200;
+
+/// Most cells a `-g` grid may have; every cell is allocated up front.
+pub const MAX_GRID_CELLS: u32 = 4096;
+
+/// How long the number of windows has to stay put before an auto grid (`-g auto`)
+/// shrinks to fit them.
+pub const AUTO_GRID_SETTLE: Duration = Duration::from_secs(2);
+
+/// Rows and columns for `count` windows on `area`: the grid whose cells have the
+/// longest shorter side, the one with fewer cells on ties. On a 1920x1080 monitor that
+/// gives 1x1, 1x2, 1x3, 2x2, 2x3, 2x3, 2x4, 2x4, 2x5, ...
+pub fn auto_dimensions(count: usize, area: Rect) -> (u32, u32) {
+    let count = count.max(1) as u32;
+    (1..=count)
+        .map(|cols| (count.div_ceil(cols), cols))
+        .max_by_key(|&(rows, cols)| {
+            let side = (area.width() / cols as i32).min(area.height() / rows as i32);
+            (side, std::cmp::Reverse(rows * cols))
+        })
+        .unwrap_or((1, 1))
+}
+
+/// How far each window stacked in a cell is offset from the one under it.
+pub const STACK_OFFSET: i32 = 32;
+
+/// `cell` with its top-left corner moved `depth` cascade steps in, keeping at least a
+/// quarter of the cell.
+pub fn stacked_rect(cell: Rect, depth: usize) -> Rect {
+    let offset = (depth as i32 * STACK_OFFSET)
+        .min(cell.width() * 3 / 4)
+        .min(cell.height() * 3 / 4);
+    Rect::new(
+        cell.left + offset,
+        cell.top + offset,
+        cell.right,
+        cell.bottom,
+    )
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Cell {
+    pub window: Option<WindowId>,
+    pub filled_at: Option<Instant>,
+    /// Windows cascaded over `window` (`--on-full stack`), bottom first, with the time
+    /// each was stacked.
+    pub stacked: Vec<(WindowId, Instant)>,
+}
+
+/// Decision returned by [`GridEngine::next_placement`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    /// Use this cell.
+    Cell(usize),
+    /// The grid was full: `window` has been released from `cell` and should be closed.
+    Evict { cell: usize, window: WindowId },
+    /// The grid was full: `window` has been released from `cell` and should be
+    /// minimized.
+    Minimize { cell: usize, window: WindowId },
+    /// The grid was full: `window` has been released from `cell` and should be moved to
+    /// `monitor`.
+    Overflow {
+        cell: usize,
+        window: WindowId,
+        monitor: i32,
+    },
+    /// The grid was full: cascade the window `depth` steps over `cell`'s occupant and
+    /// record it with [`GridEngine::stack`].
+    Stack { cell: usize, depth: usize },
+    /// The grid was re-laid out with more cells; every occupant has to be moved to its
+    /// cell again before the window goes into `cell`.
+    Grown { cell: usize },
+    /// Nothing can be placed now: the window should wait for a cell to free. Also
+    /// returned when every occupied cell is reserved or protected.
+    Full,
+}
+
+/// Tracks which window occupies which cell of a [`GridPool`], and when each one's
+/// timeout runs out.
+#[derive(Debug, Clone)]
+pub struct GridEngine {
+    pub pool: GridPool,
+    pub placement_mode: GridPlacementMode,
+    cells: Vec<Cell>,
+    window_to_cell: HashMap<WindowId, usize>,
+    reserved: Vec<usize>,
+    protected: Option<WindowId>,
+    next_cell: usize,
+    has_been_full: bool,
+    /// Deadline given to every newly placed window (`--timeout`).
+    timeout: Option<Duration>,
+    timers: TimerWheel,
+    /// Makes room when the grid is full (`--on-full`).
+    on_full: Arc<dyn FullStrategy>,
+    /// Windows in the grid when [`GridEngine::settle`] last looked, and since when.
+    auto_count: usize,
+    auto_count_since: Option<Instant>,
+    /// Windows in the grid when the auto grids last settled.
+    settled_count: usize,
+}
+
+impl GridEngine {
+    pub fn new(pool: GridPool, placement_mode: GridPlacementMode) -> Self {
+        let cells = vec![Cell::default(); pool.cell_count()];
+        Self {
+            pool,
+            placement_mode,
+            cells,
+            window_to_cell: HashMap::new(),
+            reserved: Vec::new(),
+            protected: None,
+            next_cell: 0,
+            has_been_full: false,
+            timeout: None,
+            timers: TimerWheel::default(),
+            on_full: FullPolicy::default().strategy(),
+            auto_count: 0,
+            auto_count_since: None,
+            settled_count: 0,
+        }
+    }
+
+    /// Replaces the strategy [`GridEngine::next_placement`] falls back on when every
+    /// candidate cell is taken.
+    pub fn set_on_full(&mut self, strategy: Arc<dyn FullStrategy>) {
+        self.on_full = strategy;
+    }
+
+    pub fn on_full(&self) -> &Arc<dyn FullStrategy> {
+        &self.on_full
+    }
+
+    /// Gives every window placed from now on a deadline `timeout` after it was placed.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// The deadlines of the windows in the grid. Pausing, extending or rescheduling goes
+    /// through here; placing and releasing windows starts and cancels their timers.
+    pub fn timers(&self) -> &TimerWheel {
+        &self.timers
+    }
+
+    pub fn timers_mut(&mut self) -> &mut TimerWheel {
+        &mut self.timers
+    }
+
+    pub fn cells(&self) -> &[Cell] {
+        &self.cells
+    }
+
+    pub fn cell(&self, idx: usize) -> Option<&Cell> {
+        self.cells.get(idx)
+    }
+
+    /// Occupant of each cell, in index order.
+    pub fn occupancy(&self) -> Vec<Option<WindowId>> {
+        self.cells.iter().map(|c| c.window).collect()
+    }
+
+    /// Keeps children out of `cells` (the parent's region); only the protected
+    /// (parent) window goes there. Replaces any previous reservation.
+    pub fn reserve(&mut self, cells: impl IntoIterator<Item = usize>) {
+        let total_cells = self.cells.len();
+        self.reserved = cells.into_iter().filter(|&i| i < total_cells).collect();
+    }
+
+    pub fn reserved(&self) -> &[usize] {
+        &self.reserved
+    }
+
+    pub fn is_reserved(&self, idx: usize) -> bool {
+        self.reserved.contains(&idx)
+    }
+
+    /// Marks a window (the parent) that must never be evicted or time out.
+    pub fn protect(&mut self, window: Option<WindowId>) {
+        if let Some(window) = window {
+            self.timers.cancel(window);
+        }
+        self.protected = window;
+    }
+
+    pub fn protected(&self) -> Option<WindowId> {
+        self.protected
+    }
+
+    /// True once a placement found every cell taken.
+    pub fn has_been_full(&self) -> bool {
+        self.has_been_full
+    }
+
+    pub fn cell_of(&self, window: WindowId) -> Option<usize> {
+        self.window_to_cell.get(&window).copied()
+    }
+
+    pub fn occupant(&self, idx: usize) -> Option<WindowId> {
+        self.cells.get(idx).and_then(|c| c.window)
+    }
+
+    pub fn is_free(&self, idx: usize) -> bool {
+        self.occupant(idx).is_none()
+    }
+
+    /// Cells a child window may be placed in, in preference order.
+    pub fn candidate_cells(&self) -> Vec<usize> {
+        (0..self.cells.len())
+            .filter(|&idx| !self.is_reserved(idx))
+            .collect()
+    }
+
+    pub fn free_cells(&self) -> Vec<usize> {
+        (0..self.cells.len())
+            .filter(|&idx| self.is_free(idx))
+            .collect()
+    }
+
+    /// Puts `window` in cell `idx` in place of its occupant, moving it out of any
+    /// previous cell, and starts its timeout. Windows stacked in the cell stay there, over
+    /// `window`. Returns the window previously occupying `idx`, if any.
+    pub fn assign(&mut self, idx: usize, window: WindowId, now: Instant) -> Option<WindowId> {
+        if idx >= self.cells.len() {
+            return None;
+        }
+        self.release_window(window);
+        let cell = &mut self.cells[idx];
+        let previous = cell.window.replace(window);
+        cell.filled_at = Some(now);
+        if let Some(previous) = previous {
+            self.window_to_cell.remove(&previous);
+            self.timers.cancel(previous);
+        }
+        self.window_to_cell.insert(window, idx);
+        if let Some(timeout) = self.timeout
+            && Some(window) != self.protected
+        {
+            self.timers.schedule(window, now + timeout);
+        }
+        previous
+    }
+
+    /// Removes the occupant of cell `idx` from the grid and returns it. As with
+    /// [`GridEngine::release_window`], the first window stacked over it takes its place.
+    pub fn release_cell(&mut self, idx: usize) -> Option<WindowId> {
+        let window = self.occupant(idx)?;
+        self.release_window(window);
+        Some(window)
+    }
+
+    /// Removes `window` from the grid, returning the cell it occupied. A window stacked
+    /// over it takes its place, so the cell stays occupied.
+    pub fn release_window(&mut self, window: WindowId) -> Option<usize> {
+        let idx = self.window_to_cell.remove(&window)?;
+        self.timers.cancel(window);
+        let cell = &mut self.cells[idx];
+        if cell.window == Some(window) {
+            match cell.stacked.is_empty() {
+                true => *cell = Cell::default(),
+                false => {
+                    let (next, stacked_at) = cell.stacked.remove(0);
+                    cell.window = Some(next);
+                    cell.filled_at = Some(stacked_at);
+                }
+            }
+        } else {
+            cell.stacked.retain(|&(w, _)| w != window);
+        }
+        Some(idx)
+    }
+
+    /// Removes a `window` that was closed, returning the cell it left and how long it
+    /// held it. The cell is only free if nothing was stacked in it.
+    pub fn window_closed(&mut self, window: WindowId, now: Instant) -> Option<(usize, Duration)> {
+        let idx = self.cell_of(window)?;
+        let cell = &self.cells[idx];
+        let filled_at = match cell.window == Some(window) {
+            true => cell.filled_at,
+            false => cell
+                .stacked
+                .iter()
+                .find(|&&(w, _)| w == window)
+                .map(|&(_, at)| at),
+        };
+        self.release_window(window);
+        Some((
+            idx,
+            filled_at.map_or(Duration::ZERO, |t| now.duration_since(t)),
+        ))
+    }
+
+    /// How far `window` is cascaded within its cell: 0 for the occupant, 1 for the first
+    /// window stacked over it, and so on.
+    pub fn stack_position(&self, window: WindowId) -> Option<usize> {
+        let cell = &self.cells[self.cell_of(window)?];
+        match cell.window == Some(window) {
+            true => Some(0),
+            false => cell
+                .stacked
+                .iter()
+                .position(|&(w, _)| w == window)
+                .map(|p| p + 1),
+        }
+    }
+
+    /// Windows stacked in cell `idx`, its occupant included.
+    pub fn stack_depth(&self, idx: usize) -> usize {
+        self.cells
+            .get(idx)
+            .map_or(0, |c| c.window.map_or(0, |_| 1 + c.stacked.len()))
+    }
+
+    /// Cascades `window` over the occupant of cell `idx` (see [`Placement::Stack`]) and
+    /// starts its timeout. Returns its depth, or None if the cell is empty or outside
+    /// the grid (use [`GridEngine::assign`] then).
+    pub fn stack(&mut self, idx: usize, window: WindowId, now: Instant) -> Option<usize> {
+        self.cells.get(idx)?.window?;
+        self.release_window(window);
+        self.cells[idx].stacked.push((window, now));
+        self.window_to_cell.insert(window, idx);
+        if let Some(timeout) = self.timeout
+            && Some(window) != self.protected
+        {
+            self.timers.schedule(window, now + timeout);
+        }
+        Some(self.stack_depth(idx) - 1)
+    }
+
+    /// Adds a column or a row to the first grid, whichever leaves bigger cells, and lays
+    /// its windows out again (see [`GridEngine::relayout`]). Returns false, changing
+    /// nothing, if the cells would get narrower or shorter than [`MIN_GROWN_CELL`].
+    pub fn grow(&mut self) -> bool {
+        let mut layout = self.pool.monitors[0].layout.clone();
+        let area = layout.grid_area();
+        let (width, height) = (
+            area.width() / layout.cols.max(1) as i32,
+            area.height() / layout.rows.max(1) as i32,
+        );
+        let wider = area.width() / (layout.cols as i32 + 1);
+        let taller = area.height() / (layout.rows as i32 + 1);
+        // Compare the smaller side of the cells either way
+        if wider.min(height) >= taller.min(width) {
+            if wider < MIN_GROWN_CELL {
+                return false;
+            }
+            layout.cols += 1;
+            if !layout.col_weights.is_empty() {
+                layout.col_weights.push(1);
+            }
+        } else {
+            if taller < MIN_GROWN_CELL {
+                return false;
+            }
+            layout.rows += 1;
+            if !layout.row_weights.is_empty() {
+                layout.row_weights.push(1);
+            }
+        }
+        self.relayout(0, layout);
+        true
+    }
+
+    /// Replaces the layout of the pool's `section`th grid and lays its windows out again:
+    /// reserved cells keep their row and column, and every other occupied cell (stacked
+    /// windows included) moves, in cell order, to the next cell that is not reserved.
+    /// Windows that no longer fit leave the grid. Cells of other grids keep their place.
+    pub fn relayout(&mut self, section: usize, layout: GridLayout) {
+        let old_pool = self.pool.clone();
+        self.pool.monitors[section].layout = layout;
+        let old_starts: Vec<usize> = old_pool.sections().map(|(start, _)| start).collect();
+        let new_starts: Vec<usize> = self.pool.sections().map(|(start, _)| start).collect();
+        let section_of = |idx: usize| old_starts.iter().rposition(|&start| start <= idx);
+        let (old_layout, new_layout) = (
+            &old_pool.monitors[section].layout,
+            &self.pool.monitors[section].layout,
+        );
+
+        let old_cells = std::mem::take(&mut self.cells);
+        let mut cells = vec![Cell::default(); self.pool.cell_count()];
+        let mut reserved = Vec::new();
+        let mut packed = Vec::new();
+        for (idx, cell) in old_cells.into_iter().enumerate() {
+            let Some(s) = section_of(idx) else {
+                continue;
+            };
+            let local = idx - old_starts[s];
+            let new_idx = match s == section {
+                true => {
+                    let (row, col) = old_layout.row_col(local);
+                    new_layout.index(row, col).map(|l| new_starts[s] + l)
+                }
+                false => Some(new_starts[s] + local),
+            };
+            if self.reserved.contains(&idx)
+                && let Some(new_idx) = new_idx
+            {
+                reserved.push(new_idx);
+                cells[new_idx] = cell;
+                continue;
+            }
+            match (s == section, new_idx) {
+                (false, Some(new_idx)) => cells[new_idx] = cell,
+                _ if cell.window.is_some() => packed.push(cell),
+                _ => {}
+            }
+        }
+        let slots = (new_starts[section]..new_starts[section] + new_layout.cell_count())
+            .filter(|idx| !reserved.contains(idx))
+            .collect::<Vec<_>>();
+        for (slot, cell) in slots.into_iter().zip(packed) {
+            cells[slot] = cell;
+        }
+        self.reserved = reserved;
+
+        let placed: HashMap<WindowId, usize> = cells
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, cell)| {
+                let stacked = cell.stacked.iter().map(|&(w, _)| w);
+                cell.window
+                    .into_iter()
+                    .chain(stacked)
+                    .map(move |w| (w, idx))
+            })
+            .collect();
+        for window in self.window_to_cell.keys() {
+            if !placed.contains_key(window) {
+                self.timers.cancel(*window);
+            }
+        }
+        self.window_to_cell = placed;
+        self.cells = cells;
+    }
+
+    /// True if any grid of the pool sizes itself (`-g auto`).
+    pub fn is_auto(&self) -> bool {
+        self.pool.monitors.iter().any(|m| m.auto)
+    }
+
+    /// Cells the `section`th grid needs: one per window outside the reserved cells, plus
+    /// the reserved cells.
+    fn cells_needed(&self, section: usize) -> usize {
+        let Some((start, grid)) = self.pool.sections().nth(section) else {
+            return 0;
+        };
+        (start..start + grid.layout.cell_count())
+            .map(|idx| match self.is_reserved(idx) {
+                true => 1,
+                false => self.stack_depth(idx),
+            })
+            .sum()
+    }
+
+    /// An auto grid's layout resized for `count` windows (see [`auto_dimensions`]).
+    fn auto_layout(&self, section: usize, count: usize) -> GridLayout {
+        let layout = &self.pool.monitors[section].layout;
+        let (rows, cols) = auto_dimensions(count, layout.grid_area());
+        GridLayout {
+            rows,
+            cols,
+            row_weights: Vec::new(),
+            col_weights: Vec::new(),
+            ..layout.clone()
+        }
+    }
+
+    /// Grows the first auto grid so one more window fits, and returns a free cell. While
+    /// windows keep arriving (more are in the grid than when it last settled) it makes
+    /// room for as many again, so a burst of spawns re-lays out the grid a few times
+    /// rather than once per window. None if the cells would get smaller than
+    /// [`MIN_GROWN_CELL`].
+    fn grow_auto(&mut self) -> Option<usize> {
+        let section = self.pool.monitors.iter().position(|m| m.auto)?;
+        let needed = self.cells_needed(section) + 1;
+        let burst = (self.window_to_cell.len() + 1).saturating_sub(self.settled_count);
+        let layout = [needed + burst, needed]
+            .into_iter()
+            .map(|count| self.auto_layout(section, count))
+            .find(|layout| {
+                let area = layout.grid_area();
+                area.width() / layout.cols as i32 >= MIN_GROWN_CELL
+                    && area.height() / layout.rows as i32 >= MIN_GROWN_CELL
+            })?;
+        self.relayout(section, layout);
+        self.candidate_cells()
+            .into_iter()
+            .find(|&idx| self.is_free(idx))
+    }
+
+    /// Shrinks auto grids (`-g auto`) to fit their windows once the number of windows in
+    /// the grid has not changed for [`AUTO_GRID_SETTLE`]. Call it regularly; it notes
+    /// changes in the count as it goes. Returns true if any grid was laid out again, so
+    /// every window has to be moved to its cell.
+    pub fn settle(&mut self, now: Instant) -> bool {
+        if !self.is_auto() {
+            return false;
+        }
+        let count = self.window_to_cell.len();
+        if count != self.auto_count {
+            self.auto_count = count;
+            self.auto_count_since = Some(now);
+            return false;
+        }
+        match self.auto_count_since {
+            Some(since) if now.duration_since(since) >= AUTO_GRID_SETTLE => {}
+            _ => return false,
+        }
+        self.auto_count_since = None;
+        self.settled_count = count;
+        let mut changed = false;
+        for section in 0..self.pool.monitors.len() {
+            if !self.pool.monitors[section].auto {
+                continue;
+            }
+            let layout = self.auto_layout(section, self.cells_needed(section));
+            if layout.cell_count() < self.pool.monitors[section].layout.cell_count() {
+                self.relayout(section, layout);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// When [`GridEngine::settle`] would next act, if the window count changed since
+    /// the auto grids last settled.
+    pub fn settle_deadline(&self) -> Option<Instant> {
+        self.auto_count_since.map(|since| since + AUTO_GRID_SETTLE)
+    }
+
+    /// Moves every occupant of the candidate cells, in cell order, into the lowest
+    /// candidate cells so no free cell sits before an occupied one. The protected window
+    /// stays put, and moved windows keep their fill time. Returns each move as
+    /// `(window, from, to)`.
+    pub fn compact(&mut self) -> Vec<(WindowId, usize, usize)> {
+        let slots: Vec<usize> = self
+            .candidate_cells()
+            .into_iter()
+            .filter(|&idx| self.occupant(idx).is_none_or(|w| Some(w) != self.protected))
+            .collect();
+        let occupied: Vec<usize> = slots
+            .iter()
+            .copied()
+            .filter(|&idx| !self.is_free(idx))
+            .collect();
+        let mut moves = Vec::new();
+        // Every target is at or before its source, and earlier sources leave first
+        for (&from, &to) in occupied.iter().zip(&slots) {
+            if from == to {
+                continue;
+            }
+            let cell = std::mem::take(&mut self.cells[from]);
+            if let Some(window) = cell.window {
+                self.window_to_cell.insert(window, to);
+                moves.push((window, from, to));
+            }
+            for &(stacked, _) in &cell.stacked {
+                self.window_to_cell.insert(stacked, to);
+                moves.push((stacked, from, to));
+            }
+            self.cells[to] = cell;
+        }
+        moves
+    }
+
+    /// Releases every window whose deadline has passed by `now`, earliest first, and
+    /// returns them with the cells they left.
+    pub fn expire(&mut self, now: Instant) -> Vec<(usize, WindowId)> {
+        self.timers
+            .expire(now)
+            .into_iter()
+            .filter_map(|window| Some((self.release_window(window)?, window)))
+            .collect()
+    }
+
+    /// True if the occupant of `idx` has been there for at least `timeout`.
+    pub fn is_expired(&self, idx: usize, timeout: Duration, now: Instant) -> bool {
+        self.cells
+            .get(idx)
+            .and_then(|c| c.filled_at)
+            .is_some_and(|filled_at| now.duration_since(filled_at) >= timeout)
+    }
+
+    /// Occupied cells that may be evicted, oldest first. The reserved cell and the
+    /// protected window are never included.
+    pub fn eviction_order(&self) -> Vec<usize> {
+        let mut order: Vec<(usize, Instant)> = self
+            .cells
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| !self.is_reserved(*idx))
+            .filter_map(|(idx, cell)| match (cell.window, cell.filled_at) {
+                (Some(window), Some(filled_at)) if Some(window) != self.protected => {
+                    Some((idx, filled_at))
+                }
+                _ => None,
+            })
+            .collect();
+        order.sort_by_key(|&(idx, filled_at)| (filled_at, idx));
+        order.into_iter().map(|(idx, _)| idx).collect()
+    }
+
+    /// Releases the oldest evictable window and returns its cell.
+    pub fn evict_oldest(&mut self) -> Option<(usize, WindowId)> {
+        let idx = *self.eviction_order().first()?;
+        let window = self.release_cell(idx)?;
+        Some((idx, window))
+    }
+
+    /// Like [`GridEngine::evict_oldest`], but only considers `cells`.
+    pub fn evict_oldest_in(&mut self, cells: &[usize]) -> Option<(usize, WindowId)> {
+        let idx = self
+            .eviction_order()
+            .into_iter()
+            .find(|idx| cells.contains(idx))?;
+        let window = self.release_cell(idx)?;
+        Some((idx, window))
+    }
+
+    /// Chooses the cell for the next child window according to the placement mode.
+    pub fn next_placement(&mut self) -> Placement {
+        let total_cells = self.cells.len();
+        if total_cells == 0 {
+            return Placement::Full;
+        }
+        match self.placement_mode {
+            GridPlacementMode::Sequential => {
+                // Skip every reserved cell, unless the whole grid is reserved.
+                let mut c = self.next_cell % total_cells;
+                for _ in 0..total_cells {
+                    if !self.is_reserved(c) {
+                        break;
+                    }
+                    self.next_cell += 1;
+                    c = self.next_cell % total_cells;
+                }
+                self.next_cell += 1;
+                Placement::Cell(c)
+            }
+            GridPlacementMode::FirstFree => {
+                if let Some(idx) = self
+                    .candidate_cells()
+                    .into_iter()
+                    .find(|&idx| self.is_free(idx))
+                {
+                    return Placement::Cell(idx);
+                }
+                self.has_been_full = true;
+                if let Some(cell) = self.grow_auto() {
+                    return Placement::Grown { cell };
+                }
+                let on_full = Arc::clone(&self.on_full);
+                on_full.make_room(self)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn widths(spans: &[(i32, i32)]) -> Vec<i32> {
+        spans.iter().map(|&(_, len)| len).collect()
+    }
+
+    /// Checks that `spans` cover `start..end` with `gutter` pixels between neighbours.
+    fn assert_tiles(spans: &[(i32, i32)], start: i32, end: i32, gutter: i32) {
+        let mut pos = start;
+        for &(span_start, len) in spans {
+            assert_eq!(span_start, pos, "{:?}", spans);
+            pos += len + gutter;
+        }
+        assert_eq!(pos - gutter, end, "{:?}", spans);
+    }
+
+    #[test]
+    fn split_span_gives_remainder_pixels_to_leading_spans() {
+        assert_eq!(split_span(0, 10, 3, &[], 0), [(0, 4), (4, 3), (7, 3)]);
+        assert_eq!(
+            split_span(100, 9, 3, &[], 0),
+            [(100, 3), (103, 3), (106, 3)]
+        );
+
+        let layout = GridLayout::new(7, 3, Rect::new(0, 0, 2560, 1080));
+        let columns = layout.column_spans();
+        assert_eq!(widths(&columns), [854, 853, 853]);
+        assert_tiles(&columns, 0, 2560, 0);
+        let rows = layout.row_spans();
+        assert_eq!(widths(&rows), [155, 155, 154, 154, 154, 154, 154]);
+        assert_tiles(&rows, 0, 1080, 0);
+        assert_eq!(layout.cell_rect(20), Rect::new(1707, 926, 2560, 1080));
+    }
+
+    #[test]
+    fn weights_size_rows_and_columns_proportionally() {
+        let layout = GridLayout::new(2, 3, Rect::new(0, 0, 1200, 800))
+            .with_col_weights(vec![2, 1, 1])
+            .with_row_weights(vec![3, 1]);
+        assert_eq!(layout.column_spans(), [(0, 600), (600, 300), (900, 300)]);
+        assert_eq!(layout.row_spans(), [(0, 600), (600, 200)]);
+        assert_eq!(layout.cell_rect(4), Rect::new(600, 600, 900, 800));
+
+        // Weights that don't divide evenly still tile the area
+        let spans = split_span(0, 1000, 3, &[1, 1, 1], 0);
+        assert_eq!(widths(&spans), [334, 333, 333]);
+        let spans = split_span(0, 1001, 2, &[2, 1], 0);
+        assert_eq!(widths(&spans), [668, 333]);
+        assert_tiles(&spans, 0, 1001, 0);
+
+        // A weight list of the wrong length, or all zeroes, means uniform cells
+        assert_eq!(widths(&split_span(0, 90, 3, &[1, 2], 0)), [30, 30, 30]);
+        assert_eq!(widths(&split_span(0, 90, 3, &[0, 0, 0], 0)), [30, 30, 30]);
+    }
+
+    #[test]
+    fn regions_cover_the_cells_and_gutters_between_them() {
+        let layout = GridLayout::new(2, 3, Rect::new(0, 0, 1210, 810))
+            .with_col_weights(vec![2, 1, 1])
+            .with_gutter(10);
+        let parent = CellRegion {
+            row: 0,
+            col: 0,
+            rows: 2,
+            cols: 2,
+        };
+        assert_eq!(layout.region_cells(&parent), [0, 1, 3, 4]);
+        assert_eq!(layout.region_rect(&parent), Some(Rect::new(0, 0, 903, 810)));
+        // Clipped to the grid, and None entirely outside it
+        let wide = CellRegion { cols: 5, ..parent };
+        assert_eq!(layout.region_cells(&wide), [0, 1, 2, 3, 4, 5]);
+        assert_eq!(layout.region_rect(&CellRegion::cell(2, 0)), None);
+    }
+
+    #[test]
+    fn gutters_and_margins_shrink_the_cells() {
+        let layout = GridLayout::new(2, 2, Rect::new(0, 0, 1000, 600))
+            .with_margin(10)
+            .with_gutter(20);
+        assert_eq!(layout.grid_area(), Rect::new(10, 10, 990, 590));
+        assert_eq!(layout.column_spans(), [(10, 480), (510, 480)]);
+        assert_eq!(layout.row_spans(), [(10, 280), (310, 280)]);
+        assert_eq!(layout.cell_rect(3), Rect::new(510, 310, 990, 590));
+
+        // Uneven leftovers after the gutters still go to the leading cells
+        let spans = split_span(0, 100, 3, &[], 5);
+        assert_eq!(spans, [(0, 30), (35, 30), (70, 30)]);
+        let spans = split_span(0, 102, 3, &[], 5);
+        assert_eq!(widths(&spans), [31, 31, 30]);
+        assert_tiles(&spans, 0, 102, 5);
+
+        // Negative sizes are ignored
+        let layout = GridLayout::new(1, 2, Rect::new(0, 0, 100, 100))
+            .with_margin(-5)
+            .with_gutter(-5);
+        assert_eq!(
+            layout.cell_rects(),
+            [Rect::new(0, 0, 50, 100), Rect::new(50, 0, 100, 100)]
+        );
+    }
+
+    #[test]
+    fn windows_keep_their_size_unless_fit() {
+        let layout = GridLayout::new(2, 2, Rect::new(0, 0, 1000, 1000));
+        assert_eq!(
+            layout.place_in_cell(3, 200, 100, false),
+            Some(Rect::new(650, 700, 850, 800))
+        );
+        assert_eq!(
+            layout.place_in_cell(3, 200, 100, true),
+            Some(Rect::new(500, 500, 1000, 1000))
+        );
+        // Clamped to the grid area, and refused when larger than it
+        assert_eq!(
+            layout.place_in_cell(3, 800, 100, false),
+            Some(Rect::new(200, 700, 1000, 800))
+        );
+        assert_eq!(layout.place_in_cell(0, 1200, 100, false), None);
+    }
+
+    fn two_monitor_pool() -> GridPool {
+        GridPool::new(vec![
+            MonitorGrid {
+                monitor: 0,
+                layout: GridLayout::new(2, 2, Rect::new(0, 0, 1000, 1000)),
+                auto: false,
+            },
+            MonitorGrid {
+                monitor: 1,
+                layout: GridLayout::new(1, 2, Rect::new(1000, 0, 3000, 1000)),
+                auto: false,
+            },
+        ])
+    }
+
+    #[test]
+    fn pools_number_cells_across_monitors() {
+        let pool = two_monitor_pool();
+        assert_eq!(pool.cell_count(), 6);
+        let starts: Vec<usize> = pool.sections().map(|(start, _)| start).collect();
+        assert_eq!(starts, [0, 4]);
+        assert_eq!(pool.monitor_of(3), Some(0));
+        assert_eq!(pool.monitor_of(4), Some(1));
+        assert_eq!(pool.monitor_of(6), None);
+        assert_eq!(
+            pool.locate(5).map(|(grid, local)| (grid.monitor, local)),
+            Some((1, 1))
+        );
+        assert_eq!(pool.cell_rect(4), Rect::new(1000, 0, 2000, 1000));
+        assert_eq!(pool.cell_rects().len(), 6);
+        assert_eq!(pool.cell_rects()[5], pool.cell_rect(5));
+
+        assert_eq!(pool.index(Some(1), 0, 1), Some(5));
+        assert_eq!(pool.index(Some(1), 1, 0), None);
+        // A monitor that is not in the pool means the first grid
+        assert_eq!(pool.index(Some(7), 1, 1), Some(3));
+        let row = CellRegion {
+            row: 0,
+            col: 0,
+            rows: 1,
+            cols: 2,
+        };
+        assert_eq!(pool.region_cells(Some(1), &row), [4, 5]);
+        assert_eq!(pool.region_cells(None, &row), [0, 1]);
+        assert_eq!(
+            pool.region_rect(Some(1), &row),
+            Some(Rect::new(1000, 0, 3000, 1000))
+        );
+        // Windows keep to their cell's monitor
+        assert_eq!(
+            pool.place_in_cell(4, 1200, 100, false),
+            Some(Rect::new(1000, 450, 2200, 550))
+        );
+    }
+
+    #[test]
+    fn placement_spills_onto_the_next_monitor() {
+        let mut engine = GridEngine::new(two_monitor_pool(), GridPlacementMode::FirstFree);
+        engine.reserve([0]);
+        let now = Instant::now();
+        let mut placed = Vec::new();
+        for window in 1..=5 {
+            let Placement::Cell(idx) = engine.next_placement() else {
+                panic!("grid filled early");
+            };
+            engine.assign(idx, window, now);
+            placed.push(engine.pool.monitor_of(idx).unwrap());
+        }
+        assert_eq!(placed, [0, 0, 0, 1, 1]);
+        assert_eq!(engine.free_cells(), [0]);
+    }
+
+    fn stacked_engine(start: Instant) -> GridEngine {
+        let pool = GridPool::single(0, GridLayout::new(1, 2, Rect::new(0, 0, 1000, 500)));
+        let mut engine = GridEngine::new(pool, GridPlacementMode::FirstFree);
+        engine.set_timeout(Some(Duration::from_secs(10)));
+        engine.assign(0, 1, start);
+        engine.stack(0, 2, start + Duration::from_secs(1));
+        engine.stack(0, 3, start + Duration::from_secs(2));
+        engine
+    }
+
+    #[test]
+    fn releasing_a_cell_promotes_its_stacked_windows() {
+        let start = Instant::now();
+        let mut engine = stacked_engine(start);
+        assert_eq!(engine.release_cell(0), Some(1));
+        assert_eq!(engine.cell_of(1), None);
+        assert_eq!(engine.timers().state(1), None);
+        assert_eq!(engine.occupant(0), Some(2));
+        assert_eq!(
+            engine.cell(0).unwrap().filled_at,
+            Some(start + Duration::from_secs(1))
+        );
+        assert_eq!(engine.stack_position(3), Some(1));
+        assert_eq!(engine.stack_depth(0), 2);
+        // Their timeouts keep running
+        assert_eq!(
+            engine.timers().deadline(3),
+            Some(start + Duration::from_secs(12))
+        );
+        // Window 2's deadline, give or take the wheel's tick
+        assert_eq!(
+            engine.expire(start + Duration::from_millis(11_100)),
+            [(0, 2)]
+        );
+        assert_eq!(engine.occupancy(), [Some(3), None]);
+
+        assert_eq!(engine.release_cell(0), Some(3));
+        assert!(engine.is_free(0));
+        assert_eq!(engine.release_cell(0), None);
+        assert!(engine.timers().is_empty());
+    }
+
+    #[test]
+    fn assigning_over_a_stack_keeps_the_stacked_windows() {
+        let start = Instant::now();
+        let mut engine = stacked_engine(start);
+        assert_eq!(engine.assign(0, 4, start + Duration::from_secs(3)), Some(1));
+        assert_eq!(engine.cell_of(1), None);
+        assert_eq!(engine.stack_position(4), Some(0));
+        assert_eq!(engine.stack_position(3), Some(2));
+        assert_eq!(engine.timers().len(), 3);
+
+        // Moving a stacked window to another cell takes it out of the stack
+        assert_eq!(engine.assign(1, 2, start), None);
+        assert_eq!(engine.stack_position(3), Some(1));
+        assert_eq!(engine.occupancy(), [Some(4), Some(2)]);
+    }
+
+    #[test]
+    fn auto_dimensions_prefer_square_cells() {
+        let area = Rect::new(0, 0, 1920, 1080);
+        let dimensions: Vec<(u32, u32)> = (1..=9).map(|n| auto_dimensions(n, area)).collect();
+        assert_eq!(
+            dimensions,
+            [
+                (1, 1),
+                (1, 2),
+                (1, 3),
+                (2, 2),
+                (2, 3),
+                (2, 3),
+                (2, 4),
+                (2, 4),
+                (2, 5)
+            ]
+        );
+        assert_eq!(auto_dimensions(0, area), (1, 1));
+        // A portrait monitor stacks rows instead
+        assert_eq!(auto_dimensions(3, Rect::new(0, 0, 1080, 1920)), (3, 1));
+    }
+
+    #[test]
+    fn auto_grids_grow_for_bursts_and_shrink_once_settled() {
+        let pool = GridPool::new(vec![MonitorGrid {
+            monitor: 0,
+            layout: GridLayout::new(1, 1, Rect::new(0, 0, 1920, 1080)),
+            auto: true,
+        }]);
+        let mut engine = GridEngine::new(pool, GridPlacementMode::FirstFree);
+        assert!(engine.is_auto());
+        let start = Instant::now();
+        for window in 1..=3 {
+            let idx = match engine.next_placement() {
+                Placement::Cell(idx) | Placement::Grown { cell: idx } => idx,
+                other => panic!("unexpected {:?}", other),
+            };
+            engine.assign(idx, window, start);
+        }
+        // The burst made room for as many windows again
+        let layout = &engine.pool.primary().layout;
+        assert!(layout.cell_count() > 3, "{:?}", layout);
+        assert_eq!(engine.occupancy().iter().flatten().count(), 3);
+
+        // Nothing shrinks until the count has held still for AUTO_GRID_SETTLE
+        assert!(!engine.settle(start));
+        assert_eq!(engine.settle_deadline(), Some(start + AUTO_GRID_SETTLE));
+        assert!(!engine.settle(start + AUTO_GRID_SETTLE / 2));
+        assert!(engine.settle(start + AUTO_GRID_SETTLE));
+        let layout = &engine.pool.primary().layout;
+        assert_eq!((layout.rows, layout.cols), (1, 3));
+        assert_eq!(engine.occupancy(), [Some(1), Some(2), Some(3)]);
+
+        // A window leaving shrinks it again
+        engine.release_window(2);
+        let later = start + AUTO_GRID_SETTLE * 2;
+        assert!(!engine.settle(later));
+        assert!(engine.settle(later + AUTO_GRID_SETTLE));
+        assert_eq!(engine.occupancy(), [Some(1), Some(3)]);
+    }
+}
@@ -1,4 +1,5 @@
 // src/main.rs
+use crate::process_tree::ProcessTree;
 #[cfg(not(feature = "uses_etw"))]
 #[allow(unused_imports)]
 #[cfg(feature = "uses_etw")]
@@ -12,7 +13,6 @@ use std::sync::{Arc, Mutex};
 use std::thread::sleep;
 use std::time::Duration;
 use widestring::U16CString;
-use winapi::shared::minwindef::FALSE;
 use winapi::shared::windef::HWND;
 use winapi::shared::windef::{HMONITOR, POINT, RECT};
 // Window hook for automatic grid eviction on window destroy
@@ -20,9 +20,6 @@ use winapi::shared::windef::{HMONITOR, POINT, RECT};
 // Make the publisher globally accessible
 use winapi::um::handleapi::CloseHandle;
 // use winapi::um::psapi::GetProcessImageFileNameW;
-use winapi::um::tlhelp32::{
-    CreateToolhelp32Snapshot, PROCESSENTRY32, Process32First, Process32Next, TH32CS_SNAPPROCESS,
-};
 // use winapi::um::winnt::{PROCESS_QUERY_INFORMATION, PROCESS_VM_READ};
 use winapi::um::winuser::SWP_NOSIZE;
 use winapi::um::winuser::SWP_NOZORDER;
@@ -176,6 +173,12 @@ pub fn show_taskbar_on_monitor(monitor_index: i32) {
     }
 }
 
+// Number of attached display monitors (at least 1)
+pub fn get_monitor_count() -> i32 {
+    let count = unsafe { winapi::um::winuser::GetSystemMetrics(winapi::um::winuser::SM_CMONITORS) };
+    count.max(1)
+}
+
 // Helper to get monitor RECT by index (0 = primary)
 pub fn get_monitor_rect(monitor_index: i32, use_full_area: bool) -> RECT {
     let rect = RECT {
@@ -268,39 +271,79 @@ pub fn get_monitor_rect(monitor_index: i32, use_full_area: bool) -> RECT {
     }
 }
 
-pub fn get_parent_pid(pid: u32) -> Option<u32> {
-    unsafe {
-        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
-        if snapshot.is_null() {
-            eprintln!("Failed to create process snapshot");
-            return None;
-        }
+/// Window class name, or an empty string if it cannot be read.
+pub fn get_window_class(hwnd: HWND) -> String {
+    let mut class_name = [0u16; 256];
+    let len = unsafe {
+        winapi::um::winuser::GetClassNameW(hwnd, class_name.as_mut_ptr(), class_name.len() as i32)
+    };
+    String::from_utf16_lossy(&class_name[..len.max(0) as usize])
+}
 
-        let mut entry: PROCESSENTRY32 = std::mem::zeroed();
-        entry.dwSize = std::mem::size_of::<PROCESSENTRY32>() as u32;
+/// Screen rectangle of a window, or None if it is gone.
+pub fn get_window_rect(hwnd: HWND) -> Option<crate::grid::Rect> {
+    let mut rect: RECT = unsafe { std::mem::zeroed() };
+    if unsafe { winapi::um::winuser::GetWindowRect(hwnd, &mut rect) } == 0 {
+        return None;
+    }
+    Some(crate::grid::Rect::new(
+        rect.left,
+        rect.top,
+        rect.right,
+        rect.bottom,
+    ))
+}
 
-        if Process32First(snapshot, &mut entry) != FALSE {
-            loop {
-                if entry.th32ProcessID == pid {
-                    CloseHandle(snapshot);
-                    return Some(entry.th32ParentProcessID);
-                }
+/// Window title, or an empty string if it has none.
+pub fn get_window_title(hwnd: HWND) -> String {
+    let mut title = [0u16; 512];
+    let len = unsafe {
+        winapi::um::winuser::GetWindowTextW(hwnd, title.as_mut_ptr(), title.len() as i32)
+    };
+    String::from_utf16_lossy(&title[..len.max(0) as usize])
+}
 
-                if Process32Next(snapshot, &mut entry) == FALSE {
-                    break;
-                }
-            }
+/// File name of the process's executable (e.g. `bevy_demo.exe`).
+pub fn get_process_image_name(pid: u32) -> Option<String> {
+    use winapi::um::processthreadsapi::OpenProcess;
+    use winapi::um::psapi::GetProcessImageFileNameW;
+    use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle.is_null() {
+            return None;
         }
-
-        CloseHandle(snapshot);
+        let mut buf = [0u16; 260];
+        let len = GetProcessImageFileNameW(handle, buf.as_mut_ptr(), buf.len() as u32);
+        CloseHandle(handle);
+        if len == 0 {
+            return None;
+        }
+        let path = String::from_utf16_lossy(&buf[..len as usize]);
+        path.rsplit('\\').next().map(str::to_string)
     }
+}
 
-    None
+pub fn get_parent_pid(pid: u32) -> Option<u32> {
+    ProcessTree::snapshot().parent_of(pid)
 }
+
+/// Whether the installed [`crate::filter::WindowFilter`] lets this window through.
+/// The title is only read when the filter looks at titles.
+pub fn is_window_allowed(hwnd: HWND) -> bool {
+    let filter = crate::filter::current();
+    let class = get_window_class(hwnd);
+    if !filter.allows_class(&class) {
+        return false;
+    }
+    !filter.filters_titles() || filter.allows_title(&get_window_title(hwnd))
+}
+
 pub fn find_hwnd_by_pid(pid: u32) -> Option<HWND> {
     struct EnumData {
         target_pid: u32,
         hwnd: HWND,
+        processes: ProcessTree,
     }
 
     extern "system" fn enum_windows_proc(hwnd: HWND, lparam: isize) -> i32 {
@@ -308,18 +351,9 @@ pub fn find_hwnd_by_pid(pid: u32) -> Option<HWND> {
         let mut process_id = 0;
         unsafe {
             GetWindowThreadProcessId(hwnd, &mut process_id);
-            // Check if the window class name is not "IME"
-            let mut class_name: [u16; 256] = [0; 256];
-            let len = winapi::um::winuser::GetClassNameW(
-                hwnd,
-                class_name.as_mut_ptr(),
-                class_name.len() as i32,
-            );
-            if len > 0 {
-                let class = String::from_utf16_lossy(&class_name[..len as usize]);
-                if class == "IME" {
-                    return 1; // Skip IME windows
-                }
+            // Skip IME and other helper windows
+            if !is_window_allowed(hwnd) {
+                return 1;
             }
 
             // Check if the process ID matches the target PID or its parent PID
@@ -329,7 +363,7 @@ pub fn find_hwnd_by_pid(pid: u32) -> Option<HWND> {
             }
 
             // Optionally, retrieve the parent process ID and check it
-            let parent_pid = get_parent_pid(process_id);
+            let parent_pid = data.processes.parent_of(process_id);
             if let Some(ppid) = parent_pid {
                 if ppid == data.target_pid {
                     data.hwnd = hwnd;
@@ -348,6 +382,7 @@ pub fn find_hwnd_by_pid(pid: u32) -> Option<HWND> {
     let mut data = EnumData {
         target_pid: pid,
         hwnd: ptr::null_mut(),
+        processes: ProcessTree::snapshot(),
     };
 
     unsafe {
@@ -0,0 +1,764 @@
+// src/launcher.rs
+//! Launching a target and following its windows from another program.
+//!
+//! [`Launcher`] builds the same [`CommandLineOptions`] the `startt` command line does:
+//!
+//! ```text
+//! let app = Launcher::new("cargo-e")
+//!     .args(["--run-all"])
+//!     .discovery(Strategy::EnvTag)
+//!     .grid("2x3".parse()?)
+//!     .follow()
+//!     .spawn()?;
+//! let first = app.wait_for_window(Duration::from_secs(10));
+//! ```
+//!
+//! [`Launcher::spawn`] connects to the native
+//! [`WindowSystem`](crate::window_system::WindowSystem), starts the target
+//! with `STARTT=<our pid>` in its environment (on Windows, documents and URLs are opened
+//! through ShellExecuteEx instead, untagged; then it waits for the target to be ready
+//! for input) and hands a [`Follower`] to a thread that
+//! polls every [`POLL_INTERVAL`] until the follow loop ends (see [`Follower::end`]) or
+//! the [`LaunchedApp`] is closed or dropped. On Windows, with `-f`, it also places
+//! windows as `SetWinEventHook` reports them. The app reads what the follower has found
+//! at its last poll, and receives the follower's [`Event`]s on a channel.
+use crate::cli::{CommandLineOptions, GridSpec};
+use crate::events::Event;
+use crate::follow::{FollowEnd, Follower, POLL_INTERVAL, SystemClock};
+use crate::grid::WindowId;
+use crate::process_tree::ProcessInfo;
+use crate::shutdown::{self, ShutdownReport};
+use crate::window_system::WindowInfo;
+use crossbeam_channel::Receiver;
+use std::ffi::{OsStr, OsString};
+use std::io;
+#[cfg(feature = "async")]
+use std::pin::Pin;
+use std::process::Child;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::Waker;
+#[cfg(feature = "async")]
+use std::task::{Context, Poll};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How the launch's processes are found, besides descending from the launched one.
+pub use crate::cli::FindMode as Strategy;
+
+/// Builder for a launch; see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct Launcher {
+    options: CommandLineOptions,
+}
+
+impl Launcher {
+    /// Launches `target` with the defaults of the command line: no grid, and not
+    /// following past the first windows. On Windows `target` may be a document or a URL
+    /// as well as a program, which ShellExecuteEx opens; on Linux it is executed, so it
+    /// must be a program.
+    pub fn new(target: impl AsRef<OsStr>) -> Self {
+        Launcher::from_options(CommandLineOptions {
+            command: vec![target.as_ref().to_os_string()],
+            ..CommandLineOptions::default()
+        })
+    }
+
+    /// Launches `options.command` with parsed command line options, as `startt` does.
+    pub fn from_options(options: CommandLineOptions) -> Self {
+        Launcher { options }
+    }
+
+    pub fn arg(mut self, arg: impl AsRef<OsStr>) -> Self {
+        self.options.command.push(arg.as_ref().to_os_string());
+        self
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.options
+            .command
+            .extend(args.into_iter().map(|arg| arg.as_ref().to_os_string()));
+        self
+    }
+
+    pub fn discovery(mut self, strategy: Strategy) -> Self {
+        self.options.find_mode = strategy;
+        self
+    }
+
+    /// Adds a grid (`-g`); several grids are filled as one pool, in order.
+    pub fn grid(mut self, grid: GridSpec) -> Self {
+        self.options.grids.push(grid);
+        self
+    }
+
+    /// Keeps following new windows while the launch's processes run (`-f`).
+    pub fn follow(mut self) -> Self {
+        self.options.follow_children = true;
+        self
+    }
+
+    /// Keeps following even after the launch's processes have exited (`-F`).
+    pub fn follow_forever(mut self) -> Self {
+        self.options.follow_children = true;
+        self.options.follow_forever = true;
+        self
+    }
+
+    /// Closes each window this long after it is placed in the grid (`-t`).
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout_secs = Some(timeout.as_secs());
+        self
+    }
+
+    /// How long [`LaunchedApp::close`] waits before terminating (`--grace`).
+    pub fn grace(mut self, grace: Duration) -> Self {
+        self.options.grace_secs = grace.as_secs();
+        self
+    }
+
+    pub fn options(&self) -> &CommandLineOptions {
+        &self.options
+    }
+
+    /// Starts the target and follows it on a background thread.
+    pub fn spawn(self) -> io::Result<LaunchedApp> {
+        let options = self.options;
+        let Some((program, args)) = options.command.split_first() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no target to launch",
+            ));
+        };
+        let (program, args) = (program.clone(), args.to_vec());
+        let windows = crate::window_system::native().map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("cannot connect to the window system: {}", e),
+            )
+        })?;
+        let grace = Duration::from_secs(options.grace_secs);
+        // Before looking for windows: until the target is ready for input (for at most
+        // `--wait` with `--resolve`), then `-sl`
+        let ready_timeout = if options.resolve {
+            Duration::from_secs(options.wait_secs)
+        } else {
+            Duration::MAX
+        };
+        let settle = Duration::from_millis(options.sleep_duration_ms);
+        let shared = Arc::new(Shared::default());
+        let stop = Arc::new(AtomicBool::new(false));
+        let (events_tx, events) = crossbeam_channel::unbounded();
+        let (started_tx, started_rx) = crossbeam_channel::bounded(1);
+        let thread = {
+            let (shared, stop) = (shared.clone(), stop.clone());
+            std::thread::spawn(move || {
+                let processes = crate::ps::native();
+                #[cfg(windows)]
+                let follow_children = options.follow_children;
+                let mut follower = Follower::new(&windows, &processes, options);
+                follower.send_events(events_tx);
+                let mut process = match launch(&follower, &program, &args) {
+                    Ok(process) => process,
+                    Err(e) => {
+                        let _ = started_tx.send(Err(e));
+                        return;
+                    }
+                };
+                if process.pid != 0 {
+                    follower.track(process.pid);
+                }
+                let _ = started_tx.send(Ok((process.pid, process.take_child())));
+                process.wait_until_idle(ready_timeout);
+                drop(process);
+                if !settle.is_zero() {
+                    std::thread::sleep(settle);
+                }
+                #[cfg(windows)]
+                if follow_children {
+                    match crate::window_events::WinEventHookSource::start() {
+                        Ok(source) => follower.watch(source),
+                        Err(e) => eprintln!("startt: no window events, polling only: {}", e),
+                    }
+                }
+                if let Some(engine) = follower.engine() {
+                    for grid in &engine.pool.monitors {
+                        println!(
+                            "Grid set to {}x{} on monitor {}",
+                            grid.layout.rows, grid.layout.cols, grid.monitor
+                        );
+                    }
+                }
+                loop {
+                    follower.poll(Instant::now());
+                    let end = follower.end();
+                    shared.publish(&follower, end);
+                    if end.is_some() || stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    follower.wait(&SystemClock, POLL_INTERVAL);
+                }
+                // Closes the event channel, which ends event streams
+                drop(follower);
+                shared.wake();
+            })
+        };
+        let (root_pid, child) = started_rx
+            .recv()
+            .map_err(|_| io::Error::other("launcher thread exited"))??;
+        Ok(LaunchedApp {
+            root_pid,
+            child,
+            shared,
+            events,
+            stop,
+            thread: Some(thread),
+            grace,
+        })
+    }
+}
+
+/// Starts `program` tagged for `follower`, reporting it as launched.
+fn launch(
+    follower: &Follower,
+    program: &OsStr,
+    args: &[OsString],
+) -> io::Result<platform::Process> {
+    let process = platform::launch(program, args, follower.tag()).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("cannot launch {}: {}", program.to_string_lossy(), e),
+        )
+    })?;
+    println!("Launched PID = {}", process.pid);
+    follower.emit(Event::Launched {
+        pid: process.pid,
+        file: process.file.clone(),
+        params: process.params.clone(),
+    });
+    Ok(process)
+}
+
+/// Quotes an argument that has spaces in it, for a command line string.
+fn quote(arg: &OsStr) -> String {
+    let arg = arg.to_string_lossy();
+    if arg.contains(' ') {
+        format!("\"{}\"", arg)
+    } else {
+        arg.into_owned()
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::ffi::{OsStr, OsString};
+    use std::io;
+    use std::os::windows::io::AsRawHandle;
+    use std::os::windows::process::CommandExt;
+    use std::path::Path;
+    use std::process::{Child, Command};
+    use std::time::Duration;
+    use widestring::U16CString;
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::GetProcessId;
+    use winapi::um::shellapi::{SEE_MASK_NOCLOSEPROCESS, SHELLEXECUTEINFOW, ShellExecuteExW};
+    use winapi::um::winbase::CREATE_NEW_CONSOLE;
+    use winapi::um::winnt::HANDLE;
+    use winapi::um::winuser::{SW_SHOWNORMAL, WaitForInputIdle};
+
+    /// A started target: a program started with [`Command`], or whatever
+    /// ShellExecuteEx started, with the handle it returned (null if it returned none).
+    pub struct Process {
+        pub pid: u32,
+        /// What was started and with which parameters, after any URL rewriting.
+        pub file: String,
+        pub params: String,
+        child: Option<Child>,
+        handle: HANDLE,
+    }
+
+    impl Process {
+        /// Waits up to `timeout` until the target is ready for input, so its first
+        /// windows exist. Returns at once for a console program.
+        pub fn wait_until_idle(&self, timeout: Duration) {
+            let handle = match &self.child {
+                Some(child) => child.as_raw_handle() as HANDLE,
+                None => self.handle,
+            };
+            if handle.is_null() {
+                return;
+            }
+            let millis = timeout.as_millis().min(u32::MAX as u128) as u32;
+            unsafe { WaitForInputIdle(handle, millis) };
+        }
+
+        /// None: a Windows process needs no reaping, and the child is kept for
+        /// [`Process::wait_until_idle`].
+        pub fn take_child(&mut self) -> Option<Child> {
+            None
+        }
+    }
+
+    impl Drop for Process {
+        fn drop(&mut self) {
+            if !self.handle.is_null() {
+                unsafe { CloseHandle(self.handle) };
+            }
+        }
+    }
+
+    /// The program registered to open `scheme` URLs (`HKCR\<scheme>\shell\open\command`).
+    fn url_handler(scheme: &str) -> io::Result<OsString> {
+        use winreg::RegKey;
+        use winreg::enums::HKEY_CLASSES_ROOT;
+        let command: String = RegKey::predef(HKEY_CLASSES_ROOT)
+            .open_subkey(format!(r"{}\shell\open\command", scheme))?
+            .get_value("")?;
+        // The path is quoted, or the first whitespace-separated token
+        let path = match command.strip_prefix('"') {
+            Some(rest) => rest.split('"').next(),
+            None => command.split_whitespace().next(),
+        };
+        Ok(path.unwrap_or_default().into())
+    }
+
+    /// Whether `file` names a program rather than a document or a `scheme:` URL.
+    fn is_program(file: &OsStr) -> bool {
+        match Path::new(file).extension() {
+            Some(ext) => ext.eq_ignore_ascii_case("exe") || ext.eq_ignore_ascii_case("com"),
+            // Anything before a colon but a drive letter is a URL scheme
+            None => file
+                .to_string_lossy()
+                .split_once(':')
+                .is_none_or(|(scheme, _)| scheme.len() == 1),
+        }
+    }
+
+    /// Starts a program with the tag added to its own copy of our environment, in a
+    /// console of its own as ShellExecuteEx would.
+    fn start_program(file: &OsStr, params: &str, tag: &str) -> io::Result<Child> {
+        let mut command = Command::new(file);
+        if !params.is_empty() {
+            command.raw_arg(params);
+        }
+        command
+            .env("STARTT", tag)
+            .creation_flags(CREATE_NEW_CONSOLE)
+            .spawn()
+    }
+
+    /// Opens `file` through ShellExecuteEx, so documents, URLs and `start`-style
+    /// targets work as they do from Explorer. ShellExecuteEx has no environment of its
+    /// own to give the target, so it isn't tagged.
+    fn shell_execute(file: &OsStr, params: &str) -> io::Result<HANDLE> {
+        let invalid = |e| io::Error::new(io::ErrorKind::InvalidInput, format!("{:?}", e));
+        let file_w = U16CString::from_os_str(file).map_err(invalid)?;
+        let params_w = U16CString::from_str(params).map_err(invalid)?;
+        let mut sei: SHELLEXECUTEINFOW = unsafe { std::mem::zeroed() };
+        sei.cbSize = std::mem::size_of::<SHELLEXECUTEINFOW>() as u32;
+        sei.fMask = SEE_MASK_NOCLOSEPROCESS;
+        sei.lpFile = file_w.as_ptr();
+        if !params.is_empty() {
+            sei.lpParameters = params_w.as_ptr();
+        }
+        sei.nShow = SW_SHOWNORMAL;
+        if unsafe { ShellExecuteExW(&mut sei) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(sei.hProcess)
+    }
+
+    /// Starts `program` tagged with `tag` if it is a program, and opens it through
+    /// ShellExecuteEx otherwise, or if no such program is found. http(s) URLs go to the
+    /// browser registered for them, which then is the launched process.
+    pub fn launch(program: &OsStr, args: &[OsString], tag: &str) -> io::Result<Process> {
+        let mut file = program.to_os_string();
+        let mut params = args
+            .iter()
+            .map(|arg| super::quote(arg))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let target = program.to_string_lossy();
+        if let Some(scheme) = ["http", "https"]
+            .into_iter()
+            .find(|scheme| target.starts_with(&format!("{}://", scheme)))
+        {
+            file = url_handler(scheme)?;
+            println!("Protocol handler for {}: {:?}", scheme, file);
+            params = target.into_owned();
+        }
+        let child = match is_program(&file) {
+            true => match start_program(&file, &params, tag) {
+                Ok(child) => Some(child),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e),
+            },
+            false => None,
+        };
+        let (pid, handle) = match &child {
+            Some(child) => (child.id(), std::ptr::null_mut()),
+            // No process, and PID 0, when the target was handed to one already running
+            None => match shell_execute(&file, &params)? {
+                handle if handle.is_null() => (0, handle),
+                handle => (unsafe { GetProcessId(handle) }, handle),
+            },
+        };
+        Ok(Process {
+            pid,
+            file: file.to_string_lossy().into_owned(),
+            params,
+            child,
+            handle,
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::ffi::{OsStr, OsString};
+    use std::io;
+    use std::process::Child;
+    use std::time::Duration;
+
+    /// A started target.
+    pub struct Process {
+        pub pid: u32,
+        pub file: String,
+        pub params: String,
+        child: Option<Child>,
+    }
+
+    impl Process {
+        /// X11 has no notion of a program being ready for input.
+        pub fn wait_until_idle(&self, _timeout: Duration) {}
+
+        /// The launched child, which has to be reaped once it exits.
+        pub fn take_child(&mut self) -> Option<Child> {
+            self.child.take()
+        }
+    }
+
+    /// Executes `program` with the tag in its environment.
+    pub fn launch(program: &OsStr, args: &[OsString], tag: &str) -> io::Result<Process> {
+        let child = std::process::Command::new(program)
+            .args(args)
+            .env("STARTT", tag)
+            .spawn()?;
+        Ok(Process {
+            pid: child.id(),
+            file: program.to_string_lossy().into_owned(),
+            params: args
+                .iter()
+                .map(|arg| super::quote(arg))
+                .collect::<Vec<_>>()
+                .join(" "),
+            child: Some(child),
+        })
+    }
+}
+
+/// What the follower thread has found, as of its last poll.
+#[derive(Debug, Default)]
+struct Found {
+    pids: Vec<u32>,
+    processes: Vec<ProcessInfo>,
+    windows: Vec<WindowInfo>,
+    end: Option<FollowEnd>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    found: Mutex<Found>,
+    changed: Condvar,
+    /// Tasks waiting for the next poll (the `async` API).
+    wakers: Mutex<Vec<Waker>>,
+}
+
+impl Shared {
+    fn publish(&self, follower: &Follower, end: Option<FollowEnd>) {
+        let mut pids: Vec<u32> = follower.pids().iter().copied().collect();
+        pids.sort_unstable();
+        *self.found.lock().unwrap() = Found {
+            pids,
+            processes: follower.processes(),
+            windows: follower.window_infos(),
+            end,
+        };
+        self.changed.notify_all();
+        self.wake();
+    }
+
+    /// Wakes the waiting tasks. Takes their wakers with `found` held, so none of them is
+    /// between reading it (or the event channel) and registering its waker, but wakes
+    /// them after releasing it.
+    fn wake(&self) {
+        let wakers = {
+            let _found = self.found.lock().unwrap();
+            std::mem::take(&mut *self.wakers.lock().unwrap())
+        };
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Has `waker` woken after the next poll. Called with `found` locked, so a poll
+    /// that publishes after `found` was read always wakes it.
+    #[cfg(feature = "async")]
+    fn wake_after_poll(&self, waker: &Waker) {
+        let mut wakers = self.wakers.lock().unwrap();
+        if !wakers.iter().any(|w| w.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+    }
+}
+
+/// A target started by [`Launcher::spawn`]. Dropping it stops following the launch
+/// but leaves it running; it does not wait for the follower thread, which exits by
+/// itself within a [`POLL_INTERVAL`].
+#[derive(Debug)]
+pub struct LaunchedApp {
+    root_pid: u32,
+    /// The launched process, when the app has to reap it.
+    child: Option<Child>,
+    shared: Arc<Shared>,
+    events: Receiver<Event>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+    grace: Duration,
+}
+
+impl LaunchedApp {
+    /// PID of the process that was started.
+    pub fn root_pid(&self) -> u32 {
+        self.root_pid
+    }
+
+    /// Open windows of the launch, in the order they were found.
+    pub fn windows(&self) -> Vec<WindowId> {
+        let found = self.shared.found.lock().unwrap();
+        found.windows.iter().map(|window| window.handle).collect()
+    }
+
+    /// [`LaunchedApp::windows`] with their class, title and bounds, as of the last poll.
+    pub fn window_infos(&self) -> Vec<WindowInfo> {
+        self.shared.found.lock().unwrap().windows.clone()
+    }
+
+    /// Every other process of the launch seen so far, including ones that have exited.
+    pub fn descendants(&self) -> Vec<u32> {
+        let found = self.shared.found.lock().unwrap();
+        found
+            .pids
+            .iter()
+            .copied()
+            .filter(|&pid| pid != self.root_pid)
+            .collect()
+    }
+
+    /// The launch's processes that were seen running, root included, by PID and as
+    /// they were last seen.
+    pub fn processes(&self) -> Vec<ProcessInfo> {
+        self.shared.found.lock().unwrap().processes.clone()
+    }
+
+    /// The first window of the launch, waiting up to `timeout` for one to be found.
+    /// None if none turned up in time or the follow loop ended without one.
+    pub fn wait_for_window(&self, timeout: Duration) -> Option<WindowId> {
+        let found = self.shared.found.lock().unwrap();
+        let (found, _) = self
+            .shared
+            .changed
+            .wait_timeout_while(found, timeout, |found| {
+                found.windows.is_empty() && found.end.is_none()
+            })
+            .unwrap();
+        found.windows.first().map(|window| window.handle)
+    }
+
+    /// A handle on what the follower finds, for code that can't hold the app itself,
+    /// such as a Ctrl+C handler.
+    pub fn handle(&self) -> LaunchHandle {
+        LaunchHandle {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Why the follow loop ended, or None while it runs.
+    pub fn end(&self) -> Option<FollowEnd> {
+        self.shared.found.lock().unwrap().end
+    }
+
+    /// The events of the launch: the same ones `--events` writes, from the `launched`
+    /// event on.
+    pub fn events(&self) -> &Receiver<Event> {
+        &self.events
+    }
+
+    /// Blocks until the follow loop ends on its own; with `-F` it never does.
+    pub fn wait(mut self) -> FollowEnd {
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        self.end().unwrap_or(FollowEnd::Finished)
+    }
+
+    /// Stops following and shuts the launch down: its processes and their descendants
+    /// and its windows are asked to close, and whatever is left after the grace period
+    /// is terminated.
+    pub fn close(mut self) -> ShutdownReport {
+        self.stop_following();
+        let windows = self.windows();
+        shutdown::shutdown(&[self.root_pid], &windows, self.grace)
+    }
+
+    fn stop_following(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Returned by [`LaunchedApp::handle`]; reads what the follower thread found at its
+/// last poll, even after the app was dropped.
+#[derive(Debug, Clone)]
+pub struct LaunchHandle {
+    shared: Arc<Shared>,
+}
+
+impl LaunchHandle {
+    /// Open windows of the launch, in the order they were found.
+    pub fn windows(&self) -> Vec<WindowId> {
+        let found = self.shared.found.lock().unwrap();
+        found.windows.iter().map(|window| window.handle).collect()
+    }
+
+    /// Every process of the launch seen so far, root included.
+    pub fn pids(&self) -> Vec<u32> {
+        self.shared.found.lock().unwrap().pids.clone()
+    }
+}
+
+impl Drop for LaunchedApp {
+    /// Only signals the follower thread, so dropping never blocks (an async executor,
+    /// say); [`LaunchedApp::close`] and [`LaunchedApp::wait`] join it.
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // Reaps the launched process now, or whenever it exits, so it doesn't linger
+        // as a zombie
+        if let Some(mut child) = self.child.take()
+            && matches!(child.try_wait(), Ok(None))
+        {
+            std::thread::spawn(move || child.wait());
+        }
+    }
+}
+
+/// The events of a [`LaunchedApp`] as a [`Stream`](futures_core::Stream).
+#[cfg(feature = "async")]
+pub use crate::events::Event as LaunchEvent;
+
+#[cfg(feature = "async")]
+impl LaunchedApp {
+    /// The windows of the launch that `filter` accepts, as soon as there is one. Resolves
+    /// early with what matched so far (possibly nothing) once `timeout` has passed or
+    /// the follow loop has ended. Never blocks: it is woken by the follower thread
+    /// after each poll, so `timeout` is checked every [`POLL_INTERVAL`].
+    pub async fn wait_for_windows(
+        &self,
+        filter: impl Fn(&WindowInfo) -> bool,
+        timeout: Duration,
+    ) -> Vec<WindowInfo> {
+        let deadline = Instant::now() + timeout;
+        std::future::poll_fn(|cx| {
+            let found = self.shared.found.lock().unwrap();
+            let matching: Vec<WindowInfo> = found
+                .windows
+                .iter()
+                .filter(|window| filter(window))
+                .cloned()
+                .collect();
+            if !matching.is_empty() || found.end.is_some() || Instant::now() >= deadline {
+                return Poll::Ready(matching);
+            }
+            self.shared.wake_after_poll(cx.waker());
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// The events of the launch as a stream that ends when following stops. It reads
+    /// the same channel as [`LaunchedApp::events`], so each event goes to one of them.
+    pub fn event_stream(&self) -> EventStream {
+        EventStream {
+            events: self.events.clone(),
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+/// Returned by [`LaunchedApp::event_stream`].
+#[cfg(feature = "async")]
+#[derive(Debug)]
+pub struct EventStream {
+    events: Receiver<Event>,
+    shared: Arc<Shared>,
+}
+
+#[cfg(feature = "async")]
+impl futures_core::Stream for EventStream {
+    type Item = LaunchEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<LaunchEvent>> {
+        use crossbeam_channel::TryRecvError;
+        let _found = self.shared.found.lock().unwrap();
+        match self.events.try_recv() {
+            Ok(event) => Poll::Ready(Some(event)),
+            Err(TryRecvError::Disconnected) => Poll::Ready(None),
+            Err(TryRecvError::Empty) => {
+                self.shared.wake_after_poll(cx.waker());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::task::Wake;
+
+    /// A waker that reads what was found, as a woken task polling right away would.
+    struct Reader {
+        shared: Arc<Shared>,
+        woken: AtomicBool,
+    }
+
+    impl Wake for Reader {
+        fn wake(self: Arc<Self>) {
+            let _found = self.shared.found.try_lock().unwrap();
+            self.woken.store(true, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn wakers_run_without_the_lock_held() {
+        let shared = Arc::new(Shared::default());
+        let reader = Arc::new(Reader {
+            shared: shared.clone(),
+            woken: AtomicBool::new(false),
+        });
+        shared
+            .wakers
+            .lock()
+            .unwrap()
+            .push(Waker::from(reader.clone()));
+        shared.wake();
+        assert!(reader.woken.load(Ordering::Relaxed));
+        assert!(shared.wakers.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,24 @@
+// src/linux.rs
+//! The Linux side of the `startt` binary.
+
+unsafe extern "C" {
+    fn dup(fd: i32) -> i32;
+    fn dup2(fd: i32, to: i32) -> i32;
+}
+
+/// Points stdout at stderr and returns a descriptor for the original stdout, so
+/// `println!` in the library goes to stderr too.
+pub fn divert_stdout_to_stderr() -> i32 {
+    unsafe {
+        let stdout = dup(1);
+        dup2(2, 1);
+        stdout
+    }
+}
+
+/// Undoes [`divert_stdout_to_stderr`].
+pub fn restore_stdout(stdout: i32) {
+    if stdout >= 0 {
+        unsafe { dup2(stdout, 1) };
+    }
+}
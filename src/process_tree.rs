@@ -0,0 +1,392 @@
+// src/process_tree.rs
+//! Point-in-time snapshots of the process table.
+//!
+//! [`ProcessTree::snapshot`] reads every process's PID, parent PID, image name and start
+//! time (Toolhelp32 and `GetProcessTimes` on Windows, `/proc/<pid>/stat` on Linux).
+//! The queries on top of it — descendants, start-time ordering, matching an image
+//! against a launch target, and diffing two snapshots — don't touch the OS, so they
+//! behave the same on every platform.
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// One process, as it was when the snapshot was taken.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    /// Parent PID as recorded at creation; the parent may have exited since.
+    pub ppid: u32,
+    /// Executable file name, e.g. `cargo-e.exe`.
+    pub image: String,
+    /// Milliseconds since the Unix epoch, if the process could be queried.
+    pub start_time: Option<u64>,
+    /// Only filled in by [`ProcessTree::with_cmdlines`].
+    pub cmdline: Option<String>,
+}
+
+impl ProcessInfo {
+    /// Same process as `other`: same PID and, where known, same start time (PIDs are reused).
+    fn is_same_process(&self, other: &ProcessInfo) -> bool {
+        self.pid == other.pid
+            && (self.start_time.is_none()
+                || other.start_time.is_none()
+                || self.start_time == other.start_time)
+    }
+}
+
+/// What changed between two snapshots.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProcessTreeDiff {
+    /// In the newer snapshot only.
+    pub started: Vec<u32>,
+    /// In the older snapshot only.
+    pub exited: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProcessTree {
+    entries: BTreeMap<u32, ProcessInfo>,
+}
+
+impl FromIterator<ProcessInfo> for ProcessTree {
+    fn from_iter<I: IntoIterator<Item = ProcessInfo>>(entries: I) -> Self {
+        ProcessTree {
+            entries: entries.into_iter().map(|e| (e.pid, e)).collect(),
+        }
+    }
+}
+
+/// The file name part of a launch target: quotes and directories stripped.
+pub fn program_file_name(program: &str) -> &str {
+    let program = program.trim_matches('"');
+    program.rsplit(['\\', '/']).next().unwrap_or(program)
+}
+
+/// Whether a process image belongs to a launch target (`notepad`, `notepad.exe` or a
+/// path to either). Case-insensitive; the target's `.exe`/`.com` extension is optional
+/// so Windows-style targets also match extensionless images.
+pub fn image_matches(image: &str, program: &str) -> bool {
+    let image = image.to_ascii_lowercase();
+    let program = program_file_name(program).to_ascii_lowercase();
+    if program.is_empty() {
+        return false;
+    }
+    if image.contains(&program) {
+        return true;
+    }
+    program
+        .strip_suffix(".exe")
+        .or_else(|| program.strip_suffix(".com"))
+        .is_some_and(|stem| !stem.is_empty() && image.contains(stem))
+}
+
+impl ProcessTree {
+    /// Snapshot of every process currently running.
+    pub fn snapshot() -> Self {
+        platform::entries().into_iter().collect()
+    }
+
+    /// Fills in every entry's command line (one process read per entry).
+    pub fn with_cmdlines(mut self) -> Self {
+        use crate::ps::ProcessInspector;
+        let inspector = crate::ps::native();
+        for entry in self.entries.values_mut() {
+            entry.cmdline = inspector.cmdline(entry.pid);
+        }
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, pid: u32) -> Option<&ProcessInfo> {
+        self.entries.get(&pid)
+    }
+
+    pub fn contains(&self, pid: u32) -> bool {
+        self.entries.contains_key(&pid)
+    }
+
+    /// Entries in PID order.
+    pub fn iter(&self) -> impl Iterator<Item = &ProcessInfo> {
+        self.entries.values()
+    }
+
+    pub fn pids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.entries.keys().copied()
+    }
+
+    pub fn parent_of(&self, pid: u32) -> Option<u32> {
+        self.get(pid).map(|e| e.ppid)
+    }
+
+    /// Direct children of `pid`. A child started before `pid` is an orphan whose parent
+    /// PID was reused, and is left out.
+    pub fn children(&self, pid: u32) -> Vec<u32> {
+        let parent_start = self.get(pid).and_then(|e| e.start_time);
+        self.iter()
+            .filter(|e| e.ppid == pid && e.pid != pid)
+            .filter(|e| match (parent_start, e.start_time) {
+                (Some(parent), Some(child)) => child >= parent,
+                _ => true,
+            })
+            .map(|e| e.pid)
+            .collect()
+    }
+
+    /// Every process below `pid`, depth first; `pid` itself is not included.
+    pub fn descendants(&self, pid: u32) -> Vec<u32> {
+        let mut found = Vec::new();
+        let mut stack = vec![pid];
+        while let Some(parent) = stack.pop() {
+            for child in self.children(parent) {
+                if child != pid && !found.contains(&child) {
+                    found.push(child);
+                    stack.push(child);
+                }
+            }
+        }
+        found
+    }
+
+    /// Entries oldest first; processes without a start time come last.
+    pub fn by_start_time(&self) -> Vec<&ProcessInfo> {
+        let mut entries: Vec<_> = self.iter().collect();
+        entries.sort_by_key(|e| (e.start_time.is_none(), e.start_time, e.pid));
+        entries
+    }
+
+    /// Processes whose image matches `program` (see [`image_matches`]), most recently
+    /// started first.
+    pub fn most_recent_matching(&self, program: &str) -> Vec<&ProcessInfo> {
+        let mut entries: Vec<_> = self
+            .iter()
+            .filter(|e| image_matches(&e.image, program))
+            .collect();
+        entries.sort_by_key(|e| std::cmp::Reverse((e.start_time, e.pid)));
+        entries
+    }
+
+    /// Processes started and exited between this snapshot and `newer`. A reused PID
+    /// shows up in both lists.
+    pub fn diff(&self, newer: &ProcessTree) -> ProcessTreeDiff {
+        let same = |a: &ProcessInfo, other: &ProcessTree| {
+            other.get(a.pid).is_some_and(|b| a.is_same_process(b))
+        };
+        ProcessTreeDiff {
+            started: newer
+                .iter()
+                .filter(|e| !same(e, self))
+                .map(|e| e.pid)
+                .collect(),
+            exited: self
+                .iter()
+                .filter(|e| !same(e, newer))
+                .map(|e| e.pid)
+                .collect(),
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::ProcessInfo;
+    use winapi::shared::minwindef::FILETIME;
+    use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+    use winapi::um::processthreadsapi::{GetProcessTimes, OpenProcess};
+    use winapi::um::tlhelp32::{
+        CreateToolhelp32Snapshot, PROCESSENTRY32W, Process32FirstW, Process32NextW,
+        TH32CS_SNAPPROCESS,
+    };
+    use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+
+    /// Milliseconds between 1601-01-01 (FILETIME epoch) and 1970-01-01.
+    const FILETIME_UNIX_EPOCH_MS: u64 = 11_644_473_600_000;
+
+    fn start_time(pid: u32) -> Option<u64> {
+        let zero = FILETIME {
+            dwLowDateTime: 0,
+            dwHighDateTime: 0,
+        };
+        let (mut creation, mut exit, mut kernel, mut user) = (zero, zero, zero, zero);
+        unsafe {
+            let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+            if handle.is_null() {
+                return None;
+            }
+            let ok = GetProcessTimes(handle, &mut creation, &mut exit, &mut kernel, &mut user);
+            CloseHandle(handle);
+            if ok == 0 {
+                return None;
+            }
+        }
+        let ticks = (creation.dwHighDateTime as u64) << 32 | creation.dwLowDateTime as u64;
+        (ticks / 10_000).checked_sub(FILETIME_UNIX_EPOCH_MS)
+    }
+
+    pub fn entries() -> Vec<ProcessInfo> {
+        let mut entries = Vec::new();
+        unsafe {
+            let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+            if snapshot == INVALID_HANDLE_VALUE {
+                eprintln!("Failed to create process snapshot");
+                return entries;
+            }
+            let mut entry: PROCESSENTRY32W = std::mem::zeroed();
+            entry.dwSize = std::mem::size_of::<PROCESSENTRY32W>() as u32;
+            if Process32FirstW(snapshot, &mut entry) != 0 {
+                loop {
+                    let name_len = entry
+                        .szExeFile
+                        .iter()
+                        .position(|&c| c == 0)
+                        .unwrap_or(entry.szExeFile.len());
+                    entries.push(ProcessInfo {
+                        pid: entry.th32ProcessID,
+                        ppid: entry.th32ParentProcessID,
+                        image: String::from_utf16_lossy(&entry.szExeFile[..name_len]),
+                        start_time: start_time(entry.th32ProcessID),
+                        cmdline: None,
+                    });
+                    if Process32NextW(snapshot, &mut entry) == 0 {
+                        break;
+                    }
+                }
+            }
+            CloseHandle(snapshot);
+        }
+        entries
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::ProcessInfo;
+
+    /// `USER_HZ`, the unit of the start time in `/proc/<pid>/stat`; 100 on every
+    /// mainstream Linux architecture.
+    const CLOCK_TICKS_PER_SEC: u64 = 100;
+
+    /// System boot time in seconds since the Unix epoch (`btime` in `/proc/stat`).
+    fn boot_time() -> Option<u64> {
+        let stat = std::fs::read_to_string("/proc/stat").ok()?;
+        stat.lines()
+            .find_map(|line| line.strip_prefix("btime "))?
+            .trim()
+            .parse()
+            .ok()
+    }
+
+    fn entry(pid: u32, boot_time: Option<u64>) -> Option<ProcessInfo> {
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        // "pid (comm) state ppid ..."; comm may itself contain spaces and parentheses
+        let (head, rest) = stat.rsplit_once(')')?;
+        let comm = head.split_once('(')?.1;
+        let fields: Vec<&str> = rest.split_whitespace().collect();
+        // Fields after comm start at field 3 (state); ppid is field 4, starttime field 22
+        if fields.first() == Some(&"Z") {
+            // Exited, just not reaped by its parent yet
+            return None;
+        }
+        let ppid = fields.get(1)?.parse().ok()?;
+        let start_ticks: Option<u64> = fields.get(19).and_then(|f| f.parse().ok());
+        let image = std::fs::read_link(format!("/proc/{}/exe", pid))
+            .ok()
+            .and_then(|exe| Some(exe.file_name()?.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| comm.to_string());
+        Some(ProcessInfo {
+            pid,
+            ppid,
+            image,
+            start_time: boot_time
+                .zip(start_ticks)
+                .map(|(boot, ticks)| boot * 1000 + ticks * 1000 / CLOCK_TICKS_PER_SEC),
+            cmdline: None,
+        })
+    }
+
+    pub fn entries() -> Vec<ProcessInfo> {
+        let boot_time = boot_time();
+        let Ok(dir) = std::fs::read_dir("/proc") else {
+            return Vec::new();
+        };
+        dir.filter_map(|d| d.ok()?.file_name().to_str()?.parse().ok())
+            .filter_map(|pid| entry(pid, boot_time))
+            .collect()
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    /// A snapshot once `pid` has `count` descendants, waiting up to two seconds.
+    fn snapshot_with_descendants(pid: u32, count: usize) -> ProcessTree {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        loop {
+            let tree = ProcessTree::snapshot();
+            if tree.descendants(pid).len() >= count || Instant::now() >= deadline {
+                return tree;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn a_spawned_tree_shows_up_in_snapshots_and_their_diff() {
+        let before = ProcessTree::snapshot();
+        let mut shell = std::process::Command::new("sh")
+            .args(["-c", "sleep 5 & sleep 5"])
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+        let pid = shell.id();
+        let after = snapshot_with_descendants(pid, 2);
+
+        let sleeps = after.descendants(pid);
+        assert_eq!(sleeps.len(), 2, "{:?}", sleeps);
+        for &sleep in &sleeps {
+            let entry = after.get(sleep).unwrap();
+            assert_eq!(entry.ppid, pid);
+            assert!(image_matches(&entry.image, "sleep"), "{:?}", entry);
+        }
+        assert_eq!(after.children(pid).len(), 2);
+        assert_eq!(after.parent_of(pid), Some(std::process::id()));
+
+        // The shell started before the sleeps it started
+        let order: Vec<u32> = after.by_start_time().iter().map(|e| e.pid).collect();
+        let position = |pid| order.iter().position(|&p| p == pid).unwrap();
+        let shell_start = after.get(pid).unwrap().start_time.unwrap();
+        for &sleep in &sleeps {
+            assert!(position(pid) < position(sleep));
+            assert!(after.get(sleep).unwrap().start_time.unwrap() >= shell_start);
+        }
+
+        let diff = before.diff(&after);
+        for started in sleeps.iter().chain([&pid]) {
+            assert!(diff.started.contains(started), "{:?}", diff);
+            assert!(!diff.exited.contains(started), "{:?}", diff);
+        }
+
+        let _ = std::process::Command::new("kill")
+            .args(sleeps.iter().map(|pid| pid.to_string()))
+            .status();
+        let _ = shell.wait();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let exited = loop {
+            let exited = after.diff(&ProcessTree::snapshot()).exited;
+            if sleeps.iter().all(|pid| exited.contains(pid)) || Instant::now() >= deadline {
+                break exited;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        };
+        for gone in sleeps.iter().chain([&pid]) {
+            assert!(exited.contains(gone), "{:?}", exited);
+        }
+    }
+}
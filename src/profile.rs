@@ -0,0 +1,201 @@
+// src/profile.rs
+//! Layout profiles: saved `CommandLineOptions` in TOML or JSON.
+//!
+//! `--profile` accepts either a path to a profile file (`.json` is read as JSON,
+//! anything else as TOML) or the name of an entry in the `[profiles]` table of the
+//! user config (`%APPDATA%\startt\config.toml`, or `$XDG_CONFIG_HOME/startt/config.toml`
+//! / `~/.config/startt/config.toml` elsewhere):
+//!
+//! ```toml
+//! [profiles.bevy]
+//! grid = "5x7m1"
+//! fit_grid = true
+//! follow_children = true
+//! timeout_secs = 10
+//! command = ["cargo-e", "--run-all", "--run-at-a-time", "35"]
+//! ```
+use crate::cli::CommandLineOptions;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum ProfileError {
+    /// Not a file, and no `[profiles]` entry of that name in the user config.
+    NotFound {
+        name: String,
+        config: Option<PathBuf>,
+    },
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        message: String,
+    },
+}
+
+impl std::fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProfileError::NotFound { name, config } => match config {
+                Some(config) => write!(
+                    f,
+                    "profile '{}' is not a file or a [profiles] entry in {}",
+                    name,
+                    config.display()
+                ),
+                None => write!(f, "profile '{}' is not a file", name),
+            },
+            ProfileError::Read { path, source } => {
+                write!(f, "cannot read profile {}: {}", path.display(), source)
+            }
+            ProfileError::Parse { path, message } => {
+                write!(f, "invalid profile {}: {}", path.display(), message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ProfileError::Read { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// The user config file holding the `[profiles]` table, if a config directory is known.
+pub fn user_config_path() -> Option<PathBuf> {
+    let dir = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    }?;
+    Some(dir.join("startt").join("config.toml"))
+}
+
+/// Loads a profile from a file, or from the user config's `[profiles]` table by name.
+pub fn load_profile(name: &str) -> Result<CommandLineOptions, ProfileError> {
+    let path = Path::new(name);
+    if path.is_file() {
+        return load_profile_file(path);
+    }
+    let config = user_config_path();
+    if let Some(config) = config.as_deref().filter(|config| config.is_file())
+        && let Some(options) = load_named_profile(config, name)?
+    {
+        return Ok(options);
+    }
+    Err(ProfileError::NotFound {
+        name: name.to_string(),
+        config,
+    })
+}
+
+fn read(path: &Path) -> Result<String, ProfileError> {
+    std::fs::read_to_string(path).map_err(|source| ProfileError::Read {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn parse_error(path: &Path, message: impl std::fmt::Display) -> ProfileError {
+    ProfileError::Parse {
+        path: path.to_path_buf(),
+        message: message.to_string(),
+    }
+}
+
+/// Reads a profile file; `.json` files are JSON, everything else TOML.
+pub fn load_profile_file(path: &Path) -> Result<CommandLineOptions, ProfileError> {
+    let text = read(path)?;
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    if is_json {
+        serde_json::from_str(&text).map_err(|e| parse_error(path, e))
+    } else {
+        toml::from_str(&text).map_err(|e| parse_error(path, e))
+    }
+}
+
+/// Looks up `[profiles.<name>]` in a config file. Ok(None) if there is no such entry.
+fn load_named_profile(
+    config: &Path,
+    name: &str,
+) -> Result<Option<CommandLineOptions>, ProfileError> {
+    let table: toml::Table = toml::from_str(&read(config)?).map_err(|e| parse_error(config, e))?;
+    let Some(profile) = table
+        .get("profiles")
+        .and_then(|profiles| profiles.get(name))
+    else {
+        return Ok(None);
+    };
+    profile
+        .clone()
+        .try_into()
+        .map(Some)
+        .map_err(|e| parse_error(config, format!("[profiles.{}]: {}", name, e)))
+}
+
+/// Renders options as a TOML profile (what `--print-profile` prints).
+pub fn to_toml(options: &CommandLineOptions) -> String {
+    toml::to_string_pretty(options).unwrap_or_else(|e| format!("# cannot render profile: {}\n", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `text` to a profile file unique to the calling test.
+    fn profile_file(name: &str, text: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "startt-profile-{}-{}.toml",
+            name,
+            std::process::id()
+        ));
+        std::fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn misspelled_keys_are_rejected() {
+        let path = profile_file("misspelled", "fit_gird = true\n");
+        let err = load_profile_file(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(err, ProfileError::Parse { .. }), "{}", err);
+        assert!(err.to_string().contains("fit_gird"), "{}", err);
+    }
+
+    #[test]
+    fn command_line_turns_profile_flags_off() {
+        let path = profile_file(
+            "flags",
+            "fit_grid = true\nfollow_children = true\nfollow_forever = true\nfind_mode = \"recent\"\n",
+        );
+        let options = crate::cli::parse_args([
+            "--profile".as_ref(),
+            path.as_os_str(),
+            "--fit-grid=false".as_ref(),
+            "--follow-forever=off".as_ref(),
+            "--find-recent=no".as_ref(),
+            "app".as_ref(),
+        ]);
+        std::fs::remove_file(&path).unwrap();
+        let options = options.unwrap();
+        assert!(!options.fit_grid);
+        assert!(!options.follow_forever);
+        assert!(options.follow_children);
+        assert_eq!(options.find_mode, crate::cli::FindMode::EnvTag);
+        assert_eq!(options.command, ["app"]);
+    }
+
+    #[test]
+    fn flags_reject_other_values() {
+        let err = crate::cli::parse_args(["--backfill=maybe", "app"]).unwrap_err();
+        assert!(err.to_string().contains("true or false"), "{}", err);
+    }
+}
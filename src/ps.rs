@@ -1,38 +1,341 @@
-pub fn get_cmdline_for_pid(parent_pid: u32) -> String {
-    use std::process::Command;
-
-    // Use PowerShell with NtObjectManager to get the command line
-    // Prompt for -Force if not installed, otherwise skip Install-Module
-    let ps_script = format!(
-        r#"
-            if (-not (Get-Module -ListAvailable -Name NtObjectManager)) {{
-            Write-Host "NtObjectManager not found. Installing..." -ForegroundColor Yellow
-            if (-not (Get-PackageProvider -Name NuGet -ErrorAction SilentlyContinue)) {{
-            Write-Host "NuGet provider not found. Installing..." -ForegroundColor Yellow
-            Install-PackageProvider -Name NuGet -MinimumVersion 2.8.5.201 -Force -Scope CurrentUser -ErrorAction Stop
-            }}
-            Install-Module -Name NtObjectManager -Scope CurrentUser -Force -ErrorAction Stop -Confirm:$false
-            }}
-            Import-Module NtObjectManager -ErrorAction Stop
-            try {{ (Get-NtProcess -ProcessId {}).CommandLine }} catch {{ '' }}
-            "#,
-        parent_pid
-    );
-    // println!("[PowerShell] Executing script: {}", ps_script);
-    let output = Command::new("powershell")
-        .args(&["-NoProfile", "-Command", &ps_script])
-        .output();
-
-    match output {
-        Ok(out) if out.status.success() => {
-            let cmdline = String::from_utf8_lossy(&out.stdout).trim().to_string();
-            if !cmdline.is_empty() {
-                cmdline
-            } else {
-                "<no cmdline found>".to_string()
+// src/ps.rs
+//! Native process inspection: command lines and environment blocks.
+//!
+//! [`ProcessInspector`] is implemented by [`WindowsInspector`] (reads the target's PEB
+//! with `ReadProcessMemory`) and [`ProcFsInspector`] (reads `/proc/<pid>/cmdline` and
+//! `/proc/<pid>/environ`). [`native`] returns the one for the current platform, and the
+//! free functions below use it; environment-tagged discovery (`STARTT=<pid>`) goes
+//! through [`ProcessInspector::env_pids`].
+use crate::process_tree::ProcessTree;
+use std::collections::HashSet;
+
+pub trait ProcessInspector {
+    /// All process IDs currently running.
+    fn pids(&self) -> Vec<u32>;
+
+    /// Snapshot of the process table these processes belong to.
+    fn tree(&self) -> ProcessTree {
+        ProcessTree::snapshot()
+    }
+
+    /// The process's command line, or None if it cannot be read.
+    fn cmdline(&self, pid: u32) -> Option<String>;
+
+    /// The process's environment as `(name, value)` pairs, or None if it cannot be read.
+    fn environ(&self, pid: u32) -> Option<Vec<(String, String)>>;
+
+    /// Whether the process has `name` set (to `value`, if given). None if its
+    /// environment cannot be read.
+    fn has_env_var(&self, pid: u32, name: &str, value: Option<&str>) -> Option<bool> {
+        let environ = self.environ(pid)?;
+        Some(
+            environ
+                .iter()
+                .any(|(n, v)| env_name_eq(n, name) && value.is_none_or(|value| v == value)),
+        )
+    }
+
+    /// Every process except this one that has `name` set (to `value`, if given).
+    fn env_pids(&self, name: &str, value: Option<&str>) -> Vec<u32> {
+        let own_pid = std::process::id();
+        self.pids()
+            .into_iter()
+            .filter(|&pid| pid != own_pid)
+            .filter(|&pid| self.has_env_var(pid, name, value).unwrap_or(false))
+            .collect()
+    }
+}
+
+/// Environment names are case-insensitive on Windows only.
+fn env_name_eq(a: &str, b: &str) -> bool {
+    if cfg!(windows) {
+        a.eq_ignore_ascii_case(b)
+    } else {
+        a == b
+    }
+}
+
+/// Splits a block of `NAME=value` entries. Windows keeps per-drive directories in
+/// entries like `=C:=C:\dir`, so the separator is searched after the first character.
+fn parse_environ<'a>(entries: impl Iterator<Item = &'a str>) -> Vec<(String, String)> {
+    entries
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let split = entry.get(1..)?.find('=')? + 1;
+            Some((entry[..split].to_string(), entry[split + 1..].to_string()))
+        })
+        .collect()
+}
+
+#[cfg(windows)]
+pub use windows_inspector::WindowsInspector;
+
+#[cfg(windows)]
+mod windows_inspector {
+    use super::{ProcessInspector, parse_environ};
+    use winapi::shared::ntdef::UNICODE_STRING;
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::memoryapi::{ReadProcessMemory, VirtualQueryEx};
+    use winapi::um::processthreadsapi::OpenProcess;
+    use winapi::um::winnt::{
+        HANDLE, MEMORY_BASIC_INFORMATION, PROCESS_QUERY_INFORMATION, PROCESS_VM_READ,
+    };
+
+    /// Reads command lines and environments out of the target's PEB.
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct WindowsInspector;
+
+    #[repr(C)]
+    #[allow(non_snake_case)]
+    struct PROCESS_BASIC_INFORMATION {
+        Reserved1: *mut std::ffi::c_void,
+        PebBaseAddress: *mut std::ffi::c_void,
+        Reserved2: [*mut std::ffi::c_void; 2],
+        UniqueProcessId: *mut std::ffi::c_void,
+        Reserved3: *mut std::ffi::c_void,
+    }
+
+    #[repr(C)]
+    #[allow(non_snake_case)]
+    struct PEB {
+        Reserved1: [u8; 2],
+        BeingDebugged: u8,
+        Reserved2: [u8; 1],
+        Reserved3: [*mut std::ffi::c_void; 2],
+        Ldr: *mut std::ffi::c_void,
+        ProcessParameters: *mut RTL_USER_PROCESS_PARAMETERS,
+    }
+
+    #[repr(C)]
+    #[allow(non_snake_case)]
+    struct RTL_USER_PROCESS_PARAMETERS {
+        Reserved1: [u8; 16],
+        Reserved2: [*mut std::ffi::c_void; 10],
+        ImagePathName: UNICODE_STRING,
+        CommandLine: UNICODE_STRING,
+        Environment: *mut std::ffi::c_void,
+    }
+
+    /// Environment blocks larger than this are not read.
+    const MAX_ENVIRONMENT_BYTES: usize = 1 << 20;
+
+    /// An open process handle, closed on drop.
+    struct Process(HANDLE);
+
+    impl Drop for Process {
+        fn drop(&mut self) {
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+
+    impl Process {
+        fn open(pid: u32) -> Option<Self> {
+            let handle =
+                unsafe { OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, 0, pid) };
+            (!handle.is_null()).then_some(Process(handle))
+        }
+
+        /// Reads `len` bytes at `address` into `out`.
+        fn read(
+            &self,
+            address: *const std::ffi::c_void,
+            out: *mut std::ffi::c_void,
+            len: usize,
+        ) -> bool {
+            let mut bytes_read = 0;
+            unsafe {
+                ReadProcessMemory(self.0, address as _, out as _, len, &mut bytes_read) != 0
+                    && bytes_read == len
             }
         }
-        _ => "<failed to get cmdline>".to_string(),
+
+        fn read_struct<T>(&self, address: *const std::ffi::c_void) -> Option<T> {
+            let mut value: T = unsafe { std::mem::zeroed() };
+            self.read(address, &mut value as *mut T as _, std::mem::size_of::<T>())
+                .then_some(value)
+        }
+
+        fn parameters(&self) -> Option<RTL_USER_PROCESS_PARAMETERS> {
+            // NtQueryInformationProcess is not in winapi, so it is looked up in ntdll
+            type NtQueryInformationProcess =
+                unsafe extern "system" fn(HANDLE, u32, *mut std::ffi::c_void, u32, *mut u32) -> i32;
+            let query: NtQueryInformationProcess = unsafe {
+                let ntdll =
+                    winapi::um::libloaderapi::GetModuleHandleA(b"ntdll.dll\0".as_ptr() as _);
+                if ntdll.is_null() {
+                    return None;
+                }
+                let address = winapi::um::libloaderapi::GetProcAddress(
+                    ntdll,
+                    b"NtQueryInformationProcess\0".as_ptr() as _,
+                );
+                if address.is_null() {
+                    return None;
+                }
+                std::mem::transmute(address)
+            };
+            let mut pbi: PROCESS_BASIC_INFORMATION = unsafe { std::mem::zeroed() };
+            let mut return_len = 0u32;
+            let status = unsafe {
+                query(
+                    self.0,
+                    0, // ProcessBasicInformation
+                    &mut pbi as *mut _ as *mut _,
+                    std::mem::size_of::<PROCESS_BASIC_INFORMATION>() as u32,
+                    &mut return_len,
+                )
+            };
+            if status != 0 {
+                return None;
+            }
+            let peb: PEB = self.read_struct(pbi.PebBaseAddress)?;
+            self.read_struct(peb.ProcessParameters as *const _)
+        }
+
+        fn read_unicode_string(&self, s: &UNICODE_STRING) -> Option<String> {
+            let mut buffer = vec![0u16; s.Length as usize / 2];
+            self.read(
+                s.Buffer as *const _,
+                buffer.as_mut_ptr() as _,
+                s.Length as usize,
+            )
+            .then(|| String::from_utf16_lossy(&buffer))
+        }
+
+        /// The environment block runs to a double NUL; its size is not stored in the
+        /// parameters on every Windows version, so read up to the end of its memory region.
+        fn read_environment(&self, address: *mut std::ffi::c_void) -> Option<Vec<u16>> {
+            let mut info: MEMORY_BASIC_INFORMATION = unsafe { std::mem::zeroed() };
+            let queried = unsafe {
+                VirtualQueryEx(
+                    self.0,
+                    address as _,
+                    &mut info,
+                    std::mem::size_of::<MEMORY_BASIC_INFORMATION>(),
+                )
+            };
+            if queried == 0 {
+                return None;
+            }
+            let offset = address as usize - info.BaseAddress as usize;
+            let len = info
+                .RegionSize
+                .saturating_sub(offset)
+                .min(MAX_ENVIRONMENT_BYTES)
+                & !1;
+            let mut buffer = vec![0u16; len / 2];
+            if !self.read(address, buffer.as_mut_ptr() as _, len) {
+                return None;
+            }
+            let end = buffer
+                .windows(2)
+                .position(|pair| pair == [0, 0])
+                .unwrap_or(buffer.len());
+            buffer.truncate(end);
+            Some(buffer)
+        }
+    }
+
+    impl ProcessInspector for WindowsInspector {
+        fn pids(&self) -> Vec<u32> {
+            use winapi::um::tlhelp32::{
+                CreateToolhelp32Snapshot, PROCESSENTRY32, Process32First, Process32Next,
+                TH32CS_SNAPPROCESS,
+            };
+            let mut pids = Vec::new();
+            unsafe {
+                let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+                if snapshot == winapi::um::handleapi::INVALID_HANDLE_VALUE {
+                    return pids;
+                }
+                let mut entry: PROCESSENTRY32 = std::mem::zeroed();
+                entry.dwSize = std::mem::size_of::<PROCESSENTRY32>() as u32;
+                if Process32First(snapshot, &mut entry) != 0 {
+                    loop {
+                        pids.push(entry.th32ProcessID);
+                        if Process32Next(snapshot, &mut entry) == 0 {
+                            break;
+                        }
+                    }
+                }
+                CloseHandle(snapshot);
+            }
+            pids
+        }
+
+        fn cmdline(&self, pid: u32) -> Option<String> {
+            let process = Process::open(pid)?;
+            let parameters = process.parameters()?;
+            process.read_unicode_string(&parameters.CommandLine)
+        }
+
+        fn environ(&self, pid: u32) -> Option<Vec<(String, String)>> {
+            let process = Process::open(pid)?;
+            let parameters = process.parameters()?;
+            let block = process.read_environment(parameters.Environment)?;
+            let block = String::from_utf16_lossy(&block);
+            Some(parse_environ(block.split('\0')))
+        }
+    }
+}
+
+/// Reads `/proc/<pid>/cmdline` and `/proc/<pid>/environ`.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcFsInspector;
+
+#[cfg(target_os = "linux")]
+impl ProcessInspector for ProcFsInspector {
+    fn pids(&self) -> Vec<u32> {
+        let Ok(entries) = std::fs::read_dir("/proc") else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+            .collect()
+    }
+
+    /// The arguments joined with spaces; arguments containing whitespace are quoted.
+    fn cmdline(&self, pid: u32) -> Option<String> {
+        let raw = std::fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+        let raw = String::from_utf8_lossy(&raw);
+        let args: Vec<String> = raw
+            .split('\0')
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| {
+                if arg.contains(char::is_whitespace) {
+                    format!("\"{}\"", arg)
+                } else {
+                    arg.to_string()
+                }
+            })
+            .collect();
+        Some(args.join(" "))
+    }
+
+    fn environ(&self, pid: u32) -> Option<Vec<(String, String)>> {
+        let raw = std::fs::read(format!("/proc/{}/environ", pid)).ok()?;
+        Some(parse_environ(String::from_utf8_lossy(&raw).split('\0')))
+    }
+}
+
+/// The inspector for the platform startt runs on.
+#[cfg(windows)]
+pub fn native() -> WindowsInspector {
+    WindowsInspector
+}
+
+/// The inspector for the platform startt runs on.
+#[cfg(target_os = "linux")]
+pub fn native() -> ProcFsInspector {
+    ProcFsInspector
+}
+
+pub fn get_cmdline_for_pid(parent_pid: u32) -> String {
+    match native().cmdline(parent_pid) {
+        Some(cmdline) if !cmdline.is_empty() => cmdline,
+        Some(_) => "<no cmdline found>".to_string(),
+        None => "<failed to get cmdline>".to_string(),
     }
 }
 
@@ -41,157 +344,90 @@ pub fn process_has_env_var(
     env_name: &str,
     env_value: Option<&str>,
 ) -> Option<bool> {
-    use std::process::Command;
-
-    // PowerShell script to get the environment variables for the process
-    let ps_script = format!(
-        r#"
-                if (-not (Get-Module -ListAvailable -Name NtObjectManager)) {{
-                    Write-Host "NtObjectManager not found. Installing..." -ForegroundColor Yellow
-                    if (-not (Get-PackageProvider -Name NuGet -ErrorAction SilentlyContinue)) {{
-                        Write-Host "NuGet provider not found. Installing..." -ForegroundColor Yellow
-                        Install-PackageProvider -Name NuGet -MinimumVersion 2.8.5.201 -Force -Scope CurrentUser -ErrorAction Stop
-                    }}
-                    Install-Module -Name NtObjectManager -Scope CurrentUser -Force -ErrorAction Stop -Confirm:$false
-                }}
-                Import-Module NtObjectManager -ErrorAction Stop
-                try {{
-                    $envs = Get-NtProcessEnvironment -ProcessId {pid}
-                    if ($envs -eq $null) {{ exit 2 }}
-                    $found = $false
-                    foreach ($env in $envs.GetEnumerator()) {{
-                        if ($env.Name -eq '{name}') {{
-                            {value_check}
-                        }}
-                    }}
-                    if ($found) {{ exit 0 }} else {{ exit 1 }}
-                }} catch {{ exit 3 }}
-                "#,
-        pid = parent_pid,
-        name = env_name,
-        value_check = match env_value {
-            Some(val) => format!("if ($env.Value -eq '{}') {{ $found = $true }}", val),
-            None => "$found = $true".to_string(),
-        }
-    );
-    //println!("[PowerShell] Executing script: {}", ps_script);
-    let output = Command::new("powershell")
-        .args(&["-NoProfile", "-Command", &ps_script])
-        .output();
-
-    match output {
-        Ok(out) => match out.status.code() {
-            Some(0) => Some(true),            // Found
-            Some(1) => Some(false),           // Not found
-            Some(2) | Some(3) | None => None, // Error or no env
-            _ => None,
-        },
-        Err(_) => None,
-    }
+    native().has_env_var(parent_pid, env_name, env_value)
 }
 
+/// The process's environment as `NAME=value` lines.
 pub fn process_print_env(parent_pid: u32) -> Option<String> {
-    use std::process::Command;
-
-    // PowerShell script to get all environment variables for the process
-    let ps_script = format!(
-        r#"
-                    if (-not (Get-Module -ListAvailable -Name NtObjectManager)) {{
-                         Write-Host "NtObjectManager not found. Installing..." -ForegroundColor Yellow
-                         if (-not (Get-PackageProvider -Name NuGet -ErrorAction SilentlyContinue)) {{
-                              Write-Host "NuGet provider not found. Installing..." -ForegroundColor Yellow
-                              Install-PackageProvider -Name NuGet -MinimumVersion 2.8.5.201 -Force -Scope CurrentUser -ErrorAction Stop
-                         }}
-                         Install-Module -Name NtObjectManager -Scope CurrentUser -Force -ErrorAction Stop -Confirm:$false
-                    }}
-                    Import-Module NtObjectManager -ErrorAction Stop
-                    try {{
-                         $envs = Get-NtProcessEnvironment -ProcessId {pid}
-                         if ($envs -eq $null) {{ exit 2 }}
-                         $envs.GetEnumerator() | ForEach-Object {{ "$($_.Name)=$($_.Value)" }}
-                    }} catch {{ exit 3 }}
-                    "#,
-        pid = parent_pid
-    );
-    //println!("[PowerShell] Executing script: {}", ps_script);
-    let output = Command::new("powershell")
-        .args(&["-NoProfile", "-Command", &ps_script])
-        .output();
-
-    match output {
-        Ok(out) if out.status.success() => {
-            let envs = String::from_utf8_lossy(&out.stdout).trim().to_string();
-            if !envs.is_empty() { Some(envs) } else { None }
-        }
-        _ => None,
+    let environ = native().environ(parent_pid)?;
+    let envs: Vec<String> = environ
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect();
+    if !envs.is_empty() {
+        Some(envs.join("\n"))
+    } else {
+        None
     }
 }
 
+/// Adds every process that has `env_name` set (to `env_value`, if given) to `pid_map`.
+/// Returns whether any was found.
 pub fn get_env_child_pids(
     env_name: &str,
     env_value: Option<&str>,
-    pid_map: &mut std::collections::HashSet<u32>,
+    pid_map: &mut HashSet<u32>,
 ) -> Option<bool> {
-    use std::process::Command;
-
-    // PowerShell script to enumerate all processes and check their environment variables
-    let ps_script = format!(
-        r#"
-        if (-not (Get-Module -ListAvailable -Name NtObjectManager)) {{
-            Write-Host "NtObjectManager not found. Installing..." -ForegroundColor Yellow
-            if (-not (Get-PackageProvider -Name NuGet -ErrorAction SilentlyContinue)) {{
-                Write-Host "NuGet provider not found. Installing..." -ForegroundColor Yellow
-                Install-PackageProvider -Name NuGet -MinimumVersion 2.8.5.201 -Force -Scope CurrentUser -ErrorAction Stop
-            }}
-            Install-Module -Name NtObjectManager -Scope CurrentUser -Force -ErrorAction Stop -Confirm:$false
-        }}
-        Import-Module NtObjectManager -ErrorAction Stop
-        $found = $false
-        try {{
-            $procs = Get-NtProcess
-            foreach ($proc in $procs) {{
-                try {{
-                    $envs = Get-NtProcessEnvironment -ProcessId $proc.ProcessId
-                    if ($envs -ne $null) {{
-                        foreach ($env in $envs.GetEnumerator()) {{
-                            if ($env.Name -eq '{name}') {{
-                                {value_check}
-                            }}
-                        }}
-                    }}
-                }} catch {{}}
-            }}
-            if ($found) {{ exit 0 }} else {{ exit 1 }}
-        }} catch {{ exit 2 }}
-        "#,
-        name = env_name,
-        value_check = match env_value {
-            Some(val) => format!(
-                "if ($env.Value -eq '{}') {{ Write-Output $proc.ProcessId; $found = $true }}",
-                val
-            ),
-            None => "Write-Output $proc.ProcessId; $found = $true".to_string(),
+    let pids = native().env_pids(env_name, env_value);
+    let found = !pids.is_empty();
+    pid_map.extend(pids);
+    Some(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(entries: &[(&str, &str)]) -> Vec<(String, String)> {
+        entries
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn environ_blocks_split_on_nul_and_the_first_equals_sign() {
+        let block = "PATH=/usr/bin\0STARTT=42\0EMPTY=\0\0";
+        assert_eq!(
+            parse_environ(block.split('\0')),
+            pairs(&[("PATH", "/usr/bin"), ("STARTT", "42"), ("EMPTY", "")])
+        );
+        // Values may contain `=`; Windows' per-drive directories start with one
+        let block = "OPTS=a=1,b=2\0=C:=C:\\dir\0NOEQUALS\0";
+        assert_eq!(
+            parse_environ(block.split('\0')),
+            pairs(&[("OPTS", "a=1,b=2"), ("=C:", "C:\\dir")])
+        );
+        assert!(parse_environ("".split('\0')).is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn a_tagged_child_is_found_by_its_environment() {
+        let tag = format!("ps-test-{}", std::process::id());
+        let mut child = std::process::Command::new("sleep")
+            .arg("5")
+            .env("STARTT", &tag)
+            .spawn()
+            .unwrap();
+        let pid = child.id();
+        let inspector = native();
+        // /proc shows our own environment until the child has exec'd
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(2);
+        while inspector.cmdline(pid).as_deref() != Some("sleep 5")
+            && std::time::Instant::now() < deadline
+        {
+            std::thread::sleep(std::time::Duration::from_millis(10));
         }
-    );
-
-    let output = Command::new("powershell")
-        .args(&["-NoProfile", "-Command", &ps_script])
-        .output();
-
-    match output {
-        Ok(out) => match out.status.code() {
-            Some(0) => {
-                let stdout = String::from_utf8_lossy(&out.stdout);
-                for line in stdout.lines() {
-                    if let Ok(pid) = line.trim().parse::<u32>() {
-                        pid_map.insert(pid);
-                    }
-                }
-                Some(true)
-            }
-            Some(1) => Some(false), // Not found
-            _ => None,              // Error
-        },
-        Err(_) => None,
+        let found = inspector.env_pids("STARTT", Some(&tag));
+        let has_tag = inspector.has_env_var(pid, "STARTT", Some(&tag));
+        let has_other = inspector.has_env_var(pid, "STARTT", Some("other"));
+        let cmdline = inspector.cmdline(pid);
+        let _ = child.kill();
+        let _ = child.wait();
+        assert_eq!(found, [pid]);
+        assert_eq!(has_tag, Some(true));
+        assert_eq!(has_other, Some(false));
+        assert_eq!(cmdline.as_deref(), Some("sleep 5"));
     }
 }
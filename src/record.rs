@@ -0,0 +1,146 @@
+// src/record.rs
+//! Session recordings for `--record PATH` and `startt replay`.
+//!
+//! A session file is JSON lines: a `monitors` record with the work area of every
+//! monitor, then a `window` record for each window the launch produced and a
+//! `destroyed` record when one goes away, each stamped with `t_ms` since recording
+//! started:
+//!
+//! ```text
+//! {"record":"monitors","work_areas":[{"monitor":0,"bounds":{"left":0,"top":0,"right":1920,"bottom":1040}}]}
+//! {"record":"window","t_ms":812,"hwnd":263462,"pid":5120,"class":"ConsoleWindowClass","title":"cargo-e","bounds":{...}}
+//! {"record":"destroyed","t_ms":4031,"hwnd":263462}
+//! ```
+//!
+//! The recorder listens to the [`events`](crate::events) stream, so anything that
+//! reports a discovered or destroyed window is recorded without further wiring.
+use crate::events::Event;
+use crate::grid::{Rect, WindowId};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkArea {
+    pub monitor: i32,
+    pub bounds: Rect,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+pub enum SessionRecord {
+    /// The monitors' work areas when recording started; the first line of a session.
+    Monitors { work_areas: Vec<WorkArea> },
+    /// A window belonging to the launch was seen for the first time.
+    Window {
+        t_ms: u64,
+        hwnd: WindowId,
+        pid: u32,
+        class: String,
+        title: String,
+        bounds: Option<Rect>,
+    },
+    /// A window went away.
+    Destroyed { t_ms: u64, hwnd: WindowId },
+}
+
+impl SessionRecord {
+    /// Milliseconds since recording started; 0 for the `monitors` header.
+    pub fn t_ms(&self) -> u64 {
+        match self {
+            SessionRecord::Monitors { .. } => 0,
+            SessionRecord::Window { t_ms, .. } | SessionRecord::Destroyed { t_ms, .. } => *t_ms,
+        }
+    }
+}
+
+struct Recorder {
+    out: Mutex<Box<dyn Write + Send>>,
+    started_at: Instant,
+}
+
+static RECORDER: OnceCell<Recorder> = OnceCell::new();
+
+impl Recorder {
+    fn write(&self, record: &SessionRecord) {
+        let Ok(mut line) = serde_json::to_vec(record) else {
+            return;
+        };
+        line.push(b'\n');
+        let mut out = self.out.lock().unwrap_or_else(|e| e.into_inner());
+        // Flushed per line so a session cut short by a crash can still be replayed
+        let written = out.write_all(&line).and_then(|()| out.flush());
+        if let Err(e) = written {
+            eprintln!("startt: cannot write recording: {}", e);
+        }
+    }
+}
+
+/// Starts recording to `path`, beginning with `work_areas`. Only the first call has an
+/// effect.
+pub fn install(path: &Path, work_areas: Vec<WorkArea>) -> io::Result<()> {
+    let out = io::BufWriter::new(std::fs::File::create(path)?);
+    let recorder = Recorder {
+        out: Mutex::new(Box::new(out)),
+        started_at: Instant::now(),
+    };
+    recorder.write(&SessionRecord::Monitors { work_areas });
+    let _ = RECORDER.set(recorder);
+    Ok(())
+}
+
+/// Whether a recording is in progress.
+pub fn enabled() -> bool {
+    RECORDER.get().is_some()
+}
+
+/// Records the windows `event` reports on, if a recording is in progress.
+pub fn observe(event: &Event) {
+    let Some(recorder) = RECORDER.get() else {
+        return;
+    };
+    let t_ms = recorder.started_at.elapsed().as_millis() as u64;
+    if let Some(record) = session_record(event, t_ms) {
+        recorder.write(&record);
+    }
+}
+
+/// The record of `event` at `t_ms`, for the events a session keeps.
+pub fn session_record(event: &Event, t_ms: u64) -> Option<SessionRecord> {
+    match event {
+        Event::WindowDiscovered { window } => Some(SessionRecord::Window {
+            t_ms,
+            hwnd: window.handle,
+            pid: window.pid,
+            class: window.class.clone(),
+            title: window.title.clone(),
+            bounds: Some(window.bounds),
+        }),
+        Event::WindowDestroyed { hwnd, .. } => Some(SessionRecord::Destroyed { t_ms, hwnd: *hwnd }),
+        _ => None,
+    }
+}
+
+/// Reads a session file. Blank lines are skipped; anything else that isn't a record is
+/// an error naming its line.
+pub fn load(path: &Path) -> io::Result<Vec<SessionRecord>> {
+    let file = io::BufReader::new(std::fs::File::open(path)?);
+    let mut records = Vec::new();
+    for (n, line) in file.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = serde_json::from_str(&line).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}:{}: {}", path.display(), n + 1, e),
+            )
+        })?;
+        records.push(record);
+    }
+    Ok(records)
+}
@@ -0,0 +1,685 @@
+// src/replay.rs
+//! `startt replay SESSION [options]`: feeds a [`record`](crate::record)ed session into
+//! the grid engine and prints where every window would have gone.
+//!
+//! Only the platform-neutral [`GridEngine`] is involved, so a placement bug captured on
+//! one machine can be replayed anywhere, with a different `-g` grid or placement
+//! options, and gives the same timeline every time. The grid is laid out on the
+//! recorded work areas; the first recorded window is treated as the launched (parent)
+//! window and, as live, goes to the parent cell (`-apc`, default `0x0`) and reserves it
+//! when `-rpc` or `-apc` is given. `--rule`s are matched against each window's recorded
+//! class and title; `image` and `cmdline` conditions never match, as the recording
+//! doesn't carry them. A window that finds the grid full waits for a cell, like a
+//! staged window does live. `--timeout` deadlines run on the engine's timer wheel against
+//! the recorded times.
+use crate::cli::{CellSpec, CommandLineOptions, FullPolicy};
+use crate::grid::{GridEngine, GridLayout, GridPool, MonitorGrid, Placement, Rect, WindowId};
+use crate::record::{SessionRecord, WorkArea};
+use crate::rules::{RuleAction, RuleTimer, WindowFacts};
+use crate::timer_wheel::{DEFAULT_SLOTS, TimerWheel};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ffi::OsString;
+use std::fmt::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+pub const USAGE: &str = "Usage: startt replay <session.jsonl> -g GRID [grid options]";
+
+/// Work area assumed for a monitor the recording has no entry for.
+const DEFAULT_WORK_AREA: Rect = Rect {
+    left: 0,
+    top: 0,
+    right: 1920,
+    bottom: 1080,
+};
+
+/// What happened to a window at one point of the replay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayAction {
+    /// Put in a cell when it appeared.
+    Placed { window: WindowId, cell: usize },
+    /// Closed to make room for a new window.
+    Evicted { window: WindowId, cell: usize },
+    /// Its `--timeout` ran out.
+    TimedOut { window: WindowId, cell: usize },
+    /// Minimized to make room for a new window (`--on-full minimize-oldest`).
+    Minimized { window: WindowId, cell: usize },
+    /// Moved to `monitor` to make room for a new window (`--on-full overflow-monitor`).
+    Overflowed {
+        window: WindowId,
+        cell: usize,
+        monitor: i32,
+    },
+    /// Cascaded `depth` steps over a taken cell (`--on-full stack`).
+    Stacked {
+        window: WindowId,
+        cell: usize,
+        depth: usize,
+    },
+    /// The first grid got more cells (`--on-full grow`, `-g auto`).
+    Grown { rows: u32, cols: u32 },
+    /// An auto grid shrank to fit its windows after their number settled.
+    Shrunk { rows: u32, cols: u32 },
+    /// Appeared while every cell was taken; waits for a cell.
+    Staged { window: WindowId },
+    /// Closed, freeing its cell after holding it for `lifetime`.
+    Freed {
+        window: WindowId,
+        cell: usize,
+        lifetime: Duration,
+    },
+    /// Closed while it had no cell.
+    Closed { window: WindowId },
+    /// Closed while sharing `cell` with stacked windows, which keep it.
+    Unstacked { window: WindowId, cell: usize },
+    /// A waiting window took a freed cell (`--backfill`).
+    Backfilled { window: WindowId, cell: usize },
+    /// Shifted down to close a gap (`--compact`).
+    Compacted {
+        window: WindowId,
+        from: usize,
+        to: usize,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimelineEntry {
+    pub t_ms: u64,
+    pub action: ReplayAction,
+}
+
+/// The outcome of a replay.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub engine: GridEngine,
+    pub timeline: Vec<TimelineEntry>,
+}
+
+/// Lays out the grids `options` asks for on the recorded work areas.
+pub(crate) fn grid_pool(options: &CommandLineOptions, work_areas: &[WorkArea]) -> Option<GridPool> {
+    let monitor_count = work_areas.len() as i32;
+    let monitors: Vec<MonitorGrid> = options
+        .grid_specs(monitor_count)
+        .into_iter()
+        .map(|spec| {
+            let area = work_areas
+                .iter()
+                .find(|a| a.monitor == spec.monitor)
+                .map_or(DEFAULT_WORK_AREA, |a| a.bounds);
+            let layout = GridLayout::new(spec.rows, spec.cols, area)
+                .with_gutter(options.grid_gutter)
+                .with_margin(options.grid_margin)
+                .with_row_weights(spec.row_weights)
+                .with_col_weights(spec.col_weights);
+            MonitorGrid {
+                monitor: spec.monitor,
+                layout,
+                auto: spec.auto,
+            }
+        })
+        .collect();
+    (!monitors.is_empty()).then(|| GridPool::new(monitors))
+}
+
+/// `--backfill` (or `--on-full queue`) and `--compact` after a window left `cell`.
+fn refill(
+    engine: &mut GridEngine,
+    cell: usize,
+    staged: &mut VecDeque<WindowId>,
+    options: &CommandLineOptions,
+    now: Instant,
+    mut push: impl FnMut(ReplayAction),
+) {
+    // A window stacked in the cell took it over
+    if !engine.is_free(cell) {
+        return;
+    }
+    if (options.backfill || options.on_full == FullPolicy::Queue)
+        && let Some(window) = staged.pop_front()
+    {
+        engine.assign(cell, window, now);
+        push(ReplayAction::Backfilled { window, cell });
+    }
+    if options.compact {
+        for (window, from, to) in engine.compact() {
+            push(ReplayAction::Compacted { window, from, to });
+        }
+    }
+}
+
+/// A pinned window's cell: the first free cell of the rule's range, or the range's
+/// oldest window's, which is evicted. `None` if the range has no usable cell.
+fn make_room_in(
+    engine: &mut GridEngine,
+    spec: CellSpec,
+    window: WindowId,
+    now: Instant,
+    mut push: impl FnMut(ReplayAction),
+) -> Option<usize> {
+    let cells = engine.pool.region_cells(spec.monitor, &spec.region);
+    let cell = match cells.iter().copied().find(|&idx| engine.is_free(idx)) {
+        Some(idx) => idx,
+        None => {
+            let (idx, evicted) = engine.evict_oldest_in(&cells)?;
+            push(ReplayAction::Evicted {
+                window: evicted,
+                cell: idx,
+            });
+            idx
+        }
+    };
+    // Windows stacked over the evicted one keep the cell; this one goes on top
+    if engine.is_free(cell) {
+        engine.assign(cell, window, now);
+    } else {
+        engine.stack(cell, window, now);
+    }
+    Some(cell)
+}
+
+/// Starts the timer of the first rule with one that matches a window just put in a cell.
+fn start_rule_timer(
+    engine: &mut GridEngine,
+    options: &CommandLineOptions,
+    facts: &WindowFacts,
+    window: WindowId,
+    now: Instant,
+) {
+    let Some(timer) =
+        crate::rules::first_timer_match(&options.rules, facts).and_then(|(_, rule)| rule.timer)
+    else {
+        return;
+    };
+    let timers = engine.timers_mut();
+    match timer {
+        RuleTimer::After(after) => timers.schedule(window, now + after),
+        RuleTimer::Extend(by) => {
+            if !timers.extend(window, by) {
+                timers.schedule(window, now + by);
+            }
+        }
+        RuleTimer::Off => {
+            timers.pause(window, now);
+        }
+    }
+}
+
+/// Replays `records` on the grid described by `options`. `None` if no grid was given.
+/// Timeouts (`--timeout`) run out between records; none are run past the last one.
+pub fn replay(records: &[SessionRecord], options: &CommandLineOptions) -> Option<Replay> {
+    let work_areas = records
+        .iter()
+        .find_map(|r| match r {
+            SessionRecord::Monitors { work_areas } => Some(work_areas.as_slice()),
+            _ => None,
+        })
+        .unwrap_or_default();
+    let start = Instant::now();
+    let mut engine = GridEngine::new(grid_pool(options, work_areas)?, options.grid_placement_mode);
+    engine.set_timeout(options.timeout_secs.map(Duration::from_secs));
+    engine.set_on_full(options.on_full.strategy());
+    // Millisecond ticks from the start of the recording: timeouts run out exactly when
+    // they would have, and in the same order on every run
+    *engine.timers_mut() = TimerWheel::new(Duration::from_millis(1), DEFAULT_SLOTS, start);
+    let mut timeline = Vec::new();
+    let mut seen = HashSet::new();
+    let mut staged: VecDeque<WindowId> = VecDeque::new();
+
+    let mut records: Vec<&SessionRecord> = records.iter().collect();
+    records.sort_by_key(|r| r.t_ms());
+    let mut previous = start;
+    for record in records {
+        let t_ms = record.t_ms();
+        let now = start + Duration::from_millis(t_ms);
+        // Auto grids note the window count the previous record left, then shrink once
+        // it has held for long enough
+        engine.settle(previous);
+        previous = now;
+        while let Some(deadline) = engine.settle_deadline().filter(|&d| d <= now) {
+            if engine.settle(deadline) {
+                let layout = &engine.pool.primary().layout;
+                timeline.push(TimelineEntry {
+                    t_ms: deadline.duration_since(start).as_millis() as u64,
+                    action: ReplayAction::Shrunk {
+                        rows: layout.rows,
+                        cols: layout.cols,
+                    },
+                });
+            }
+        }
+        let deadlines: HashMap<WindowId, Instant> = engine
+            .occupancy()
+            .into_iter()
+            .flatten()
+            .filter_map(|w| Some((w, engine.timers().deadline(w)?)))
+            .collect();
+        for (cell, window) in engine.expire(now) {
+            let t_ms = deadlines
+                .get(&window)
+                .map_or(t_ms, |d| d.duration_since(start).as_millis() as u64);
+            let mut push = |action| timeline.push(TimelineEntry { t_ms, action });
+            push(ReplayAction::TimedOut { window, cell });
+            refill(&mut engine, cell, &mut staged, options, now, push);
+        }
+
+        let mut push = |action| timeline.push(TimelineEntry { t_ms, action });
+        match *record {
+            SessionRecord::Monitors { .. } => {}
+            SessionRecord::Window {
+                hwnd,
+                ref class,
+                ref title,
+                ..
+            } => {
+                if !seen.insert(hwnd) {
+                    continue;
+                }
+                let facts = WindowFacts {
+                    class,
+                    title,
+                    ..Default::default()
+                };
+                let action =
+                    crate::rules::first_match(&options.rules, &facts).and_then(|rule| rule.action);
+                if matches!(action, Some(RuleAction::Ignore | RuleAction::Float)) {
+                    continue;
+                }
+                // The launch's first window goes to the parent cell when one is asked for
+                if seen.len() == 1
+                    && (options.reserve_parent_cell || options.assign_parent_cell.is_some())
+                {
+                    let CellSpec { region, monitor } =
+                        options.assign_parent_cell.unwrap_or_default();
+                    if let Some(idx) = engine.pool.index(monitor, region.row, region.col) {
+                        engine.reserve(engine.pool.region_cells(monitor, &region));
+                        engine.protect(Some(hwnd));
+                        engine.assign(idx, hwnd, now);
+                        push(ReplayAction::Placed {
+                            window: hwnd,
+                            cell: idx,
+                        });
+                        continue;
+                    }
+                }
+                if let Some(RuleAction::Cell(spec)) = action
+                    && let Some(cell) = make_room_in(&mut engine, spec, hwnd, now, &mut push)
+                {
+                    start_rule_timer(&mut engine, options, &facts, hwnd, now);
+                    push(ReplayAction::Placed { window: hwnd, cell });
+                    continue;
+                }
+                let cell = match engine.next_placement() {
+                    Placement::Cell(cell) => cell,
+                    Placement::Evict { cell, window } => {
+                        push(ReplayAction::Evicted { window, cell });
+                        cell
+                    }
+                    Placement::Minimize { cell, window } => {
+                        push(ReplayAction::Minimized { window, cell });
+                        cell
+                    }
+                    Placement::Overflow {
+                        cell,
+                        window,
+                        monitor,
+                    } => {
+                        push(ReplayAction::Overflowed {
+                            window,
+                            cell,
+                            monitor,
+                        });
+                        cell
+                    }
+                    Placement::Stack { cell, depth } => {
+                        engine.stack(cell, hwnd, now);
+                        push(ReplayAction::Stacked {
+                            window: hwnd,
+                            cell,
+                            depth,
+                        });
+                        continue;
+                    }
+                    Placement::Grown { cell } => {
+                        let layout = &engine.pool.primary().layout;
+                        push(ReplayAction::Grown {
+                            rows: layout.rows,
+                            cols: layout.cols,
+                        });
+                        cell
+                    }
+                    Placement::Full => {
+                        staged.push_back(hwnd);
+                        push(ReplayAction::Staged { window: hwnd });
+                        continue;
+                    }
+                };
+                // Sequential placement wraps around onto occupied cells
+                if let Some(previous) = engine.assign(cell, hwnd, now) {
+                    push(ReplayAction::Evicted {
+                        window: previous,
+                        cell,
+                    });
+                }
+                start_rule_timer(&mut engine, options, &facts, hwnd, now);
+                push(ReplayAction::Placed { window: hwnd, cell });
+            }
+            SessionRecord::Destroyed { hwnd, .. } => {
+                staged.retain(|&w| w != hwnd);
+                let Some((cell, lifetime)) = engine.window_closed(hwnd, now) else {
+                    push(ReplayAction::Closed { window: hwnd });
+                    continue;
+                };
+                if !engine.is_free(cell) {
+                    push(ReplayAction::Unstacked { window: hwnd, cell });
+                    continue;
+                }
+                push(ReplayAction::Freed {
+                    window: hwnd,
+                    cell,
+                    lifetime,
+                });
+                refill(&mut engine, cell, &mut staged, options, now, push);
+            }
+        }
+    }
+    Some(Replay { engine, timeline })
+}
+
+impl Replay {
+    /// The timeline, one line per action, followed by the final occupancy. Windows are
+    /// described from `records`.
+    pub fn render(&self, records: &[SessionRecord]) -> String {
+        let windows: HashMap<WindowId, (u32, &str, &str)> = records
+            .iter()
+            .filter_map(|r| match r {
+                SessionRecord::Window {
+                    hwnd,
+                    pid,
+                    class,
+                    title,
+                    ..
+                } => Some((*hwnd, (*pid, class.as_str(), title.as_str()))),
+                _ => None,
+            })
+            .collect();
+        let describe = |window: WindowId| match windows.get(&window) {
+            Some((pid, class, title)) => {
+                format!("{:#x} (PID {}, {} {:?})", window, pid, class, title)
+            }
+            None => format!("{:#x}", window),
+        };
+
+        let mut out = String::new();
+        for entry in &self.timeline {
+            let seconds = entry.t_ms as f64 / 1000.0;
+            let line = match entry.action {
+                ReplayAction::Placed { window, cell } => {
+                    format!("placed     cell {:>3}  {}", cell, describe(window))
+                }
+                ReplayAction::Evicted { window, cell } => {
+                    format!("evicted    cell {:>3}  {}", cell, describe(window))
+                }
+                ReplayAction::TimedOut { window, cell } => {
+                    format!("timed out  cell {:>3}  {}", cell, describe(window))
+                }
+                ReplayAction::Minimized { window, cell } => {
+                    format!("minimized  cell {:>3}  {}", cell, describe(window))
+                }
+                ReplayAction::Overflowed {
+                    window,
+                    cell,
+                    monitor,
+                } => format!(
+                    "overflowed cell {:>3}  {} to monitor {}",
+                    cell,
+                    describe(window),
+                    monitor
+                ),
+                ReplayAction::Stacked {
+                    window,
+                    cell,
+                    depth,
+                } => format!(
+                    "stacked    cell {:>3}  {} at depth {}",
+                    cell,
+                    describe(window),
+                    depth
+                ),
+                ReplayAction::Grown { rows, cols } => format!("grown      grid {}x{}", rows, cols),
+                ReplayAction::Shrunk { rows, cols } => format!("shrunk     grid {}x{}", rows, cols),
+                ReplayAction::Staged { window } => {
+                    format!("staged     grid full  {}", describe(window))
+                }
+                ReplayAction::Freed {
+                    window,
+                    cell,
+                    lifetime,
+                } => format!(
+                    "freed      cell {:>3}  {} after {:.3}s",
+                    cell,
+                    describe(window),
+                    lifetime.as_secs_f64()
+                ),
+                ReplayAction::Closed { window } => {
+                    format!("closed     no cell    {}", describe(window))
+                }
+                ReplayAction::Unstacked { window, cell } => {
+                    format!("unstacked  cell {:>3}  {}", cell, describe(window))
+                }
+                ReplayAction::Backfilled { window, cell } => {
+                    format!("backfill   cell {:>3}  {}", cell, describe(window))
+                }
+                ReplayAction::Compacted { window, from, to } => format!(
+                    "compacted  cell {:>3}  {} from cell {}",
+                    to,
+                    describe(window),
+                    from
+                ),
+            };
+            let _ = writeln!(out, "{:>9.3}s  {}", seconds, line);
+        }
+        let _ = writeln!(out, "Final cells:");
+        for (cell, contents) in self.engine.cells().iter().enumerate() {
+            if let Some(window) = contents.window {
+                let _ = writeln!(out, "  {:>3}  {}", cell, describe(window));
+            }
+            for &(window, _) in &contents.stacked {
+                let _ = writeln!(out, "       + {}", describe(window));
+            }
+        }
+        out
+    }
+}
+
+/// Runs `startt replay` with the arguments after `replay`; returns the exit code.
+pub fn run(args: Vec<OsString>) -> i32 {
+    let mut args = args.into_iter();
+    let Some(session) = args.next() else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+    let options = match crate::cli::parse_args(args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("startt: {}", e);
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    };
+    let session = Path::new(&session);
+    let records = match crate::record::load(session) {
+        Ok(records) => records,
+        Err(e) => {
+            eprintln!("startt: cannot read {}: {}", session.display(), e);
+            return 1;
+        }
+    };
+    let Some(replay) = replay(&records, &options) else {
+        eprintln!("startt: replay needs a grid (-g ROWSxCOLS)");
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+    print!("{}", replay.render(&records));
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Event;
+    use crate::fake::{FakeClock, FakeProcess, FakeProcessTree, FakeWindow, FakeWindowSystem};
+    use crate::follow::{Clock, Follower, POLL_INTERVAL};
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    /// Where each window was put, in order: the cell it was placed, back-filled or
+    /// compacted into.
+    fn replayed_moves(replay: &Replay) -> Vec<(WindowId, usize)> {
+        replay
+            .timeline
+            .iter()
+            .filter_map(|entry| match entry.action {
+                ReplayAction::Placed { window, cell }
+                | ReplayAction::Backfilled { window, cell } => Some((window, cell)),
+                ReplayAction::Compacted { window, to, .. } => Some((window, to)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// A live follow of windows titled `titles`, opened 200ms apart, with `-g 2x2`
+    /// and `args`, recorded as `--record` would, written to a session file and
+    /// loaded back. Checks that replaying it gives the live cell assignments and final
+    /// occupancy, and returns the records and the replay.
+    fn record_and_replay(
+        args: &[&str],
+        titles: &[&str],
+        destroyed: &[(u64, usize)],
+    ) -> (Vec<SessionRecord>, Replay) {
+        let work_area = Rect::new(0, 0, 1920, 1040);
+        let clock = FakeClock::new();
+        let windows = FakeWindowSystem::new(clock.clone(), vec![work_area]);
+        let processes = FakeProcessTree::new(clock.clone());
+        processes.start_at(ms(0), FakeProcess::new(100, 1, "app"));
+        let created: Vec<WindowId> = titles
+            .iter()
+            .enumerate()
+            .map(|(i, title)| {
+                let window = FakeWindow::new(100, "App", title, Rect::new(10, 10, 410, 310));
+                windows.create_at(ms(300 + 200 * i as u64), window)
+            })
+            .collect();
+        for &(at, i) in destroyed {
+            windows.destroy_at(ms(at), created[i]);
+        }
+        let options =
+            crate::cli::parse_args(["-f", "-g", "2x2"].iter().chain(args).chain(&["app"])).unwrap();
+
+        // Live, recording the session as `--record` would
+        let mut follower = Follower::new(&windows, &processes, options.clone());
+        let (tx, rx) = crossbeam_channel::unbounded();
+        follower.send_events(tx);
+        follower.track(100);
+        let mut records = vec![SessionRecord::Monitors {
+            work_areas: vec![WorkArea {
+                monitor: 0,
+                bounds: work_area,
+            }],
+        }];
+        let mut live_moves = Vec::new();
+        while clock.elapsed() < Duration::from_secs(8) {
+            follower.poll(clock.now());
+            let t_ms = clock.elapsed().as_millis() as u64;
+            for event in rx.try_iter() {
+                if let Event::CellAssigned { hwnd, cell, .. } = event {
+                    live_moves.push((hwnd, cell));
+                }
+                records.extend(crate::record::session_record(&event, t_ms));
+            }
+            clock.sleep(POLL_INTERVAL);
+        }
+
+        // Through a session file and back
+        let path = std::env::temp_dir().join(format!(
+            "startt-replay-{}-{}.jsonl",
+            std::process::id(),
+            titles.len()
+        ));
+        let text: String = records
+            .iter()
+            .map(|record| serde_json::to_string(record).unwrap() + "\n")
+            .collect();
+        std::fs::write(&path, text).unwrap();
+        let loaded = crate::record::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded, records);
+
+        let replay = replay(&loaded, &options).unwrap();
+        assert_eq!(replayed_moves(&replay), live_moves);
+        assert_eq!(
+            replay.engine.occupancy(),
+            follower.engine().unwrap().occupancy()
+        );
+        (loaded, replay)
+    }
+
+    fn any_action(replay: &Replay, f: impl Fn(&ReplayAction) -> bool) -> bool {
+        replay.timeline.iter().any(|e| f(&e.action))
+    }
+
+    #[test]
+    fn recorded_session_replays_to_the_same_placements() {
+        let titles = ["w0", "w1", "w2", "w3", "w4", "w5"];
+        let (_, replay) = record_and_replay(
+            &["-t", "3", "--on-full", "queue"],
+            &titles,
+            &[(2000, 1), (2600, 3)],
+        );
+        // The scenario exercised staging, back-filling and timeouts
+        assert!(any_action(&replay, |a| matches!(
+            a,
+            ReplayAction::Staged { .. }
+        )));
+        assert!(any_action(&replay, |a| matches!(
+            a,
+            ReplayAction::Backfilled { .. }
+        )));
+        assert!(any_action(&replay, |a| matches!(
+            a,
+            ReplayAction::TimedOut { .. }
+        )));
+
+        // Sequential placement without a parent cell, a floating and a pinned window
+        let titles = ["w0", "w1", "tool", "pinned", "w4"];
+        let (records, replay) = record_and_replay(
+            &[
+                "--grid-placement",
+                "sequential",
+                "--rule",
+                "title~=^tool => float",
+                "--rule",
+                "title=pinned => 1x1",
+            ],
+            &titles,
+            &[],
+        );
+        let hwnd_of = |title: &str| {
+            records.iter().find_map(|r| match r {
+                SessionRecord::Window { hwnd, title: t, .. } if t == title => Some(*hwnd),
+                _ => None,
+            })
+        };
+        let cells: Vec<Option<usize>> = ["w0", "w1", "tool", "pinned", "w4"]
+            .into_iter()
+            .map(|title| replay.engine.cell_of(hwnd_of(title).unwrap()))
+            .collect();
+        assert_eq!(cells, [Some(0), Some(1), None, Some(3), Some(2)]);
+        assert!(!any_action(&replay, |a| matches!(
+            a,
+            ReplayAction::Evicted { .. }
+        )));
+    }
+}
@@ -0,0 +1,283 @@
+// src/resolve.rs
+//! `--resolve`: report what a launch turned into (root PID, descendant PIDs and
+//! top-level windows) and exit, without touching any window.
+//!
+//! Exit codes: 0 when at least one window was found, [`EXIT_NOT_FOUND`] when none
+//! turned up within `--wait` seconds, 2 for usage errors.
+use crate::cli::CommandLineOptions;
+use crate::grid::{Rect, WindowId};
+use crate::launcher::Launcher;
+use crate::process_tree::ProcessInfo;
+use crate::window_system::WindowInfo;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::time::Duration;
+
+/// Exit code when no window appeared within `--wait` seconds.
+pub const EXIT_NOT_FOUND: i32 = 3;
+
+/// Output format of `--resolve` (`--format`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResolveFormat {
+    /// One JSON object.
+    #[default]
+    Json,
+    /// Tab-separated records: `root`, `process` and `window` lines.
+    Tsv,
+}
+
+impl std::str::FromStr for ResolveFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(ResolveFormat::Json),
+            "tsv" => Ok(ResolveFormat::Tsv),
+            _ => Err("json or tsv"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResolvedProcess {
+    pub pid: u32,
+    pub parent_pid: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResolvedWindow {
+    pub hwnd: WindowId,
+    pub pid: u32,
+    pub class: String,
+    pub title: String,
+    pub bounds: Option<Rect>,
+}
+
+/// Everything `--resolve` found for one launch.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct Resolution {
+    /// PID returned by ShellExecuteEx; 0 if the launch was handed to an existing process.
+    pub root_pid: u32,
+    /// The root's descendants plus the owners of the windows found, root excluded.
+    pub processes: Vec<ResolvedProcess>,
+    pub windows: Vec<ResolvedWindow>,
+}
+
+/// Tabs and line breaks would break a TSV record.
+fn tsv_field(s: &str) -> String {
+    s.replace(['\t', '\r', '\n'], " ")
+}
+
+fn tsv_option<T: std::fmt::Display>(value: Option<T>) -> String {
+    value.map_or_else(String::new, |v| v.to_string())
+}
+
+impl Resolution {
+    /// The resolution of a launch rooted at `root_pid`. Parent PIDs come from
+    /// `processes`, as they were last seen.
+    pub fn new(
+        root_pid: u32,
+        descendants: Vec<u32>,
+        processes: &[ProcessInfo],
+        windows: Vec<WindowInfo>,
+    ) -> Self {
+        let parents: HashMap<u32, u32> = processes
+            .iter()
+            .map(|process| (process.pid, process.ppid))
+            .collect();
+        Resolution {
+            root_pid,
+            processes: descendants
+                .into_iter()
+                .map(|pid| ResolvedProcess {
+                    pid,
+                    parent_pid: parents.get(&pid).copied(),
+                })
+                .collect(),
+            windows: windows
+                .into_iter()
+                .map(|window| ResolvedWindow {
+                    hwnd: window.handle,
+                    pid: window.pid,
+                    class: window.class,
+                    title: window.title,
+                    bounds: Some(window.bounds),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn is_found(&self) -> bool {
+        !self.windows.is_empty()
+    }
+
+    /// 0 when a window was found, [`EXIT_NOT_FOUND`] otherwise.
+    pub fn exit_code(&self) -> i32 {
+        if self.is_found() { 0 } else { EXIT_NOT_FOUND }
+    }
+
+    /// The resolution as `format`, ending in a newline.
+    pub fn render(&self, format: ResolveFormat) -> String {
+        match format {
+            ResolveFormat::Json => {
+                serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string()) + "\n"
+            }
+            ResolveFormat::Tsv => {
+                let mut out = format!("root\t{}\n", self.root_pid);
+                for process in &self.processes {
+                    out += &format!(
+                        "process\t{}\t{}\n",
+                        process.pid,
+                        tsv_option(process.parent_pid)
+                    );
+                }
+                for window in &self.windows {
+                    let bounds = window.bounds.map_or_else(
+                        || "\t\t\t".to_string(),
+                        |b| format!("{}\t{}\t{}\t{}", b.left, b.top, b.right, b.bottom),
+                    );
+                    out += &format!(
+                        "window\t{}\t{}\t{}\t{}\t{}\n",
+                        window.hwnd,
+                        window.pid,
+                        tsv_field(&window.class),
+                        tsv_field(&window.title),
+                        bounds
+                    );
+                }
+                out
+            }
+        }
+    }
+}
+
+/// Launches `options.command` and reports what it turned into once a window of the launch
+/// shows up, or after `--wait` seconds without one. The launch is followed without
+/// grids or window effects, so nothing is moved, and it is left running.
+pub fn resolve(mut options: CommandLineOptions) -> io::Result<Resolution> {
+    options.resolve = true;
+    options.grids.clear();
+    options.follow_children = false;
+    options.follow_forever = false;
+    options.should_hide_title_bar = false;
+    options.should_hide_border = false;
+    options.flash_topmost_ms = 0;
+    let wait = Duration::from_secs(options.wait_secs);
+    let app = Launcher::from_options(options).spawn()?;
+    app.wait_for_window(wait);
+    Ok(Resolution::new(
+        app.root_pid(),
+        app.descendants(),
+        &app.processes(),
+        app.window_infos(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::{FakeClock, FakeProcess, FakeProcessTree, FakeWindow, FakeWindowSystem};
+    use crate::ps::ProcessInspector;
+    use crate::window_system::WindowSystem;
+
+    const MONITOR: Rect = Rect {
+        left: 0,
+        top: 0,
+        right: 1920,
+        bottom: 1080,
+    };
+
+    /// Root 100 with a child 101 and a grandchild 102; 102 has a window whose title
+    /// holds a tab, if `with_window`.
+    fn fake_resolution(with_window: bool) -> Resolution {
+        let clock = FakeClock::new();
+        let processes = FakeProcessTree::new(clock.clone());
+        let windows = FakeWindowSystem::new(clock.clone(), vec![MONITOR]);
+        processes.start_at(Duration::ZERO, FakeProcess::new(100, 1, "cmd.exe"));
+        processes.start_at(Duration::ZERO, FakeProcess::new(101, 100, "cargo-e.exe"));
+        processes.start_at(Duration::ZERO, FakeProcess::new(102, 101, "app.exe"));
+        let bounds = Rect {
+            left: 0,
+            top: 0,
+            right: 960,
+            bottom: 540,
+        };
+        let window = with_window.then(|| {
+            windows.create_at(
+                Duration::ZERO,
+                FakeWindow::new(102, "AppWindow", "app\tmain", bounds),
+            )
+        });
+        let tree = processes.tree();
+        let infos = window
+            .and_then(|window| windows.info(window))
+            .into_iter()
+            .collect();
+        let processes: Vec<ProcessInfo> = tree.iter().cloned().collect();
+        Resolution::new(100, tree.descendants(100), &processes, infos)
+    }
+
+    #[test]
+    fn json_output_is_one_well_formed_object() {
+        let rendered = fake_resolution(true).render(ResolveFormat::Json);
+        assert!(rendered.ends_with('\n'));
+        assert_eq!(rendered.lines().count(), 1);
+        let json: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+        assert_eq!(json["root_pid"], 100);
+        let mut processes: Vec<(u64, u64)> = json["processes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| {
+                (
+                    p["pid"].as_u64().unwrap(),
+                    p["parent_pid"].as_u64().unwrap(),
+                )
+            })
+            .collect();
+        processes.sort_unstable();
+        assert_eq!(processes, [(101, 100), (102, 101)]);
+        let window = &json["windows"][0];
+        assert_eq!(window["pid"], 102);
+        assert_eq!(window["class"], "AppWindow");
+        assert_eq!(window["title"], "app\tmain");
+        assert_eq!(window["bounds"]["right"], 960);
+    }
+
+    #[test]
+    fn tsv_output_has_one_record_per_line_with_fixed_columns() {
+        let rendered = fake_resolution(true).render(ResolveFormat::Tsv);
+        assert!(rendered.ends_with('\n'));
+        let records: Vec<Vec<&str>> = rendered.lines().map(|l| l.split('\t').collect()).collect();
+        assert_eq!(records[0], ["root", "100"]);
+        let mut processes: Vec<&Vec<&str>> = records.iter().filter(|r| r[0] == "process").collect();
+        processes.sort();
+        assert_eq!(processes.len(), 2);
+        assert_eq!(*processes[0], ["process", "101", "100"]);
+        assert_eq!(*processes[1], ["process", "102", "101"]);
+        let windows: Vec<&Vec<&str>> = records.iter().filter(|r| r[0] == "window").collect();
+        assert_eq!(windows.len(), 1);
+        // The tab in the title must not add a column
+        assert_eq!(
+            windows[0][2..],
+            ["102", "AppWindow", "app main", "0", "0", "960", "540"]
+        );
+        assert_eq!(records.len(), 4);
+    }
+
+    #[test]
+    fn finding_no_window_exits_with_not_found() {
+        let resolution = fake_resolution(false);
+        assert!(!resolution.is_found());
+        assert_eq!(resolution.exit_code(), EXIT_NOT_FOUND);
+        // Still well-formed, with the processes that were found
+        let json: serde_json::Value =
+            serde_json::from_str(&resolution.render(ResolveFormat::Json)).unwrap();
+        assert_eq!(json["windows"].as_array().unwrap().len(), 0);
+        assert_eq!(json["processes"].as_array().unwrap().len(), 2);
+        assert_eq!(resolution.render(ResolveFormat::Tsv).lines().count(), 3);
+        assert_eq!(fake_resolution(true).exit_code(), 0);
+    }
+}
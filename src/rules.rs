@@ -0,0 +1,414 @@
+// src/rules.rs
+//! Window-matching rules that route specific windows to specific cells.
+//!
+//! A rule is one or more conditions joined by `&&`, then `=>` and an action:
+//!
+//! ```text
+//! title~=panic => 0x0
+//! class=ConsoleWindowClass && cmdline~=--example\s+ui => 1x0+1x2m1
+//! image=cargo-e.exe => float
+//! title~=^Untitled => ignore
+//! ```
+//!
+//! Fields are `class`, `title`, `image` (the executable's file name) and `cmdline`.
+//! `=` compares the whole value, `~=` searches with a regex; both ignore case. A value
+//! may be put in single or double quotes, inside which `&&` and `=>` are part of it
+//! (`title="Tom && Jerry" => float`); quotes must pair up anywhere in a rule, so a
+//! value with an apostrophe goes in double quotes. The
+//! action is a cell or cell range in `-apc` syntax (`ROWxCOL[+ROWSxCOLS][mMONITOR]`),
+//! `ignore` (never touch the window) or `float` (apply effects but leave it out of
+//! the grid). Rules are tried in order and the first match wins.
+//!
+//! A rule may also set the window's timeout, after the action or instead of one:
+//!
+//! ```text
+//! title~=cargo test => 1x0 timeout=30
+//! title~=panic => extend=300
+//! title~=debugger => timeout=off
+//! ```
+//!
+//! `timeout=SECS` replaces the `--timeout` deadline, `extend=SECS` pushes it back and
+//! `timeout=off` pauses it for as long as the rule keeps matching. Timers are looked
+//! at again whenever a window's title changes, so a rule on the title can react to what
+//! the window is doing.
+use crate::cli::CellSpec;
+use regex::{Regex, RegexBuilder};
+use std::time::Duration;
+
+/// Window property a condition looks at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleField {
+    Class,
+    Title,
+    /// File name of the owning process's executable.
+    Image,
+    /// Command line of the owning process.
+    Cmdline,
+}
+
+impl std::str::FromStr for RuleField {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "class" => Ok(RuleField::Class),
+            "title" => Ok(RuleField::Title),
+            "image" | "exe" => Ok(RuleField::Image),
+            "cmdline" | "cmd" => Ok(RuleField::Cmdline),
+            _ => Err("class, title, image or cmdline"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum RuleMatch {
+    Exact(String),
+    Regex(Regex),
+}
+
+impl RuleMatch {
+    pub fn is_match(&self, value: &str) -> bool {
+        match self {
+            RuleMatch::Exact(expected) => value.eq_ignore_ascii_case(expected),
+            RuleMatch::Regex(regex) => regex.is_match(value),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RuleCondition {
+    pub field: RuleField,
+    pub matcher: RuleMatch,
+}
+
+/// What happens to a window that matches a rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleAction {
+    /// Place the window in the first usable cell of this cell or range.
+    Cell(CellSpec),
+    /// Leave the window completely alone.
+    Ignore,
+    /// Apply the window effects but keep it out of the grid.
+    Float,
+}
+
+/// How a rule changes a window's timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleTimer {
+    /// `timeout=SECS`: the deadline is SECS after the rule matched.
+    After(Duration),
+    /// `extend=SECS`: the deadline moves SECS later, once per match.
+    Extend(Duration),
+    /// `timeout=off`: the timer is paused while the rule matches.
+    Off,
+}
+
+impl std::str::FromStr for RuleTimer {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const EXPECTED: &str = "timeout=SECS, timeout=off or extend=SECS";
+        let (key, value) = s.split_once('=').ok_or(EXPECTED)?;
+        let seconds = || value.parse().map(Duration::from_secs).map_err(|_| EXPECTED);
+        match key.to_ascii_lowercase().as_str() {
+            "timeout" if value.eq_ignore_ascii_case("off") => Ok(RuleTimer::Off),
+            "timeout" => seconds().map(RuleTimer::After),
+            "extend" => seconds().map(RuleTimer::Extend),
+            _ => Err(EXPECTED),
+        }
+    }
+}
+
+/// The window properties rules are matched against. `cmdline` is only looked up
+/// when a rule needs it (see [`WindowRule::needs_cmdline`]).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WindowFacts<'a> {
+    pub class: &'a str,
+    pub title: &'a str,
+    pub image: &'a str,
+    pub cmdline: Option<&'a str>,
+}
+
+impl WindowFacts<'_> {
+    fn get(&self, field: RuleField) -> &str {
+        match field {
+            RuleField::Class => self.class,
+            RuleField::Title => self.title,
+            RuleField::Image => self.image,
+            RuleField::Cmdline => self.cmdline.unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WindowRule {
+    pub conditions: Vec<RuleCondition>,
+    /// `None` for a rule that only sets a timer.
+    pub action: Option<RuleAction>,
+    pub timer: Option<RuleTimer>,
+    /// The rule as written, for display and `--print-profile`.
+    source: String,
+}
+
+impl WindowRule {
+    pub fn matches(&self, facts: &WindowFacts) -> bool {
+        self.conditions
+            .iter()
+            .all(|c| c.matcher.is_match(facts.get(c.field)))
+    }
+
+    pub fn needs_cmdline(&self) -> bool {
+        self.conditions
+            .iter()
+            .any(|c| c.field == RuleField::Cmdline)
+    }
+}
+
+/// The first rule with an action matching `facts`, if any.
+pub fn first_match<'r>(rules: &'r [WindowRule], facts: &WindowFacts) -> Option<&'r WindowRule> {
+    rules
+        .iter()
+        .find(|rule| rule.action.is_some() && rule.matches(facts))
+}
+
+/// The first rule with a timer matching `facts`, with its position in `rules`.
+pub fn first_timer_match<'r>(
+    rules: &'r [WindowRule],
+    facts: &WindowFacts,
+) -> Option<(usize, &'r WindowRule)> {
+    rules
+        .iter()
+        .enumerate()
+        .find(|(_, rule)| rule.timer.is_some() && rule.matches(facts))
+}
+
+const RULE_EXPECTED: &str = "FIELD=VALUE or FIELD~=REGEX [&& ...] => ROWxCOL[+ROWSxCOLS][mN]|ignore|float [timeout=SECS|off|extend=SECS]";
+
+fn unquote(s: &str) -> &str {
+    let s = s.trim();
+    for quote in ['"', '\''] {
+        if let Some(inner) = s.strip_prefix(quote).and_then(|s| s.strip_suffix(quote)) {
+            return inner;
+        }
+    }
+    s
+}
+
+/// Byte offsets of every `separator` in `s` that isn't inside single or double quotes.
+fn find_outside_quotes(s: &str, separator: &str) -> Result<Vec<usize>, &'static str> {
+    let mut found = Vec::new();
+    let mut quote = None;
+    let mut chars = s.char_indices();
+    while let Some((idx, c)) = chars.next() {
+        match quote {
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if s[idx..].starts_with(separator) => {
+                found.push(idx);
+                // Skip the rest of the separator
+                chars.nth(separator.len() - 2);
+            }
+            None => {}
+        }
+    }
+    match quote {
+        Some(_) => Err("a closing quote in the rule"),
+        None => Ok(found),
+    }
+}
+
+fn parse_condition(s: &str) -> Result<RuleCondition, &'static str> {
+    // The field is a plain word, so the first `=` ends it
+    let (field, value) = s.split_once('=').ok_or(RULE_EXPECTED)?;
+    let (field, is_regex) = match field.strip_suffix('~') {
+        Some(field) => (field, true),
+        None => (field, false),
+    };
+    let field = field.trim().parse()?;
+    let value = unquote(value);
+    let matcher = if is_regex {
+        RegexBuilder::new(value)
+            .case_insensitive(true)
+            .build()
+            .map(RuleMatch::Regex)
+            .map_err(|_| "a valid regular expression after ~=")?
+    } else {
+        RuleMatch::Exact(value.to_string())
+    };
+    Ok(RuleCondition { field, matcher })
+}
+
+impl std::str::FromStr for WindowRule {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let arrow = *find_outside_quotes(s, "=>")?.last().ok_or(RULE_EXPECTED)?;
+        let (conditions, action) = (&s[..arrow], &s[arrow + 2..]);
+        // A timer comes last, after the action or on its own
+        let action = unquote(action).to_ascii_lowercase();
+        let (action, timer) = match action.rsplit_once(char::is_whitespace) {
+            Some((rest, last)) if last.starts_with("timeout=") || last.starts_with("extend=") => {
+                (rest.trim(), Some(last.parse()?))
+            }
+            _ if action.starts_with("timeout=") || action.starts_with("extend=") => {
+                ("", Some(action.parse()?))
+            }
+            _ => (action.trim(), None),
+        };
+        let action = match action {
+            "" if timer.is_some() => None,
+            "ignore" => Some(RuleAction::Ignore),
+            "float" | "floating" => Some(RuleAction::Float),
+            cell => Some(RuleAction::Cell(cell.parse()?)),
+        };
+        let mut start = 0;
+        let mut parts = Vec::new();
+        for and in find_outside_quotes(conditions, "&&")? {
+            parts.push(&conditions[start..and]);
+            start = and + 2;
+        }
+        parts.push(&conditions[start..]);
+        let conditions = parts
+            .into_iter()
+            .map(parse_condition)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(WindowRule {
+            conditions,
+            action,
+            timer,
+            source: s.trim().to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for WindowRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::CellRegion;
+
+    fn rule(s: &str) -> WindowRule {
+        s.parse().unwrap_or_else(|e| panic!("{}: {}", s, e))
+    }
+
+    fn facts<'a>(class: &'a str, title: &'a str) -> WindowFacts<'a> {
+        WindowFacts {
+            class,
+            title,
+            image: "app.exe",
+            cmdline: Some("app.exe --example ui"),
+        }
+    }
+
+    #[test]
+    fn each_action_parses() {
+        let cell = |row, col, rows, cols, monitor| {
+            Some(RuleAction::Cell(CellSpec {
+                region: CellRegion {
+                    row,
+                    col,
+                    rows,
+                    cols,
+                },
+                monitor,
+            }))
+        };
+        let cases = [
+            ("title~=panic => 0x0", cell(0, 0, 1, 1, None)),
+            ("title~=panic => 1x0+1x2m1", cell(1, 0, 1, 2, Some(1))),
+            ("title~=panic =>1x2", cell(1, 2, 1, 1, None)),
+            ("title~=panic => ignore", Some(RuleAction::Ignore)),
+            ("title~=panic => IGNORE", Some(RuleAction::Ignore)),
+            ("title~=panic => float", Some(RuleAction::Float)),
+            ("title~=panic => floating", Some(RuleAction::Float)),
+            ("title~=panic => timeout=5", None),
+        ];
+        for (source, action) in cases {
+            let rule = rule(source);
+            assert_eq!(rule.action, action, "{}", source);
+            assert_eq!(rule.to_string(), source);
+        }
+        for bad in [
+            "title~=panic",
+            "title~=panic => ",
+            "title~=panic => 0x",
+            "nope",
+        ] {
+            assert!(bad.parse::<WindowRule>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn timers_come_after_the_action_or_instead_of_it() {
+        let secs = Duration::from_secs;
+        let cases = [
+            (
+                "title~=a => 1x0 timeout=30",
+                Some(RuleTimer::After(secs(30))),
+            ),
+            ("title~=a => float TIMEOUT=OFF", Some(RuleTimer::Off)),
+            ("title~=a => extend=300", Some(RuleTimer::Extend(secs(300)))),
+            ("title~=a => timeout=off", Some(RuleTimer::Off)),
+            ("title~=a => ignore", None),
+        ];
+        for (source, timer) in cases {
+            assert_eq!(rule(source).timer, timer, "{}", source);
+        }
+        assert!(rule("title~=a => 1x0 timeout=30").action.is_some());
+        for bad in [
+            "title~=a => 1x0 timeout=soon",
+            "title~=a => extend=off",
+            "title~=a => timeout=",
+        ] {
+            assert!(bad.parse::<WindowRule>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn conditions_compare_or_search_and_all_must_hold() {
+        let rule = rule("class=App && title~=^build\\s+\\d && image=APP.EXE => float");
+        assert_eq!(rule.conditions.len(), 3);
+        assert!(rule.matches(&facts("app", "Build 42")));
+        assert!(!rule.matches(&facts("AppX", "Build 42")));
+        assert!(!rule.matches(&facts("App", "rebuild 42")));
+        assert!(!rule.needs_cmdline());
+
+        let rule = self::rule("cmd~=--example\\s+ui => 0x0");
+        assert_eq!(rule.conditions[0].field, RuleField::Cmdline);
+        assert!(rule.needs_cmdline());
+        assert!(rule.matches(&facts("App", "")));
+        // A regex may contain `=`
+        let rule = self::rule("cmdline~=--example=?\\s*ui => 0x0");
+        assert!(rule.matches(&facts("App", "")));
+        assert!("colour=red => 0x0".parse::<WindowRule>().is_err());
+        assert!("title~=( => 0x0".parse::<WindowRule>().is_err());
+    }
+
+    #[test]
+    fn quoted_values_keep_separators() {
+        let rule = rule("title=\"Tom && Jerry\" && class='a=>b' => float");
+        assert_eq!(rule.conditions.len(), 2);
+        assert!(rule.matches(&facts("A=>B", "tom && jerry")));
+        assert!(!rule.matches(&facts("A=>B", "Tom")));
+
+        let rule = self::rule("title~=\"x => y\" => ignore");
+        assert_eq!(rule.action, Some(RuleAction::Ignore));
+        assert!(rule.matches(&facts("", "box => yard")));
+
+        // Quotes around the action and an apostrophe inside double quotes
+        let rule = self::rule("title=\"it's\" => '1x1'");
+        assert!(rule.matches(&facts("", "IT'S")));
+        assert!(matches!(rule.action, Some(RuleAction::Cell(_))));
+
+        assert_eq!(
+            "title=it's => float".parse::<WindowRule>().unwrap_err(),
+            "a closing quote in the rule"
+        );
+    }
+}
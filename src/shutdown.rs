@@ -0,0 +1,236 @@
+// src/shutdown.rs
+//! Staged shutdown of a launched process tree.
+//!
+//! [`shutdown`] first asks every process to close: `WM_CLOSE` to each of its visible
+//! top-level windows (console windows included, which sends `CTRL_CLOSE_EVENT` to every
+//! process of that console), or `SIGTERM` on Linux. It then waits up to the grace
+//! period for them to exit, and only terminates the survivors (`TerminateProcess` /
+//! `SIGKILL`), deepest descendants first so parents can't respawn them.
+//!
+//! Windows processes without a window get no close request. `CTRL_BREAK` only reaches
+//! a process through the id of a process group started with `CREATE_NEW_PROCESS_GROUP`,
+//! which ShellExecuteEx never does; any other id is taken as group 0 and would break
+//! startt's own console.
+use crate::events::{self, Event};
+use crate::grid::WindowId;
+use crate::process_tree::ProcessTree;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+/// How often survivors are checked for while waiting out the grace period.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Outcome of a [`shutdown`], by PID.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// Exited within the grace period.
+    pub closed: Vec<u32>,
+    /// Still running after the grace period and terminated.
+    pub force_killed: Vec<u32>,
+    /// Still running and could not be terminated (e.g. access denied).
+    pub failed: Vec<u32>,
+}
+
+impl ShutdownReport {
+    pub fn print(&self) {
+        println!("Closed PIDs: {:?}", self.closed);
+        if !self.force_killed.is_empty() {
+            println!("Force-killed PIDs: {:?}", self.force_killed);
+        }
+        if !self.failed.is_empty() {
+            println!("Failed to terminate PIDs: {:?}", self.failed);
+        }
+    }
+}
+
+/// Number of ancestors of `pid` that are themselves in `pids`.
+fn depth(tree: &ProcessTree, pids: &HashSet<u32>, pid: u32) -> usize {
+    let mut depth = 0;
+    let mut current = pid;
+    while let Some(parent) = tree.parent_of(current) {
+        if !pids.contains(&parent) || parent == pid || depth > pids.len() {
+            break;
+        }
+        depth += 1;
+        current = parent;
+    }
+    depth
+}
+
+/// Shuts down `roots` and all their descendants, plus `windows` (e.g. a launch that was
+/// handed to a process outside the tree). Blocks for at most about `grace`.
+pub fn shutdown(roots: &[u32], windows: &[WindowId], grace: Duration) -> ShutdownReport {
+    let own_pid = std::process::id();
+    let tree = ProcessTree::snapshot();
+    let mut pids: HashSet<u32> = HashSet::new();
+    for &root in roots {
+        if tree.contains(root) {
+            pids.insert(root);
+        }
+        pids.extend(tree.descendants(root));
+    }
+    pids.retain(|&pid| pid != 0 && pid != own_pid);
+
+    platform::request_close(&pids, windows);
+
+    let deadline = Instant::now() + grace;
+    let mut survivors = pids.clone();
+    loop {
+        let exited = tree.diff(&ProcessTree::snapshot()).exited;
+        survivors.retain(|pid| !exited.contains(pid));
+        if survivors.is_empty() || Instant::now() >= deadline {
+            break;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+
+    let mut report = ShutdownReport {
+        closed: pids
+            .iter()
+            .copied()
+            .filter(|pid| !survivors.contains(pid))
+            .collect(),
+        ..Default::default()
+    };
+    report.closed.sort_unstable();
+
+    let mut survivors: Vec<u32> = survivors.into_iter().collect();
+    survivors.sort_by_key(|&pid| std::cmp::Reverse((depth(&tree, &pids, pid), pid)));
+    for pid in survivors {
+        if platform::terminate(pid) {
+            report.force_killed.push(pid);
+        } else {
+            report.failed.push(pid);
+        }
+    }
+    events::emit(Event::Shutdown {
+        closed: report.closed.clone(),
+        force_killed: report.force_killed.clone(),
+        failed: report.failed.clone(),
+    });
+    report
+}
+
+#[cfg(windows)]
+mod platform {
+    use crate::grid::WindowId;
+    use std::collections::HashSet;
+    use winapi::shared::minwindef::{BOOL, LPARAM, TRUE};
+    use winapi::shared::windef::HWND;
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::{OpenProcess, TerminateProcess};
+    use winapi::um::winnt::PROCESS_TERMINATE;
+    use winapi::um::winuser::{
+        EnumWindows, GetWindowThreadProcessId, IsWindowVisible, PostMessageW, WM_CLOSE,
+    };
+
+    struct EnumData<'a> {
+        pids: &'a HashSet<u32>,
+        windows: Vec<(HWND, u32)>,
+    }
+
+    extern "system" fn enum_windows_proc(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let data = unsafe { &mut *(lparam as *mut EnumData) };
+        let mut pid = 0;
+        unsafe {
+            GetWindowThreadProcessId(hwnd, &mut pid);
+            if data.pids.contains(&pid) && IsWindowVisible(hwnd) != 0 {
+                data.windows.push((hwnd, pid));
+            }
+        }
+        TRUE
+    }
+
+    pub fn request_close(pids: &HashSet<u32>, extra_windows: &[WindowId]) {
+        let mut data = EnumData {
+            pids,
+            windows: Vec::new(),
+        };
+        unsafe {
+            EnumWindows(Some(enum_windows_proc), &mut data as *mut _ as LPARAM);
+        }
+        for &(hwnd, pid) in &data.windows {
+            println!("Sending WM_CLOSE to HWND {:?} (PID {})", hwnd, pid);
+            unsafe { PostMessageW(hwnd, WM_CLOSE, 0, 0) };
+        }
+        for &hwnd in extra_windows {
+            if !data.windows.iter().any(|&(h, _)| h as WindowId == hwnd) {
+                println!("Sending WM_CLOSE to HWND {:?}", hwnd as HWND);
+                unsafe { PostMessageW(hwnd as HWND, WM_CLOSE, 0, 0) };
+            }
+        }
+    }
+
+    pub fn terminate(pid: u32) -> bool {
+        unsafe {
+            let handle = OpenProcess(PROCESS_TERMINATE, 0, pid);
+            if handle.is_null() {
+                return false;
+            }
+            let ok = TerminateProcess(handle, 1) != 0;
+            CloseHandle(handle);
+            ok
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use crate::grid::WindowId;
+    use std::collections::HashSet;
+
+    const SIGKILL: i32 = 9;
+    const SIGTERM: i32 = 15;
+
+    unsafe extern "C" {
+        fn kill(pid: i32, sig: i32) -> i32;
+    }
+
+    fn signal(pid: u32, sig: i32) -> bool {
+        // Never 0 or negative: those address whole process groups
+        i32::try_from(pid).is_ok_and(|pid| pid > 0 && unsafe { kill(pid, sig) } == 0)
+    }
+
+    pub fn request_close(pids: &HashSet<u32>, _windows: &[WindowId]) {
+        for &pid in pids {
+            signal(pid, SIGTERM);
+        }
+    }
+
+    pub fn terminate(pid: u32) -> bool {
+        signal(pid, SIGKILL)
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn processes_that_ignore_sigterm_are_killed_after_the_grace_period() {
+        let sh = |script| {
+            std::process::Command::new("sh")
+                .args(["-c", script])
+                .stderr(std::process::Stdio::null())
+                .spawn()
+                .unwrap()
+        };
+        let mut polite = sh("trap 'exit 0' TERM; while :; do sleep 0.1; done");
+        let mut stubborn = sh("trap '' TERM; while :; do sleep 0.1; done");
+        // Until both shells have set their traps
+        std::thread::sleep(Duration::from_millis(300));
+        let started = Instant::now();
+        let report = shutdown(
+            &[polite.id(), stubborn.id()],
+            &[],
+            Duration::from_millis(1000),
+        );
+        assert!(started.elapsed() >= Duration::from_millis(1000));
+        assert!(report.closed.contains(&polite.id()), "{:?}", report);
+        assert!(report.force_killed.contains(&stubborn.id()), "{:?}", report);
+        assert!(!report.force_killed.contains(&polite.id()), "{:?}", report);
+        assert!(report.failed.is_empty(), "{:?}", report);
+        assert!(polite.wait().unwrap().success());
+        assert!(!stubborn.wait().unwrap().success());
+    }
+}
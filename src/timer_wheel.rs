@@ -0,0 +1,364 @@
+// src/timer_wheel.rs
+//! Per-window deadlines on a hashed timer wheel.
+//!
+//! Time is cut into ticks; a deadline lands in the slot of the first tick at or after
+//! it (modulo the number of slots), and [`TimerWheel::expire`] only looks at the slots
+//! of the ticks that passed since the previous call. Pausing, extending, rescheduling
+//! or cancelling a timer bumps its generation instead of searching the slots; stale
+//! slot entries are dropped when their slot comes around. Timers never fire early and
+//! at most one tick late.
+use crate::grid::WindowId;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Slot width of [`TimerWheel::default`].
+pub const DEFAULT_TICK: Duration = Duration::from_millis(100);
+/// Slots of [`TimerWheel::default`]: one turn of the wheel is about 51 seconds.
+pub const DEFAULT_SLOTS: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerState {
+    Running {
+        deadline: Instant,
+    },
+    /// Stopped with `remaining` left; [`TimerWheel::resume`] restarts it from there.
+    Paused {
+        remaining: Duration,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Timer {
+    state: TimerState,
+    generation: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SlotEntry {
+    window: WindowId,
+    generation: u64,
+    tick: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct TimerWheel {
+    tick: Duration,
+    origin: Instant,
+    slots: Vec<Vec<SlotEntry>>,
+    /// First tick not yet handled by `expire`.
+    next_tick: u64,
+    timers: HashMap<WindowId, Timer>,
+    generation: u64,
+}
+
+impl Default for TimerWheel {
+    fn default() -> Self {
+        Self::new(DEFAULT_TICK, DEFAULT_SLOTS, Instant::now())
+    }
+}
+
+impl TimerWheel {
+    /// A wheel of `slots` slots, `tick` wide each, counting ticks from `origin`.
+    /// Panics if `tick` is zero or there are no slots.
+    pub fn new(tick: Duration, slots: usize, origin: Instant) -> Self {
+        assert!(!tick.is_zero(), "a timer wheel needs a non-zero tick");
+        assert!(slots > 0, "a timer wheel needs at least one slot");
+        Self {
+            tick,
+            origin,
+            slots: vec![Vec::new(); slots],
+            next_tick: 0,
+            timers: HashMap::new(),
+            generation: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.timers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timers.is_empty()
+    }
+
+    pub fn state(&self, window: WindowId) -> Option<TimerState> {
+        self.timers.get(&window).map(|t| t.state)
+    }
+
+    /// When `window`'s timer fires; `None` if it has none or it is paused.
+    pub fn deadline(&self, window: WindowId) -> Option<Instant> {
+        match self.state(window)? {
+            TimerState::Running { deadline } => Some(deadline),
+            TimerState::Paused { .. } => None,
+        }
+    }
+
+    /// Time left on `window`'s timer, paused or not.
+    pub fn remaining(&self, window: WindowId, now: Instant) -> Option<Duration> {
+        match self.state(window)? {
+            TimerState::Running { deadline } => Some(deadline.saturating_duration_since(now)),
+            TimerState::Paused { remaining } => Some(remaining),
+        }
+    }
+
+    /// The earliest running deadline.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.timers
+            .values()
+            .filter_map(|t| match t.state {
+                TimerState::Running { deadline } => Some(deadline),
+                TimerState::Paused { .. } => None,
+            })
+            .min()
+    }
+
+    /// First tick at or after `at`.
+    fn tick_at(&self, at: Instant) -> u64 {
+        let since = at.saturating_duration_since(self.origin).as_nanos();
+        let tick = self.tick.as_nanos();
+        since.div_ceil(tick) as u64
+    }
+
+    fn set(&mut self, window: WindowId, state: TimerState) {
+        self.generation += 1;
+        let generation = self.generation;
+        self.timers.insert(window, Timer { state, generation });
+        if let TimerState::Running { deadline } = state {
+            // Already overdue: picked up by the next `expire` of a later tick
+            let tick = self.tick_at(deadline).max(self.next_tick);
+            let slot = (tick % self.slots.len() as u64) as usize;
+            self.slots[slot].push(SlotEntry {
+                window,
+                generation,
+                tick,
+            });
+        }
+    }
+
+    /// Sets `window`'s timer to fire at `deadline`, replacing any timer it had.
+    pub fn schedule(&mut self, window: WindowId, deadline: Instant) {
+        self.set(window, TimerState::Running { deadline });
+    }
+
+    /// Removes `window`'s timer. Returns false if it had none.
+    pub fn cancel(&mut self, window: WindowId) -> bool {
+        self.timers.remove(&window).is_some()
+    }
+
+    /// Stops `window`'s timer with its remaining time kept. Returns false if it had no
+    /// running timer.
+    pub fn pause(&mut self, window: WindowId, now: Instant) -> bool {
+        let Some(deadline) = self.deadline(window) else {
+            return false;
+        };
+        let remaining = deadline.saturating_duration_since(now);
+        self.set(window, TimerState::Paused { remaining });
+        true
+    }
+
+    /// Restarts a paused timer with the time it had left. Returns false if `window` had
+    /// no paused timer.
+    pub fn resume(&mut self, window: WindowId, now: Instant) -> bool {
+        let Some(TimerState::Paused { remaining }) = self.state(window) else {
+            return false;
+        };
+        self.schedule(window, now + remaining);
+        true
+    }
+
+    /// Pushes `window`'s deadline back by `by`, paused or not. Returns false if it had no
+    /// timer.
+    pub fn extend(&mut self, window: WindowId, by: Duration) -> bool {
+        let state = match self.state(window) {
+            Some(TimerState::Running { deadline }) => TimerState::Running {
+                deadline: deadline + by,
+            },
+            Some(TimerState::Paused { remaining }) => TimerState::Paused {
+                remaining: remaining + by,
+            },
+            None => return false,
+        };
+        self.set(window, state);
+        true
+    }
+
+    /// Removes and returns every timer whose deadline has passed by `now`, earliest
+    /// deadline first.
+    pub fn expire(&mut self, now: Instant) -> Vec<WindowId> {
+        // The last tick starting at or before `now`
+        let now_tick = now.saturating_duration_since(self.origin).as_nanos() / self.tick.as_nanos();
+        let now_tick = now_tick as u64;
+        if now_tick < self.next_tick {
+            return Vec::new();
+        }
+        let slot_count = self.slots.len() as u64;
+        let turns = (now_tick - self.next_tick + 1).min(slot_count);
+        let mut fired = Vec::new();
+        for tick in self.next_tick..self.next_tick + turns {
+            let slot = (tick % slot_count) as usize;
+            let timers = &self.timers;
+            self.slots[slot].retain(|entry| {
+                let current = timers
+                    .get(&entry.window)
+                    .is_some_and(|t| t.generation == entry.generation);
+                if current && entry.tick <= now_tick {
+                    fired.push(entry.window);
+                    return false;
+                }
+                current
+            });
+        }
+        self.next_tick = now_tick + 1;
+
+        // An entry's tick is the first at or after its deadline, so these are all due
+        let mut fired: Vec<(Instant, WindowId)> = fired
+            .into_iter()
+            .filter_map(|window| Some((self.deadline(window)?, window)))
+            .collect();
+        fired.sort_unstable();
+        for (_, window) in &fired {
+            self.timers.remove(window);
+        }
+        fired.into_iter().map(|(_, window)| window).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{GridEngine, GridLayout, GridPlacementMode, GridPool, Rect};
+
+    const TICK: Duration = Duration::from_millis(100);
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn deadlines_fire_in_order_and_never_early() {
+        let start = Instant::now();
+        let mut wheel = TimerWheel::new(TICK, 8, start);
+        wheel.schedule(1, start + ms(250));
+        wheel.schedule(2, start + ms(120));
+        wheel.schedule(3, start + ms(120) - Duration::from_nanos(1));
+        assert_eq!(
+            wheel.next_deadline(),
+            Some(start + ms(120) - Duration::from_nanos(1))
+        );
+
+        assert!(wheel.expire(start + ms(119)).is_empty());
+        // Both fire at the start of the next tick, the earlier deadline first
+        assert!(wheel.expire(start + ms(199)).is_empty());
+        assert_eq!(wheel.expire(start + ms(200)), [3, 2]);
+        // At most a tick late
+        assert!(wheel.expire(start + ms(299)).is_empty());
+        assert_eq!(wheel.expire(start + ms(300)), [1]);
+        assert!(wheel.is_empty());
+        assert_eq!(wheel.next_deadline(), None);
+    }
+
+    #[test]
+    fn overdue_and_distant_deadlines() {
+        let start = Instant::now();
+        let mut wheel = TimerWheel::new(TICK, 8, start);
+        assert!(wheel.expire(start + ms(500)).is_empty());
+        // Already passed: fires on the next call in a later tick
+        wheel.schedule(1, start + ms(100));
+        // More than one turn of the wheel away: its slot comes round first
+        wheel.schedule(2, start + ms(2000));
+        assert!(wheel.expire(start + ms(599)).is_empty());
+        assert_eq!(wheel.expire(start + ms(600)), [1]);
+        assert!(wheel.expire(start + ms(1300)).is_empty());
+        assert!(wheel.expire(start + ms(1999)).is_empty());
+        assert_eq!(wheel.expire(start + ms(2000)), [2]);
+    }
+
+    #[test]
+    fn paused_timers_keep_their_remaining_time() {
+        let start = Instant::now();
+        let mut wheel = TimerWheel::new(TICK, 8, start);
+        wheel.schedule(1, start + ms(1000));
+        assert!(wheel.pause(1, start + ms(400)));
+        assert_eq!(
+            wheel.state(1),
+            Some(TimerState::Paused { remaining: ms(600) })
+        );
+        assert_eq!(wheel.deadline(1), None);
+        assert!(!wheel.pause(1, start + ms(500)));
+        assert!(wheel.expire(start + ms(5000)).is_empty());
+
+        assert!(wheel.resume(1, start + ms(5000)));
+        assert!(!wheel.resume(1, start + ms(5000)));
+        assert_eq!(wheel.deadline(1), Some(start + ms(5600)));
+        assert!(wheel.expire(start + ms(5599)).is_empty());
+        assert_eq!(wheel.expire(start + ms(5600)), [1]);
+    }
+
+    #[test]
+    fn extending_moves_the_deadline_back() {
+        let start = Instant::now();
+        let mut wheel = TimerWheel::new(TICK, 8, start);
+        wheel.schedule(1, start + ms(300));
+        assert!(wheel.extend(1, ms(500)));
+        assert_eq!(wheel.remaining(1, start + ms(100)), Some(ms(700)));
+        assert!(wheel.expire(start + ms(799)).is_empty());
+        assert_eq!(wheel.expire(start + ms(800)), [1]);
+
+        // Extending a paused timer adds to what it has left
+        wheel.schedule(2, start + ms(1000));
+        wheel.pause(2, start + ms(900));
+        assert!(wheel.extend(2, ms(200)));
+        assert_eq!(wheel.remaining(2, start + ms(5000)), Some(ms(300)));
+        assert!(!wheel.extend(3, ms(200)));
+    }
+
+    #[test]
+    fn rescheduling_invalidates_the_old_slot_entry() {
+        let start = Instant::now();
+        let mut wheel = TimerWheel::new(TICK, 8, start);
+        wheel.schedule(1, start + ms(200));
+        wheel.schedule(1, start + ms(600));
+        // The entry at 200ms is stale and does not fire
+        assert!(wheel.expire(start + ms(500)).is_empty());
+        assert_eq!(wheel.len(), 1);
+        assert_eq!(wheel.expire(start + ms(600)), [1]);
+
+        // Nor does one left behind by a cancel and a later schedule
+        wheel.schedule(2, start + ms(900));
+        assert!(wheel.cancel(2));
+        assert!(!wheel.cancel(2));
+        wheel.schedule(2, start + ms(1700));
+        assert!(wheel.expire(start + ms(1000)).is_empty());
+        assert_eq!(wheel.expire(start + ms(1700)), [2]);
+
+        // Or by a pause, even once the slot comes round again
+        wheel.schedule(3, start + ms(1800));
+        wheel.pause(3, start + ms(1750));
+        assert!(wheel.expire(start + ms(4000)).is_empty());
+        assert_eq!(wheel.len(), 1);
+    }
+
+    #[test]
+    fn closing_a_window_cancels_its_timeout() {
+        let pool = GridPool::single(0, GridLayout::new(1, 2, Rect::new(0, 0, 1000, 500)));
+        let mut engine = GridEngine::new(pool, GridPlacementMode::FirstFree);
+        engine.set_timeout(Some(Duration::from_secs(3)));
+        let start = Instant::now();
+        engine.assign(0, 1, start);
+        engine.assign(1, 2, start + ms(500));
+        assert_eq!(engine.timers().len(), 2);
+
+        assert_eq!(
+            engine.window_closed(1, start + ms(1000)),
+            Some((0, ms(1000)))
+        );
+        assert_eq!(engine.timers().state(1), None);
+        assert_eq!(engine.expire(start + Duration::from_secs(10)), [(1, 2)]);
+        assert!(engine.timers().is_empty());
+
+        // The protected (parent) window never gets one
+        engine.protect(Some(3));
+        engine.assign(0, 3, start);
+        assert!(engine.timers().is_empty());
+    }
+}
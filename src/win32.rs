@@ -0,0 +1,117 @@
+// src/win32.rs
+//! The Windows side of the `startt` binary: console setup, the taskbar options and
+//! ETW process tracking. Launching and gridding go through [`startt::Launcher`], as
+//! on Linux.
+#[cfg(feature = "uses_etw")]
+use ferrisetw::{EventRecord, SchemaLocator, trace::UserTrace};
+use startt::cli::CommandLineOptions;
+#[cfg(feature = "uses_etw")]
+use std::collections::HashSet;
+use std::os::windows::io::AsRawHandle;
+#[cfg(feature = "uses_etw")]
+use std::sync::{Arc, Mutex};
+use winapi::um::consoleapi::{GetConsoleMode, SetConsoleMode};
+use winapi::um::processenv::{GetStdHandle, SetStdHandle};
+use winapi::um::winbase::{STD_ERROR_HANDLE, STD_OUTPUT_HANDLE};
+use winapi::um::wincon::ENABLE_VIRTUAL_TERMINAL_PROCESSING;
+use winapi::um::winnt::HANDLE;
+
+/// Adds the children the root starts, as ETW's kernel process provider reports them,
+/// to `tracked_pids`.
+#[cfg(feature = "uses_etw")]
+pub fn start_etw_process_tracker_with_schema(
+    root_pid: u32,
+    tracked_pids: Arc<Mutex<HashSet<u32>>>,
+) {
+    use ferrisetw::parser::Parser;
+    let process_callback =
+        move |record: &EventRecord, schema_locator: &SchemaLocator| match schema_locator
+            .event_schema(record)
+        {
+            Ok(schema) => {
+                let event_id = record.event_id();
+                let parser = Parser::create(record, &schema);
+                let process_id: u32 = parser.try_parse("ProcessID").unwrap_or(0);
+                let parent_id: u32 = parser.try_parse("ParentID").unwrap_or(0);
+                let image_name: String = parser
+                    .try_parse("ImageName")
+                    .unwrap_or_else(|_| "N/A".to_string());
+
+                // Only print events for the root process or its children
+                if parent_id == root_pid {
+                    if event_id == 1 {
+                        println!(
+                            "Process START: PID={}, PPID={}, ImageName={}",
+                            process_id, parent_id, image_name
+                        );
+                        tracked_pids.lock().unwrap().insert(process_id);
+                    } else if event_id == 2 {
+                        let exit_code: u32 = parser.try_parse("ExitCode").unwrap_or(0);
+                        println!(
+                            "Process EXIT: PID={}, ExitCode={}, ImageName={}",
+                            process_id, exit_code, image_name
+                        );
+                    }
+                }
+            }
+            Err(err) => println!("Error {:?}", err),
+        };
+
+    let process_provider =
+        ferrisetw::provider::Provider::by_guid("22fb2cd6-0e7b-422b-a0c7-2fad1fd0e716") // Microsoft-Windows-Kernel-Process
+            .add_callback(process_callback)
+            .build();
+
+    // Generate a random trace name to avoid "AlreadyExist" error
+    let random_trace_name = format!("MyTrace_{}", rand::random::<u32>());
+    let (_user_trace, handle) = UserTrace::new()
+        .named(random_trace_name)
+        .enable(process_provider)
+        .start()
+        .unwrap();
+
+    std::thread::spawn(move || {
+        let status = <UserTrace as ferrisetw::trace::TraceTrait>::process_from_handle(handle);
+        println!("Trace ended with status {:?}", status);
+    });
+}
+
+pub fn enable_ansi_support() {
+    unsafe {
+        let handle = std::io::stdout().as_raw_handle() as HANDLE;
+        let mut mode = 0;
+        if GetConsoleMode(handle, &mut mode) != 0 {
+            SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING);
+        }
+    }
+}
+
+/// Points stdout at stderr and returns the original stdout handle. Rust looks the
+/// handle up on every write, so this also catches `println!` in the library.
+pub fn divert_stdout_to_stderr() -> HANDLE {
+    unsafe {
+        let stdout = GetStdHandle(STD_OUTPUT_HANDLE);
+        SetStdHandle(STD_OUTPUT_HANDLE, GetStdHandle(STD_ERROR_HANDLE));
+        stdout
+    }
+}
+
+/// Undoes [`divert_stdout_to_stderr`].
+pub fn restore_stdout(stdout: HANDLE) {
+    unsafe { SetStdHandle(STD_OUTPUT_HANDLE, stdout) };
+}
+
+/// `-htb` / `-stb`: hides or shows the taskbar of every monitor with a grid.
+pub fn apply_taskbar_options(options: &CommandLineOptions) {
+    let monitors = options.grid_specs(startt::hwnd::get_monitor_count());
+    for monitor in monitors.iter().map(|grid| grid.monitor) {
+        if options.hide_taskbar {
+            println!("Hiding taskbar on monitor {}", monitor);
+            startt::hwnd::hide_taskbar_on_monitor(monitor);
+        }
+        if options.show_taskbar {
+            println!("Showing taskbar on monitor {}", monitor);
+            startt::hwnd::show_taskbar_on_monitor(monitor);
+        }
+    }
+}
@@ -0,0 +1,265 @@
+// src/window_events.rs
+//! Push-based window discovery.
+//!
+//! A [`WindowEventSource`] delivers [`WindowEvent`]s as windows are created, shown,
+//! renamed, moved and destroyed, so the follow loop can place a new window (or free a
+//! cell) as soon as it happens instead of waiting for its next `EnumWindows` pass.
+//! [`WinEventHookSource`] gets them from `SetWinEventHook`; [`FakeWindowEventSource`]
+//! is fed by hand and works anywhere.
+use crate::grid::WindowId;
+use crossbeam_channel::{Receiver, Sender};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WindowEventKind {
+    Created,
+    Shown,
+    Destroyed,
+    LocationChanged,
+    NameChanged,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowEvent {
+    pub kind: WindowEventKind,
+    pub hwnd: WindowId,
+    /// Owning process; 0 when it can no longer be looked up (destroyed windows).
+    pub pid: u32,
+}
+
+pub trait WindowEventSource {
+    /// The next event, waiting at most `timeout` for one.
+    fn next_event(&mut self, timeout: Duration) -> Option<WindowEvent>;
+
+    /// The next event if one is already queued.
+    fn try_next_event(&mut self) -> Option<WindowEvent> {
+        self.next_event(Duration::ZERO)
+    }
+}
+
+/// An event source whose events are pushed by the caller, from any thread.
+pub struct FakeWindowEventSource {
+    tx: Sender<WindowEvent>,
+    rx: Receiver<WindowEvent>,
+}
+
+impl Default for FakeWindowEventSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FakeWindowEventSource {
+    pub fn new() -> Self {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        FakeWindowEventSource { tx, rx }
+    }
+
+    /// A handle for pushing events from another thread.
+    pub fn sender(&self) -> Sender<WindowEvent> {
+        self.tx.clone()
+    }
+
+    pub fn push(&self, kind: WindowEventKind, hwnd: WindowId, pid: u32) {
+        let _ = self.tx.send(WindowEvent { kind, hwnd, pid });
+    }
+}
+
+impl WindowEventSource for FakeWindowEventSource {
+    fn next_event(&mut self, timeout: Duration) -> Option<WindowEvent> {
+        self.rx.recv_timeout(timeout).ok()
+    }
+}
+
+#[cfg(windows)]
+pub use hook::WinEventHookSource;
+
+#[cfg(windows)]
+mod hook {
+    use super::{WindowEvent, WindowEventKind, WindowEventSource};
+    use crossbeam_channel::{Receiver, Sender};
+    use std::io;
+    use std::os::raw::c_long;
+    use std::sync::Mutex;
+    use std::thread::JoinHandle;
+    use std::time::Duration;
+    use winapi::shared::minwindef::DWORD;
+    use winapi::shared::windef::{HWINEVENTHOOK, HWND};
+    use winapi::um::processthreadsapi::GetCurrentThreadId;
+    use winapi::um::winuser::{
+        CHILDID_SELF, DispatchMessageW, EVENT_OBJECT_CREATE, EVENT_OBJECT_DESTROY,
+        EVENT_OBJECT_LOCATIONCHANGE, EVENT_OBJECT_NAMECHANGE, EVENT_OBJECT_SHOW, GetMessageW,
+        GetParent, GetWindowThreadProcessId, OBJID_WINDOW, PostThreadMessageW, SetWinEventHook,
+        TranslateMessage, UnhookWinEvent, WINEVENT_OUTOFCONTEXT, WINEVENT_SKIPOWNPROCESS, WM_QUIT,
+    };
+
+    /// Every live source; the hook callback has no context pointer to find its own.
+    static SUBSCRIBERS: Mutex<Vec<Sender<WindowEvent>>> = Mutex::new(Vec::new());
+
+    unsafe extern "system" fn win_event_proc(
+        _hook: HWINEVENTHOOK,
+        event: DWORD,
+        hwnd: HWND,
+        id_object: c_long,
+        id_child: c_long,
+        _event_thread: DWORD,
+        _event_time: DWORD,
+    ) {
+        // Only the windows themselves, not their scrollbars, carets or children
+        if hwnd.is_null() || id_object != OBJID_WINDOW || id_child != CHILDID_SELF as c_long {
+            return;
+        }
+        let kind = match event {
+            EVENT_OBJECT_CREATE => WindowEventKind::Created,
+            EVENT_OBJECT_SHOW => WindowEventKind::Shown,
+            EVENT_OBJECT_DESTROY => WindowEventKind::Destroyed,
+            EVENT_OBJECT_LOCATIONCHANGE => WindowEventKind::LocationChanged,
+            EVENT_OBJECT_NAMECHANGE => WindowEventKind::NameChanged,
+            _ => return,
+        };
+        let mut pid = 0;
+        if kind != WindowEventKind::Destroyed {
+            if !unsafe { GetParent(hwnd) }.is_null() {
+                return;
+            }
+            unsafe { GetWindowThreadProcessId(hwnd, &mut pid) };
+        }
+        let event = WindowEvent {
+            kind,
+            hwnd: hwnd as isize,
+            pid,
+        };
+        let mut subscribers = SUBSCRIBERS.lock().unwrap_or_else(|e| e.into_inner());
+        subscribers.retain(|tx| tx.send(event).is_ok());
+    }
+
+    /// Window events from `SetWinEventHook`, delivered out of context on a dedicated
+    /// message-loop thread. The hooks are removed when the source is dropped.
+    pub struct WinEventHookSource {
+        rx: Receiver<WindowEvent>,
+        thread_id: DWORD,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl WinEventHookSource {
+        pub fn start() -> io::Result<Self> {
+            let (tx, rx) = crossbeam_channel::unbounded();
+            SUBSCRIBERS
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(tx);
+
+            let (started_tx, started_rx) = crossbeam_channel::bounded(1);
+            let thread = std::thread::spawn(move || unsafe {
+                let hook = |min, max| {
+                    SetWinEventHook(
+                        min,
+                        max,
+                        std::ptr::null_mut(),
+                        Some(win_event_proc),
+                        0,
+                        0,
+                        WINEVENT_OUTOFCONTEXT | WINEVENT_SKIPOWNPROCESS,
+                    )
+                };
+                // CREATE, DESTROY and SHOW are adjacent, as are LOCATIONCHANGE and NAMECHANGE
+                let hooks = [
+                    hook(EVENT_OBJECT_CREATE, EVENT_OBJECT_SHOW),
+                    hook(EVENT_OBJECT_LOCATIONCHANGE, EVENT_OBJECT_NAMECHANGE),
+                ];
+                if hooks.iter().any(|h| h.is_null()) {
+                    let _ = started_tx.send(Err(io::Error::last_os_error()));
+                } else {
+                    let _ = started_tx.send(Ok(GetCurrentThreadId()));
+                    let mut msg = std::mem::zeroed();
+                    while GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) > 0 {
+                        TranslateMessage(&msg);
+                        DispatchMessageW(&msg);
+                    }
+                }
+                for hook in hooks.into_iter().filter(|h| !h.is_null()) {
+                    UnhookWinEvent(hook);
+                }
+            });
+            let thread_id = started_rx
+                .recv()
+                .map_err(|_| io::Error::other("window event thread exited"))??;
+            Ok(WinEventHookSource {
+                rx,
+                thread_id,
+                thread: Some(thread),
+            })
+        }
+    }
+
+    impl WindowEventSource for WinEventHookSource {
+        fn next_event(&mut self, timeout: Duration) -> Option<WindowEvent> {
+            self.rx.recv_timeout(timeout).ok()
+        }
+    }
+
+    impl Drop for WinEventHookSource {
+        fn drop(&mut self) {
+            unsafe { PostThreadMessageW(self.thread_id, WM_QUIT, 0, 0) };
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::{FakeClock, FakeProcess, FakeProcessTree, FakeWindow, FakeWindowSystem};
+    use crate::follow::{Clock, Follower};
+    use crate::grid::Rect;
+
+    #[test]
+    fn events_place_windows_and_free_cells_between_polls() {
+        let clock = FakeClock::new();
+        let windows = FakeWindowSystem::new(clock.clone(), vec![Rect::new(0, 0, 1000, 500)]);
+        let processes = FakeProcessTree::new(clock.clone());
+        processes.start_at(Duration::ZERO, FakeProcess::new(100, 1, "app"));
+        let options = crate::cli::parse_args(["-f", "-g", "1x2", "app"]).unwrap();
+        let mut follower = Follower::new(&windows, &processes, options);
+        let source = FakeWindowEventSource::new();
+        let events = source.sender();
+        follower.watch(source);
+        follower.track(100);
+        follower.poll(clock.now());
+
+        let bounds = Rect::new(0, 0, 300, 200);
+        let first = windows.create_at(clock.elapsed(), FakeWindow::new(100, "App", "one", bounds));
+        let other = windows.create_at(
+            clock.elapsed(),
+            FakeWindow::new(200, "App", "other", bounds),
+        );
+        for (hwnd, pid) in [(first, 100), (other, 200)] {
+            events
+                .send(WindowEvent {
+                    kind: WindowEventKind::Created,
+                    hwnd,
+                    pid,
+                })
+                .unwrap();
+        }
+        follower.handle_window_events(clock.now());
+        let engine = follower.engine().unwrap();
+        assert_eq!(engine.cell_of(first), Some(0));
+        assert_eq!(engine.cell_of(other), None);
+        assert_eq!(windows.window(first).unwrap().moves, [bounds]);
+
+        windows.destroy_at(clock.elapsed(), first);
+        events
+            .send(WindowEvent {
+                kind: WindowEventKind::Destroyed,
+                hwnd: first,
+                pid: 0,
+            })
+            .unwrap();
+        follower.handle_window_events(clock.now());
+        assert!(follower.engine().unwrap().is_free(0));
+        assert!(follower.windows().is_empty());
+    }
+}
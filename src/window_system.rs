@@ -0,0 +1,823 @@
+// src/window_system.rs
+//! The windowing calls startt makes, behind one trait.
+//!
+//! [`WindowSystem`] covers what launching and gridding needs: listing top-level windows,
+//! who owns them, what they are called, where they are, and moving, restoring,
+//! minimizing, decorating and closing them. [`Win32WindowSystem`] implements it with
+//! user32; [`X11WindowSystem`] talks to an X server, preferring the EWMH requests of a
+//! window manager (`_NET_CLIENT_LIST`, `_NET_WM_PID`, `_NET_MOVERESIZE_WINDOW`,
+//! `_MOTIF_WM_HINTS`) and falling back to plain core requests without one, e.g. under
+//! a bare Xvfb. [`native`] connects to the one for the current platform.
+//!
+//! Windows are identified by [`WindowId`]: an HWND on Windows, an X window ID on X11.
+use crate::grid::{Rect, WindowId};
+use crate::process_tree::ProcessTree;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Duration;
+
+pub trait WindowSystem: Send + Sync {
+    /// Every top-level window, visible or not.
+    fn windows(&self) -> Vec<WindowId>;
+
+    /// Whether the window still exists.
+    fn is_window(&self, window: WindowId) -> bool;
+
+    /// Whether the window is shown (it may still be minimized or covered).
+    fn is_visible(&self, window: WindowId) -> bool;
+
+    /// The process that owns the window, if it can be told.
+    fn pid(&self, window: WindowId) -> Option<u32>;
+
+    /// Window class (the class part of `WM_CLASS` on X11), or an empty string.
+    fn class(&self, window: WindowId) -> String;
+
+    /// Window title, or an empty string if it has none.
+    fn title(&self, window: WindowId) -> String;
+
+    /// Screen rectangle of the window including its frame, or None if it is gone.
+    fn bounds(&self, window: WindowId) -> Option<Rect>;
+
+    /// Moves the window to `bounds`, resizing it only if `resize` is set. Returns false
+    /// if the request could not be made; whether the window actually went there is
+    /// for the caller to check with [`WindowSystem::bounds`].
+    fn set_bounds(&self, window: WindowId, bounds: Rect, resize: bool) -> bool;
+
+    /// Shows the window, bringing it back if it is minimized.
+    fn restore(&self, window: WindowId);
+
+    fn minimize(&self, window: WindowId);
+
+    /// Keeps the window above all others, or lets it be covered again.
+    fn set_topmost(&self, window: WindowId, topmost: bool);
+
+    /// Removes the title bar and/or the border, leaving the rest of the frame alone.
+    fn hide_decorations(&self, window: WindowId, title_bar: bool, border: bool);
+
+    /// Asks the window to close, as its close button would.
+    fn close(&self, window: WindowId);
+
+    /// Wiggles the window for `duration` to draw the eye to it, without waiting for it
+    /// to finish. Not every platform has this; the default does nothing.
+    fn shake(&self, _window: WindowId, _duration: Duration) {}
+
+    /// Brings the window to the front and gives it the keyboard focus.
+    fn focus(&self, window: WindowId);
+
+    /// The window that has the focus, if any.
+    fn foreground(&self) -> Option<WindowId>;
+
+    /// Work area of every monitor, the primary one first. Never empty.
+    fn monitors(&self) -> Vec<Rect>;
+
+    /// Work area of `monitor`, or of the primary monitor if there is no such monitor.
+    fn monitor_rect(&self, monitor: i32) -> Rect {
+        let monitors = self.monitors();
+        usize::try_from(monitor)
+            .ok()
+            .and_then(|idx| monitors.get(idx))
+            .or(monitors.first())
+            .copied()
+            .unwrap_or(FALLBACK_MONITOR)
+    }
+
+    /// What the window is, as of now; None if it is gone. The owning process's `exe`
+    /// and `created_at` are left for [`WindowInfo::with_process`] to fill in.
+    fn info(&self, window: WindowId) -> Option<WindowInfo> {
+        let bounds = self.bounds(window)?;
+        let class = self.class(window);
+        Some(WindowInfo {
+            handle: window,
+            pid: self.pid(window).unwrap_or(0),
+            is_console: is_console_class(&class),
+            class,
+            title: self.title(window),
+            exe: None,
+            bounds,
+            created_at: None,
+            monitor: monitor_of(&self.monitors(), bounds),
+        })
+    }
+
+    /// Visible top-level windows owned by any of `pids`.
+    fn windows_of(&self, pids: &HashSet<u32>) -> Vec<WindowId> {
+        self.windows()
+            .into_iter()
+            .filter(|&w| self.is_visible(w))
+            .filter(|&w| self.pid(w).is_some_and(|pid| pids.contains(&pid)))
+            .collect()
+    }
+}
+
+/// A top-level window, as it was when looked at. Serialized with the handle as
+/// `hwnd`, like the windows in [`Event`](crate::events::Event)s.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowInfo {
+    #[serde(rename = "hwnd")]
+    pub handle: WindowId,
+    /// 0 if the owning process can't be told.
+    pub pid: u32,
+    pub class: String,
+    pub title: String,
+    /// Image name of the owning process, e.g. `cargo-e.exe`, if known.
+    pub exe: Option<String>,
+    pub bounds: Rect,
+    /// When the owning process started, in milliseconds since the Unix epoch, if known.
+    pub created_at: Option<u64>,
+    /// Whether it is a console window rather than a GUI one.
+    pub is_console: bool,
+    /// Index of the monitor the window's center is on, if it is on any.
+    pub monitor: Option<i32>,
+}
+
+impl WindowInfo {
+    /// Fills in `exe` and `created_at` from the owning process's entry in `tree`, if
+    /// it has one.
+    pub fn with_process(mut self, tree: &ProcessTree) -> Self {
+        if let Some(process) = tree.get(self.pid) {
+            self.exe = Some(process.image.clone());
+            self.created_at = process.start_time;
+        }
+        self
+    }
+}
+
+/// Window class of console windows (conhost's, on Windows).
+pub const CONSOLE_WINDOW_CLASS: &str = "ConsoleWindowClass";
+
+pub fn is_console_class(class: &str) -> bool {
+    class == CONSOLE_WINDOW_CLASS
+}
+
+/// Index into `monitors` of the one that holds the center of `bounds`.
+pub fn monitor_of(monitors: &[Rect], bounds: Rect) -> Option<i32> {
+    let (x, y) = bounds.center();
+    monitors
+        .iter()
+        .position(|m| x >= m.left && x < m.right && y >= m.top && y < m.bottom)
+        .map(|idx| idx as i32)
+}
+
+/// Assumed when the platform reports no monitor at all.
+const FALLBACK_MONITOR: Rect = Rect {
+    left: 0,
+    top: 0,
+    right: 1920,
+    bottom: 1080,
+};
+
+#[cfg(windows)]
+pub use win32::Win32WindowSystem;
+
+#[cfg(windows)]
+mod win32 {
+    use super::WindowSystem;
+    use crate::grid::{Rect, WindowId};
+    use std::sync::Mutex;
+    use std::thread::JoinHandle;
+    use std::time::Duration;
+    use winapi::shared::minwindef::LPARAM;
+    use winapi::shared::windef::HWND;
+    use winapi::um::winuser::{
+        EnumWindows, GetForegroundWindow, GetWindowThreadProcessId, HWND_NOTOPMOST, HWND_TOPMOST,
+        IsIconic, IsWindow, IsWindowVisible, PostMessageW, SW_MINIMIZE, SW_RESTORE, SW_SHOW,
+        SWP_NOMOVE, SWP_NOSIZE, SWP_NOZORDER, SetForegroundWindow, SetWindowPos, ShowWindow,
+        WM_CLOSE,
+    };
+
+    /// Threads shaking a window, until [`Win32WindowSystem::wait_for_shakes`].
+    static SHAKES: Mutex<Vec<JoinHandle<()>>> = Mutex::new(Vec::new());
+
+    /// user32: `EnumWindows`, `SetWindowPos`, `ShowWindow`, `WM_CLOSE`.
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct Win32WindowSystem;
+
+    impl Win32WindowSystem {
+        /// Waits for the windows being shaken to settle back where they were, so none is
+        /// left out of its cell when startt exits.
+        pub fn wait_for_shakes() {
+            let shakes = std::mem::take(&mut *SHAKES.lock().unwrap());
+            for shake in shakes {
+                let _ = shake.join();
+            }
+        }
+    }
+
+    impl WindowSystem for Win32WindowSystem {
+        fn windows(&self) -> Vec<WindowId> {
+            unsafe extern "system" fn enum_proc(hwnd: HWND, lparam: LPARAM) -> i32 {
+                let windows = unsafe { &mut *(lparam as *mut Vec<WindowId>) };
+                windows.push(hwnd as WindowId);
+                1
+            }
+            let mut windows = Vec::new();
+            unsafe { EnumWindows(Some(enum_proc), &mut windows as *mut _ as LPARAM) };
+            windows
+        }
+
+        fn is_window(&self, window: WindowId) -> bool {
+            unsafe { IsWindow(window as HWND) != 0 }
+        }
+
+        fn is_visible(&self, window: WindowId) -> bool {
+            unsafe { IsWindowVisible(window as HWND) != 0 }
+        }
+
+        fn pid(&self, window: WindowId) -> Option<u32> {
+            let mut pid = 0;
+            unsafe { GetWindowThreadProcessId(window as HWND, &mut pid) };
+            (pid != 0).then_some(pid)
+        }
+
+        fn class(&self, window: WindowId) -> String {
+            crate::hwnd::get_window_class(window as HWND)
+        }
+
+        fn title(&self, window: WindowId) -> String {
+            crate::hwnd::get_window_title(window as HWND)
+        }
+
+        fn bounds(&self, window: WindowId) -> Option<Rect> {
+            crate::hwnd::get_window_rect(window as HWND)
+        }
+
+        fn set_bounds(&self, window: WindowId, bounds: Rect, resize: bool) -> bool {
+            let flags = if resize {
+                SWP_NOZORDER
+            } else {
+                SWP_NOSIZE | SWP_NOZORDER
+            };
+            let moved = unsafe {
+                SetWindowPos(
+                    window as HWND,
+                    std::ptr::null_mut(),
+                    bounds.left,
+                    bounds.top,
+                    bounds.width(),
+                    bounds.height(),
+                    flags,
+                )
+            };
+            moved != 0
+        }
+
+        fn restore(&self, window: WindowId) {
+            let hwnd = window as HWND;
+            unsafe {
+                if IsIconic(hwnd) != 0 {
+                    ShowWindow(hwnd, SW_RESTORE);
+                } else if IsWindowVisible(hwnd) == 0 {
+                    ShowWindow(hwnd, SW_SHOW);
+                }
+            }
+        }
+
+        fn minimize(&self, window: WindowId) {
+            unsafe { ShowWindow(window as HWND, SW_MINIMIZE) };
+        }
+
+        fn set_topmost(&self, window: WindowId, topmost: bool) {
+            let after = if topmost {
+                HWND_TOPMOST
+            } else {
+                HWND_NOTOPMOST
+            };
+            unsafe { SetWindowPos(window as HWND, after, 0, 0, 0, 0, SWP_NOMOVE | SWP_NOSIZE) };
+        }
+
+        fn hide_decorations(&self, window: WindowId, title_bar: bool, border: bool) {
+            unsafe {
+                if border {
+                    crate::hwnd::hide_window_border(window as HWND);
+                }
+                if title_bar {
+                    crate::hwnd::hide_window_title_bar(window as HWND);
+                }
+            }
+        }
+
+        fn close(&self, window: WindowId) {
+            unsafe { PostMessageW(window as HWND, WM_CLOSE, 0, 0) };
+        }
+
+        /// On a thread of its own; see [`Win32WindowSystem::wait_for_shakes`].
+        fn shake(&self, window: WindowId, duration: Duration) {
+            let duration_ms = duration.as_millis() as u64;
+            let shake = std::thread::spawn(move || unsafe {
+                crate::hwnd::shake_window(window as HWND, 10, duration_ms)
+            });
+            let mut shakes = SHAKES.lock().unwrap();
+            shakes.retain(|shake| !shake.is_finished());
+            shakes.push(shake);
+        }
+
+        fn focus(&self, window: WindowId) {
+            unsafe { SetForegroundWindow(window as HWND) };
+        }
+
+        fn foreground(&self) -> Option<WindowId> {
+            let hwnd = unsafe { GetForegroundWindow() };
+            (!hwnd.is_null()).then_some(hwnd as WindowId)
+        }
+
+        fn monitors(&self) -> Vec<Rect> {
+            (0..crate::hwnd::get_monitor_count())
+                .map(|monitor| {
+                    let rect = crate::hwnd::get_monitor_rect(monitor, false);
+                    Rect::new(rect.left, rect.top, rect.right, rect.bottom)
+                })
+                .collect()
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub use x11::X11WindowSystem;
+
+#[cfg(target_os = "linux")]
+mod x11 {
+    use super::{FALLBACK_MONITOR, WindowSystem};
+    use crate::grid::{Rect, WindowId};
+    use std::io;
+    use x11rb::connection::Connection;
+    use x11rb::protocol::randr::ConnectionExt as _;
+    use x11rb::protocol::xproto::{
+        AtomEnum, ClientMessageEvent, ConfigureWindowAux, ConnectionExt as _, EventMask,
+        InputFocus, MapState, PropMode, StackMode, Window,
+    };
+    use x11rb::rust_connection::RustConnection;
+    use x11rb::wrapper::ConnectionExt as _;
+
+    x11rb::atom_manager! {
+        Atoms: AtomsCookie {
+            UTF8_STRING,
+            WM_CHANGE_STATE,
+            WM_DELETE_WINDOW,
+            WM_PROTOCOLS,
+            _MOTIF_WM_HINTS,
+            _NET_ACTIVE_WINDOW,
+            _NET_CLIENT_LIST,
+            _NET_CLOSE_WINDOW,
+            _NET_FRAME_EXTENTS,
+            _NET_MOVERESIZE_WINDOW,
+            _NET_SUPPORTING_WM_CHECK,
+            _NET_WM_NAME,
+            _NET_WM_PID,
+            _NET_WM_STATE,
+            _NET_WM_STATE_ABOVE,
+            _NET_WM_STATE_HIDDEN,
+            _NET_WM_STATE_MAXIMIZED_HORZ,
+            _NET_WM_STATE_MAXIMIZED_VERT,
+            _NET_WORKAREA,
+        }
+    }
+
+    /// The `CurrentTime` timestamp of requests.
+    const CURRENT_TIME: u32 = 0;
+    /// `_NET_WM_STATE` client message actions.
+    const STATE_REMOVE: u32 = 0;
+    const STATE_ADD: u32 = 1;
+    /// Marks EWMH requests as coming from a pager or tool rather than the application,
+    /// which window managers honour more readily.
+    const SOURCE_PAGER: u32 = 2;
+    /// ICCCM `IconicState`, requested with `WM_CHANGE_STATE` to minimize.
+    const ICONIC_STATE: u32 = 3;
+    /// `_MOTIF_WM_HINTS`: only the decorations field is set.
+    const MWM_HINTS_DECORATIONS: u32 = 1 << 1;
+    const MWM_DECOR_BORDER: u32 = 1 << 1;
+    const MWM_DECOR_RESIZEH: u32 = 1 << 2;
+    const MWM_DECOR_TITLE: u32 = 1 << 3;
+    const MWM_DECOR_MENU: u32 = 1 << 4;
+    const MWM_DECOR_MINIMIZE: u32 = 1 << 5;
+    const MWM_DECOR_MAXIMIZE: u32 = 1 << 6;
+    /// `_NET_MOVERESIZE_WINDOW` flags: north-west gravity, x, y, width and height given.
+    const MOVERESIZE_NORTH_WEST: u32 = 1;
+    const MOVERESIZE_XY: u32 = (1 << 8) | (1 << 9);
+    const MOVERESIZE_SIZE: u32 = (1 << 10) | (1 << 11);
+
+    /// An X11 connection (`$DISPLAY`). Requests go through the window manager when one
+    /// is running, and straight to the windows otherwise.
+    pub struct X11WindowSystem {
+        conn: RustConnection,
+        root: Window,
+        atoms: Atoms,
+        screen_size: (u16, u16),
+    }
+
+    impl std::fmt::Debug for X11WindowSystem {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("X11WindowSystem")
+                .field("root", &self.root)
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl X11WindowSystem {
+        /// Connects to `$DISPLAY`.
+        pub fn connect() -> io::Result<Self> {
+            let (conn, screen) = x11rb::connect(None).map_err(io::Error::other)?;
+            let (root, screen_size) = {
+                let screen = &conn.setup().roots[screen];
+                (
+                    screen.root,
+                    (screen.width_in_pixels, screen.height_in_pixels),
+                )
+            };
+            let atoms = Atoms::new(&conn)
+                .map_err(io::Error::other)?
+                .reply()
+                .map_err(io::Error::other)?;
+            Ok(X11WindowSystem {
+                conn,
+                root,
+                atoms,
+                screen_size,
+            })
+        }
+
+        /// The 32-bit values of a property, empty if it is not set.
+        fn property32(&self, window: Window, property: u32, type_: impl Into<u32>) -> Vec<u32> {
+            self.conn
+                .get_property(false, window, property, type_, 0, u32::MAX / 4)
+                .ok()
+                .and_then(|cookie| cookie.reply().ok())
+                .and_then(|reply| Some(reply.value32()?.collect()))
+                .unwrap_or_default()
+        }
+
+        /// The bytes of a text property, None if it is not set.
+        fn text_property(
+            &self,
+            window: Window,
+            property: impl Into<u32>,
+            type_: impl Into<u32>,
+        ) -> Option<Vec<u8>> {
+            let reply = self
+                .conn
+                .get_property(false, window, property, type_, 0, u32::MAX / 4)
+                .ok()?
+                .reply()
+                .ok()?;
+            (reply.format == 8).then_some(reply.value)
+        }
+
+        /// Whether an EWMH window manager is running (`_NET_SUPPORTING_WM_CHECK`).
+        fn has_window_manager(&self) -> bool {
+            let check = self.property32(
+                self.root,
+                self.atoms._NET_SUPPORTING_WM_CHECK,
+                AtomEnum::WINDOW,
+            );
+            check.first().is_some_and(|&w| {
+                self.conn
+                    .get_window_attributes(w)
+                    .ok()
+                    .is_some_and(|cookie| cookie.reply().is_ok())
+            })
+        }
+
+        /// Sends a client message about `window` to the root window, where the window
+        /// manager picks it up.
+        fn send_to_root(&self, window: Window, type_: u32, data: [u32; 5]) {
+            let event = ClientMessageEvent::new(32, window, type_, data);
+            let _ = self.conn.send_event(
+                false,
+                self.root,
+                EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+                event,
+            );
+        }
+
+        fn change_state(&self, window: Window, action: u32, first: u32, second: u32) {
+            self.send_to_root(
+                window,
+                self.atoms._NET_WM_STATE,
+                [action, first, second, SOURCE_PAGER, 0],
+            );
+        }
+
+        /// Left, right, top and bottom frame widths the window manager adds.
+        fn frame_extents(&self, window: Window) -> [i32; 4] {
+            let extents =
+                self.property32(window, self.atoms._NET_FRAME_EXTENTS, AtomEnum::CARDINAL);
+            match extents[..] {
+                [left, right, top, bottom] => [left, right, top, bottom].map(|e| e as i32),
+                _ => [0; 4],
+            }
+        }
+
+        fn flush(&self) {
+            let _ = self.conn.flush();
+        }
+    }
+
+    fn xid(window: WindowId) -> Window {
+        window as Window
+    }
+
+    impl WindowSystem for X11WindowSystem {
+        /// `_NET_CLIENT_LIST`, or the root window's children without a window manager.
+        fn windows(&self) -> Vec<WindowId> {
+            let clients = self.property32(self.root, self.atoms._NET_CLIENT_LIST, AtomEnum::WINDOW);
+            let windows = if clients.is_empty() {
+                self.conn
+                    .query_tree(self.root)
+                    .ok()
+                    .and_then(|cookie| cookie.reply().ok())
+                    .map(|tree| tree.children)
+                    .unwrap_or_default()
+            } else {
+                clients
+            };
+            windows.into_iter().map(|w| w as WindowId).collect()
+        }
+
+        fn is_window(&self, window: WindowId) -> bool {
+            self.conn
+                .get_window_attributes(xid(window))
+                .ok()
+                .is_some_and(|cookie| cookie.reply().is_ok())
+        }
+
+        fn is_visible(&self, window: WindowId) -> bool {
+            self.conn
+                .get_window_attributes(xid(window))
+                .ok()
+                .and_then(|cookie| cookie.reply().ok())
+                .is_some_and(|attributes| attributes.map_state == MapState::VIEWABLE)
+        }
+
+        /// `_NET_WM_PID`, which most toolkits set.
+        fn pid(&self, window: WindowId) -> Option<u32> {
+            self.property32(xid(window), self.atoms._NET_WM_PID, AtomEnum::CARDINAL)
+                .first()
+                .copied()
+        }
+
+        fn class(&self, window: WindowId) -> String {
+            // "instance\0class\0"
+            let Some(wm_class) =
+                self.text_property(xid(window), AtomEnum::WM_CLASS, AtomEnum::STRING)
+            else {
+                return String::new();
+            };
+            let mut parts = wm_class.split(|&b| b == 0);
+            let instance = parts.next().unwrap_or_default();
+            let class = parts.next().filter(|c| !c.is_empty()).unwrap_or(instance);
+            String::from_utf8_lossy(class).into_owned()
+        }
+
+        /// `_NET_WM_NAME`, or `WM_NAME` if the window has no UTF-8 title.
+        fn title(&self, window: WindowId) -> String {
+            self.text_property(xid(window), self.atoms._NET_WM_NAME, self.atoms.UTF8_STRING)
+                .or_else(|| self.text_property(xid(window), AtomEnum::WM_NAME, AtomEnum::ANY))
+                .map(|title| String::from_utf8_lossy(&title).into_owned())
+                .unwrap_or_default()
+        }
+
+        fn bounds(&self, window: WindowId) -> Option<Rect> {
+            let window = xid(window);
+            let geometry = self.conn.get_geometry(window).ok()?.reply().ok()?;
+            let origin = self
+                .conn
+                .translate_coordinates(window, self.root, 0, 0)
+                .ok()?
+                .reply()
+                .ok()?;
+            let [left, right, top, bottom] = self.frame_extents(window);
+            let (x, y) = (i32::from(origin.dst_x), i32::from(origin.dst_y));
+            Some(Rect::new(
+                x - left,
+                y - top,
+                x + i32::from(geometry.width) + right,
+                y + i32::from(geometry.height) + bottom,
+            ))
+        }
+
+        /// `_NET_MOVERESIZE_WINDOW` with the frame fitted into `bounds`, or a plain
+        /// `ConfigureWindow` without a window manager.
+        fn set_bounds(&self, window: WindowId, bounds: Rect, resize: bool) -> bool {
+            let window = xid(window);
+            let [left, right, top, bottom] = self.frame_extents(window);
+            let width = (bounds.width() - left - right).max(1);
+            let height = (bounds.height() - top - bottom).max(1);
+            let sent = if self.has_window_manager() {
+                let flags = MOVERESIZE_NORTH_WEST
+                    | MOVERESIZE_XY
+                    | if resize { MOVERESIZE_SIZE } else { 0 }
+                    | (SOURCE_PAGER << 12);
+                self.send_to_root(
+                    window,
+                    self.atoms._NET_MOVERESIZE_WINDOW,
+                    [
+                        flags,
+                        bounds.left as u32,
+                        bounds.top as u32,
+                        width as u32,
+                        height as u32,
+                    ],
+                );
+                true
+            } else {
+                let mut aux = ConfigureWindowAux::new().x(bounds.left).y(bounds.top);
+                if resize {
+                    aux = aux.width(width as u32).height(height as u32);
+                }
+                self.conn.configure_window(window, &aux).is_ok()
+            };
+            self.flush();
+            sent
+        }
+
+        /// Maps the window and drops its hidden and maximized states, so the window
+        /// manager lets it be moved.
+        fn restore(&self, window: WindowId) {
+            let window = xid(window);
+            let _ = self.conn.map_window(window);
+            if self.has_window_manager() {
+                self.change_state(window, STATE_REMOVE, self.atoms._NET_WM_STATE_HIDDEN, 0);
+                self.change_state(
+                    window,
+                    STATE_REMOVE,
+                    self.atoms._NET_WM_STATE_MAXIMIZED_VERT,
+                    self.atoms._NET_WM_STATE_MAXIMIZED_HORZ,
+                );
+            }
+            self.flush();
+        }
+
+        /// ICCCM `WM_CHANGE_STATE` to `IconicState`; unmaps the window without a window
+        /// manager.
+        fn minimize(&self, window: WindowId) {
+            let window = xid(window);
+            if self.has_window_manager() {
+                self.send_to_root(
+                    window,
+                    self.atoms.WM_CHANGE_STATE,
+                    [ICONIC_STATE, 0, 0, 0, 0],
+                );
+            } else {
+                let _ = self.conn.unmap_window(window);
+            }
+            self.flush();
+        }
+
+        /// `_NET_WM_STATE_ABOVE`, or raising the window without a window manager.
+        fn set_topmost(&self, window: WindowId, topmost: bool) {
+            let window = xid(window);
+            if self.has_window_manager() {
+                let action = if topmost { STATE_ADD } else { STATE_REMOVE };
+                self.change_state(window, action, self.atoms._NET_WM_STATE_ABOVE, 0);
+            } else if topmost {
+                let aux = ConfigureWindowAux::new().stack_mode(StackMode::ABOVE);
+                let _ = self.conn.configure_window(window, &aux);
+            }
+            self.flush();
+        }
+
+        /// `_MOTIF_WM_HINTS` decorations, which most window managers honour.
+        fn hide_decorations(&self, window: WindowId, title_bar: bool, border: bool) {
+            if !title_bar && !border {
+                return;
+            }
+            let mut decorations = 0;
+            if !border {
+                decorations |= MWM_DECOR_BORDER | MWM_DECOR_RESIZEH;
+            }
+            if !title_bar {
+                decorations |=
+                    MWM_DECOR_TITLE | MWM_DECOR_MENU | MWM_DECOR_MINIMIZE | MWM_DECOR_MAXIMIZE;
+            }
+            let hints = [MWM_HINTS_DECORATIONS, 0, decorations, 0, 0];
+            let _ = self.conn.change_property32(
+                PropMode::REPLACE,
+                xid(window),
+                self.atoms._MOTIF_WM_HINTS,
+                self.atoms._MOTIF_WM_HINTS,
+                &hints,
+            );
+            self.flush();
+        }
+
+        /// `_NET_CLOSE_WINDOW`, or `WM_DELETE_WINDOW` sent to the window itself without
+        /// a window manager.
+        fn close(&self, window: WindowId) {
+            let window = xid(window);
+            if self.has_window_manager() {
+                self.send_to_root(
+                    window,
+                    self.atoms._NET_CLOSE_WINDOW,
+                    [0, SOURCE_PAGER, 0, 0, 0],
+                );
+            } else {
+                let event = ClientMessageEvent::new(
+                    32,
+                    window,
+                    self.atoms.WM_PROTOCOLS,
+                    [self.atoms.WM_DELETE_WINDOW, 0, 0, 0, 0],
+                );
+                let _ = self
+                    .conn
+                    .send_event(false, window, EventMask::NO_EVENT, event);
+            }
+            self.flush();
+        }
+
+        fn focus(&self, window: WindowId) {
+            let window = xid(window);
+            if self.has_window_manager() {
+                self.send_to_root(
+                    window,
+                    self.atoms._NET_ACTIVE_WINDOW,
+                    [SOURCE_PAGER, CURRENT_TIME, 0, 0, 0],
+                );
+            } else {
+                let raise = ConfigureWindowAux::new().stack_mode(StackMode::ABOVE);
+                let _ = self.conn.configure_window(window, &raise);
+                let _ = self
+                    .conn
+                    .set_input_focus(InputFocus::PARENT, window, CURRENT_TIME);
+            }
+            self.flush();
+        }
+
+        /// `_NET_ACTIVE_WINDOW`, or the input focus without a window manager.
+        fn foreground(&self) -> Option<WindowId> {
+            let window = if self.has_window_manager() {
+                let active =
+                    self.property32(self.root, self.atoms._NET_ACTIVE_WINDOW, AtomEnum::WINDOW);
+                active.first().copied()?
+            } else {
+                self.conn.get_input_focus().ok()?.reply().ok()?.focus
+            };
+            // None, or the root itself (`PointerRoot`)
+            (window > 1 && window != self.root).then_some(window as WindowId)
+        }
+
+        /// RandR monitors, each cut down to the desktop's `_NET_WORKAREA`; the whole
+        /// screen if RandR reports none.
+        fn monitors(&self) -> Vec<Rect> {
+            let mut monitors: Vec<(bool, Rect)> = self
+                .conn
+                .randr_get_monitors(self.root, true)
+                .ok()
+                .and_then(|cookie| cookie.reply().ok())
+                .map(|reply| reply.monitors)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|m| {
+                    let (x, y) = (i32::from(m.x), i32::from(m.y));
+                    let rect = Rect::new(x, y, x + i32::from(m.width), y + i32::from(m.height));
+                    (m.primary, rect)
+                })
+                .collect();
+            if monitors.is_empty() {
+                let (width, height) = self.screen_size;
+                let screen = Rect::new(0, 0, i32::from(width), i32::from(height));
+                monitors.push((true, screen));
+            }
+            // Primary first, the rest in their RandR order
+            monitors.sort_by_key(|&(primary, _)| !primary);
+            let work_area =
+                self.property32(self.root, self.atoms._NET_WORKAREA, AtomEnum::CARDINAL);
+            let work_area = match work_area[..] {
+                [x, y, width, height, ..] => {
+                    let (x, y) = (x as i32, y as i32);
+                    Some(Rect::new(x, y, x + width as i32, y + height as i32))
+                }
+                _ => None,
+            };
+            let monitors: Vec<Rect> = monitors
+                .into_iter()
+                .map(|(_, rect)| match work_area {
+                    Some(area) => Rect::new(
+                        rect.left.max(area.left),
+                        rect.top.max(area.top),
+                        rect.right.min(area.right),
+                        rect.bottom.min(area.bottom),
+                    ),
+                    None => rect,
+                })
+                .filter(|rect| rect.width() > 0 && rect.height() > 0)
+                .collect();
+            if monitors.is_empty() {
+                vec![FALLBACK_MONITOR]
+            } else {
+                monitors
+            }
+        }
+    }
+}
+
+/// The window system for the platform startt runs on.
+#[cfg(windows)]
+pub fn native() -> std::io::Result<Win32WindowSystem> {
+    Ok(Win32WindowSystem)
+}
+
+/// The window system for the platform startt runs on: the X server named by
+/// `$DISPLAY`.
+#[cfg(target_os = "linux")]
+pub fn native() -> std::io::Result<X11WindowSystem> {
+    X11WindowSystem::connect()
+}